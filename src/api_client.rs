@@ -1,9 +1,139 @@
+use crate::cassette::{self, Cassette};
+use crate::http_log::HttpLog;
+use crate::journal::{self, Journal};
+use crate::redact;
+use crate::timezone;
 use crate::types;
 use reqwest::StatusCode;
 
+#[derive(Clone)]
 pub struct ApiClient {
 	pub api_root: String,
 	pub auth_token: String,
+
+	/// Create entries for this user instead of for the owner of the API token.
+	pub user_id: Option<u64>,
+
+	/// Record responses to disk, or replay them instead of contacting Paymo.
+	pub cassette: Option<std::sync::Arc<Cassette>>,
+
+	/// Record changes to entries, so that they can be undone.
+	pub journal: Option<std::sync::Arc<Journal>>,
+
+	/// When and how often to retry failed requests.
+	pub retry: RetryPolicy,
+
+	/// Log all requests and responses with their full bodies.
+	pub http_log: Option<std::sync::Arc<HttpLog>>,
+}
+
+impl std::fmt::Debug for ApiClient {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("ApiClient")
+			.field("api_root", &redact::url(&self.api_root))
+			.field("auth_token", &"[redacted]")
+			.field("user_id", &self.user_id)
+			.field("cassette", &self.cassette)
+			.field("journal", &self.journal)
+			.field("retry", &self.retry)
+			.field("http_log", &self.http_log)
+			.finish()
+	}
+}
+
+/// When and how often to retry failed requests.
+///
+/// Requests that fail without a response are retried too, except for POST requests,
+/// because the server may have created the entry before the connection failed.
+/// For the same reason, POST requests are only retried for status code 429 (too many requests),
+/// since a gateway error from a proxy can arrive after Paymo created the entry.
+///
+/// Requests are not retried by default.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicy {
+	/// The maximum number of retries for a single request.
+	pub retries: u32,
+
+	/// The delay before the first retry in milliseconds, doubled for each following retry.
+	pub backoff_ms: u64,
+
+	/// The response status codes to retry.
+	pub status_codes: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			retries: 0,
+			backoff_ms: 500,
+			status_codes: vec![429, 502, 503, 504],
+		}
+	}
+}
+
+impl RetryPolicy {
+	/// Get the delay before a retry, starting at 0 for the first retry.
+	fn delay(&self, retry: u32) -> std::time::Duration {
+		std::time::Duration::from_millis(self.backoff_ms.saturating_mul(1u64.checked_shl(retry).unwrap_or(u64::MAX)))
+	}
+}
+
+/// Changes to an existing time entry.
+#[derive(Debug, Default)]
+pub struct EntryUpdate {
+	pub task_id: Option<u64>,
+	pub date: Option<uurlog::Date>,
+	/// The new duration in seconds.
+	pub duration: Option<u32>,
+	pub end_time: Option<chrono::DateTime<chrono::Utc>>,
+	pub description: Option<String>,
+}
+
+/// A new invoice.
+#[derive(Debug, serde::Serialize)]
+pub struct NewInvoice<'a> {
+	pub client_id: u64,
+	pub title: &'a str,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub currency: Option<&'a str>,
+	pub date: chrono::NaiveDate,
+	pub items: Vec<NewInvoiceItem>,
+}
+
+/// A line of a new invoice.
+#[derive(Debug, serde::Serialize)]
+pub struct NewInvoiceItem {
+	pub item: String,
+	pub description: String,
+	pub price_unit: f64,
+	pub quantity: f64,
+}
+
+/// A new expense.
+#[derive(Debug, serde::Serialize)]
+pub struct NewExpense<'a> {
+	pub client_id: u64,
+	pub project_id: u64,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub user_id: Option<u64>,
+	pub amount: f64,
+	pub date: chrono::NaiveDate,
+	pub notes: &'a str,
+}
+
+/// The result of a health check request.
+#[derive(Debug)]
+pub struct Ping {
+	/// The status code of the response.
+	pub status: StatusCode,
+
+	/// The time until the response headers were received.
+	pub latency: std::time::Duration,
+
+	/// The rate limit headers of the response.
+	pub rate_limit: Vec<(String, String)>,
 }
 
 impl ApiClient {
@@ -21,9 +151,70 @@ impl ApiClient {
 		}
 	}
 
+	/// Get the user to create entries for.
+	///
+	/// This is the user set in `user_id`, or the owner of the API token.
+	pub async fn acting_user(&self) -> Result<types::User, String> {
+		match self.user_id {
+			Some(user_id) => self.get_user(user_id).await,
+			None => self.my_user().await,
+		}
+	}
+
+	pub async fn get_user(&self, user_id: u64) -> Result<types::User, String> {
+		#[derive(serde::Deserialize)]
+		struct Response {
+			users: Vec<types::User>,
+		}
+
+		let mut response : Response = self.get(&format!("users/{}", user_id), "").await?;
+		if response.users.len() != 1 {
+			Err(format!("expected exactly 1 user, got {}", response.users.len()))
+		} else {
+			Ok(response.users.remove(0))
+		}
+	}
+
+	pub async fn get_users(&self) -> Result<Vec<types::User>, String> {
+		#[derive(serde::Deserialize)]
+		struct Response {
+			#[serde(deserialize_with = "skip_invalid")]
+			users: Vec<types::User>,
+		}
+
+		let response : Response = self.get("users", "").await?;
+		Ok(response.users)
+	}
+
+	/// Get the company of the API token, with the workspace settings.
+	pub async fn get_company(&self) -> Result<types::Company, String> {
+		#[derive(serde::Deserialize)]
+		struct Response {
+			company: types::Company,
+		}
+
+		let response : Response = self.get("company", "").await?;
+		Ok(response.company)
+	}
+
+	/// Perform a single cheap request to check if the API is reachable and the token is accepted.
+	pub async fn ping(&self) -> Result<Ping, String> {
+		let start = std::time::Instant::now();
+		let response = self.send(reqwest::Method::GET, "me", None).await
+			.map_err(|e| format!("failed to get me: {}", e))?;
+		let latency = start.elapsed();
+
+		Ok(Ping {
+			status: response.status,
+			latency,
+			rate_limit: response.rate_limit,
+		})
+	}
+
 	pub async fn get_clients(&self) -> Result<Vec<types::Client>, String> {
 		#[derive(serde::Deserialize)]
 		struct Response {
+			#[serde(deserialize_with = "skip_invalid")]
 			clients: Vec<types::Client>,
 		}
 
@@ -41,10 +232,27 @@ impl ApiClient {
 		Ok(response.entries)
 	}
 
-	pub async fn add_entry(&self, task_id: u64, date: uurlog::Date, duration: uurlog::Hours, description: &str) -> Result<(), String> {
+	pub async fn get_entry(&self, entry_id: u64) -> Result<types::TimeEntry, String> {
+		#[derive(serde::Deserialize)]
+		struct Response {
+			entries: Vec<types::TimeEntry>,
+		}
+
+		let mut response : Response = self.get(&format!("entries/{}", entry_id), "").await?;
+		if response.entries.len() != 1 {
+			Err(format!("expected exactly 1 entry, got {}", response.entries.len()))
+		} else {
+			Ok(response.entries.remove(0))
+		}
+	}
+
+	/// Add an entry with a date and a duration in seconds, and return the ID of the new entry.
+	pub async fn add_entry(&self, task_id: u64, date: uurlog::Date, duration: u32, description: &str) -> Result<u64, String> {
 		#[derive(serde::Serialize)]
 		struct NewTimeEntry<'a> {
 			task_id: u64,
+			#[serde(skip_serializing_if = "Option::is_none")]
+			user_id: Option<u64>,
 			date: &'a str,
 			duration: u32,
 			description: &'a str,
@@ -52,21 +260,208 @@ impl ApiClient {
 
 		let new_entry = NewTimeEntry {
 			task_id,
+			user_id: self.user_id,
 			date: &format!("{}", date),
-			duration: duration.total_minutes() * 60,
+			duration,
+			description,
+		};
+
+		self.post_entry(&new_entry).await
+	}
+
+	/// Add an entry with a start and end time, rather than a date and duration.
+	///
+	/// Returns the ID of the new entry.
+	pub async fn add_entry_with_times(
+		&self,
+		task_id: u64,
+		start_time: chrono::DateTime<chrono::Utc>,
+		end_time: chrono::DateTime<chrono::Utc>,
+		description: &str,
+	) -> Result<u64, String> {
+		#[derive(serde::Serialize)]
+		struct NewTimeEntry<'a> {
+			task_id: u64,
+			#[serde(skip_serializing_if = "Option::is_none")]
+			user_id: Option<u64>,
+			start_time: &'a str,
+			end_time: &'a str,
+			description: &'a str,
+		}
+
+		let new_entry = NewTimeEntry {
+			task_id,
+			user_id: self.user_id,
+			start_time: &format_timestamp(start_time),
+			end_time: &format_timestamp(end_time),
+			description,
+		};
+
+		self.post_entry(&new_entry).await
+	}
+
+	/// Start a running timer on a task, and return the ID of the new entry.
+	pub async fn start_timer(&self, task_id: u64, start_time: chrono::DateTime<chrono::Utc>, description: &str) -> Result<u64, String> {
+		#[derive(serde::Serialize)]
+		struct NewTimeEntry<'a> {
+			task_id: u64,
+			#[serde(skip_serializing_if = "Option::is_none")]
+			user_id: Option<u64>,
+			start_time: &'a str,
+			description: &'a str,
+		}
+
+		let new_entry = NewTimeEntry {
+			task_id,
+			user_id: self.user_id,
+			start_time: &format_timestamp(start_time),
 			description,
 		};
 
-		self.post_new("entries", &new_entry).await
+		self.post_entry(&new_entry).await
+	}
+
+	/// Create a draft invoice, and return it.
+	///
+	/// Creating an invoice does not send it to the client.
+	pub async fn create_invoice(&self, invoice: &NewInvoice<'_>) -> Result<types::Invoice, String> {
+		#[derive(serde::Deserialize)]
+		struct Response {
+			invoices: Vec<types::Invoice>,
+		}
+
+		let mut response: Response = self.post_created("invoices", invoice).await?;
+		match response.invoices.len() {
+			1 => Ok(response.invoices.remove(0)),
+			n => Err(format!("expected exactly 1 invoice, got {}", n)),
+		}
+	}
+
+	/// Get all invoices of a client.
+	pub async fn get_client_invoices(&self, client_id: u64) -> Result<Vec<types::Invoice>, String> {
+		#[derive(serde::Deserialize)]
+		struct Response {
+			#[serde(deserialize_with = "skip_invalid")]
+			invoices: Vec<types::Invoice>,
+		}
+
+		let mut builder = FilterBuilder::new();
+		builder.test_equal("client_id", Some(client_id));
+		let response: Response = self.get("invoices", &builder.finish()).await?;
+		Ok(response.invoices)
+	}
+
+	/// Create a new entry, and return the ID of the new entry.
+	async fn post_entry(&self, new_entry: &impl serde::Serialize) -> Result<u64, String> {
+		#[derive(serde::Deserialize)]
+		struct Response {
+			entries: Vec<types::TimeEntry>,
+		}
+
+		let mut response: Response = self.post_created("entries", new_entry).await?;
+		let entry = match response.entries.len() {
+			1 => response.entries.remove(0),
+			n => return Err(format!("expected exactly 1 entry, got {}", n)),
+		};
+		let entry_id = entry.id;
+		self.record(journal::Change::Add { entry_id, entry: Some(entry) });
+		Ok(entry_id)
+	}
+
+	/// Get the expenses matching a filter.
+	pub async fn get_expenses(&self, filter: &ExpenseFilter) -> Result<Vec<types::Expense>, String> {
+		#[derive(serde::Deserialize)]
+		struct Response {
+			#[serde(deserialize_with = "skip_invalid")]
+			expenses: Vec<types::Expense>,
+		}
+
+		let response : Response = self.get("expenses", &filter.build_query()).await?;
+		Ok(response.expenses)
+	}
+
+	/// Add an expense, and return the new expense.
+	pub async fn add_expense(&self, expense: &NewExpense<'_>) -> Result<types::Expense, String> {
+		#[derive(serde::Deserialize)]
+		struct Response {
+			expenses: Vec<types::Expense>,
+		}
+
+		let mut response: Response = self.post_created("expenses", expense).await?;
+		match response.expenses.len() {
+			1 => Ok(response.expenses.remove(0)),
+			n => Err(format!("expected exactly 1 expense, got {}", n)),
+		}
+	}
+
+	pub async fn delete_expense(&self, expense_id: u64) -> Result<(), String> {
+		self.delete("expenses", expense_id).await
+	}
+
+	/// Stop a running timer by setting the end time of the entry.
+	///
+	/// Stopping a timer is not recorded in the journal, undoing the run that started the timer deletes the entry.
+	pub async fn stop_timer(&self, entry_id: u64, end_time: chrono::DateTime<chrono::Utc>) -> Result<(), String> {
+		let update = EntryUpdate {
+			end_time: Some(end_time),
+			..EntryUpdate::default()
+		};
+		self.put_entry_update(entry_id, &update).await
+	}
+
+	/// Change fields of an existing entry.
+	///
+	/// Only the fields that are set in `update` are changed.
+	/// The entry as it was before the change is recorded in the journal.
+	pub async fn update_entry(&self, entry: &types::TimeEntry, update: &EntryUpdate) -> Result<(), String> {
+		self.put_entry_update(entry.id, update).await?;
+		self.record(journal::Change::Update { entry: entry.clone() });
+		Ok(())
+	}
+
+	async fn put_entry_update(&self, entry_id: u64, update: &EntryUpdate) -> Result<(), String> {
+		#[derive(serde::Serialize)]
+		struct Update<'a> {
+			#[serde(skip_serializing_if = "Option::is_none")]
+			task_id: Option<u64>,
+			#[serde(skip_serializing_if = "Option::is_none")]
+			date: Option<String>,
+			#[serde(skip_serializing_if = "Option::is_none")]
+			duration: Option<u32>,
+			#[serde(skip_serializing_if = "Option::is_none")]
+			end_time: Option<String>,
+			#[serde(skip_serializing_if = "Option::is_none")]
+			description: Option<&'a str>,
+		}
+
+		let body = Update {
+			task_id: update.task_id,
+			date: update.date.map(|x| x.to_string()),
+			duration: update.duration,
+			end_time: update.end_time.map(format_timestamp),
+			description: update.description.as_deref(),
+		};
+		self.put("entries", entry_id, &body).await
 	}
 
-	pub async fn delete_entry(&self, entry_id: u64) -> Result<(), String> {
-		self.delete("entries", entry_id).await
+	/// Delete an entry, recording the deleted entry in the journal.
+	pub async fn delete_entry(&self, entry: &types::TimeEntry) -> Result<(), String> {
+		self.delete("entries", entry.id).await?;
+		self.record(journal::Change::Delete { entry: entry.clone() });
+		Ok(())
+	}
+
+	/// Record a change in the journal, if there is one.
+	fn record(&self, change: journal::Change) {
+		if let Some(journal) = &self.journal {
+			journal.record(change);
+		}
 	}
 
 	pub async fn get_projects_filtered(&self, filter: &ProjectsFilter) -> Result<Vec<types::Project>, String> {
 		#[derive(serde::Deserialize)]
 		struct Response {
+			#[serde(deserialize_with = "skip_invalid")]
 			projects: Vec<types::Project>,
 		}
 
@@ -74,7 +469,20 @@ impl ApiClient {
 		Ok(response.projects)
 	}
 
-	#[allow(dead_code)]
+	pub async fn get_project(&self, project_id: u64) -> Result<types::Project, String> {
+		#[derive(serde::Deserialize)]
+		struct Response {
+			projects: Vec<types::Project>,
+		}
+
+		let mut response : Response = self.get(&format!("projects/{}", project_id), "").await?;
+		if response.projects.len() != 1 {
+			Err(format!("expected exactly 1 project, got {}", response.projects.len()))
+		} else {
+			Ok(response.projects.remove(0))
+		}
+	}
+
 	pub async fn get_projects(&self) -> Result<Vec<types::Project>, String> {
 		self.get_projects_filtered(&ProjectsFilter::default()).await
 	}
@@ -82,6 +490,7 @@ impl ApiClient {
 	pub async fn get_tasks(&self) -> Result<Vec<types::Task>, String> {
 		#[derive(serde::Deserialize)]
 		struct Response {
+			#[serde(deserialize_with = "skip_invalid")]
 			tasks: Vec<types::Task>,
 		}
 
@@ -89,52 +498,140 @@ impl ApiClient {
 		Ok(response.tasks)
 	}
 
-	async fn get<T: serde::de::DeserializeOwned>(&self, relative_url: &str, query: &str) -> Result<T, String> {
-		log::debug!("GET {}/{}?{}", self.api_root, relative_url, query);
-		let client = reqwest::Client::new();
-		let response = client.get(&format!("{}/{}?{}", self.api_root, relative_url, query))
-			.basic_auth(&self.auth_token, Some(""))
-			.send()
-			.await
-			.map_err(|e| format!("failed to get {}: error sending request: {}", relative_url, e))?;
+	pub async fn get_task(&self, task_id: u64) -> Result<types::Task, String> {
+		#[derive(serde::Deserialize)]
+		struct Response {
+			tasks: Vec<types::Task>,
+		}
 
-		if response.status() != StatusCode::OK {
-			let status = response.status();
-			let body = response.text().await.unwrap_or_else(|_| String::new());
-			Err(format!("failed to get {}: served responded with status code {:?}: {}", relative_url, status, body))
+		let mut response : Response = self.get(&format!("tasks/{}", task_id), "").await?;
+		if response.tasks.len() != 1 {
+			Err(format!("expected exactly 1 task, got {}", response.tasks.len()))
 		} else {
-			response.json().await.map_err(|e| format!("failed to get {}: error parsing response {}", relative_url, e))
+			Ok(response.tasks.remove(0))
+		}
+	}
+
+	/// Set the users assigned to a task.
+	pub async fn set_task_users(&self, task_id: u64, users: &[u64]) -> Result<(), String> {
+		#[derive(serde::Serialize)]
+		struct TaskUpdate<'a> {
+			users: &'a [u64],
 		}
+
+		self.put("tasks", task_id, &TaskUpdate { users }).await
 	}
 
-	async fn post_new(&self, relative_url: &str, body: &impl serde::Serialize) -> Result<(), String> {
-		log::debug!("POST {}/{}", self.api_root, relative_url);
+	/// Send a request to the API, or replay the response from a recording.
+	///
+	/// Failed requests are retried according to the retry policy.
+	async fn send(&self, method: reqwest::Method, url: &str, body: Option<serde_json::Value>) -> Result<cassette::Response, String> {
+		log::debug!("{} {}/{}", method, redact::url(&self.api_root), url);
+		if let Some(cassette) = self.cassette.as_deref().filter(|x| x.is_replay()) {
+			return cassette.load(method.as_str(), url, body.as_ref());
+		}
+
+		let mut retry = 0;
+		let response = loop {
+			let time = chrono::Utc::now();
+			let start = std::time::Instant::now();
+			let result = self.send_once(&method, url, body.as_ref()).await;
+			if let Some(http_log) = &self.http_log {
+				http_log.record(time, method.as_str(), &format!("{}/{}", self.api_root, url), body.as_ref(), start.elapsed(), &result);
+			}
+			let reason = match &result {
+				Ok(response) if method == reqwest::Method::POST && response.status != StatusCode::TOO_MANY_REQUESTS => break result?,
+				Ok(response) if self.retry.status_codes.contains(&response.status.as_u16()) => format!("status code {:?}", response.status),
+				Err(e) if method != reqwest::Method::POST => e.clone(),
+				_ => break result?,
+			};
+			if retry >= self.retry.retries {
+				break result?;
+			}
+			let delay = self.retry.delay(retry);
+			log::info!("{} {} failed ({}), retrying in {:.1} seconds", method, url, reason, delay.as_secs_f64());
+			tokio::time::sleep(delay).await;
+			retry += 1;
+		};
+
+		if let Some(cassette) = &self.cassette {
+			cassette.save(method.as_str(), url, body.as_ref(), &response)?;
+		}
+		Ok(response)
+	}
+
+	/// Send a request to the API once, without retrying.
+	async fn send_once(&self, method: &reqwest::Method, url: &str, body: Option<&serde_json::Value>) -> Result<cassette::Response, String> {
 		let client = reqwest::Client::new();
-		let response = client.post(&format!("{}/{}", self.api_root, relative_url))
-			.basic_auth(&self.auth_token, Some(""))
-			.json(body)
-			.send()
+		let mut request = client.request(method.clone(), format!("{}/{}", self.api_root, url))
+			.basic_auth(&self.auth_token, Some(""));
+		if let Some(body) = body {
+			request = request.json(body);
+		}
+		let response = request.send()
+			.await
+			.map_err(|e| format!("error sending request: {}", e.without_url()))?;
+
+		let status = response.status();
+		let rate_limit = response.headers()
+			.iter()
+			.filter(|(name, _)| name.as_str().starts_with("x-ratelimit"))
+			.map(|(name, value)| (name.to_string(), value.to_str().unwrap_or("<invalid>").to_string()))
+			.collect();
+		let body_text = response.text()
 			.await
-			.map_err(|e| format!("failed to get {}: error sending request: {}", relative_url, e))?;
+			.map_err(|e| format!("error reading response: {}", e.without_url()))?;
+		Ok(cassette::Response {
+			status,
+			rate_limit,
+			body: body_text,
+		})
+	}
+
+	async fn get<T: serde::de::DeserializeOwned>(&self, relative_url: &str, query: &str) -> Result<T, String> {
+		let url = if query.is_empty() {
+			relative_url.to_owned()
+		} else {
+			format!("{}?{}", relative_url, query)
+		};
+		let response = self.send(reqwest::Method::GET, &url, None).await
+			.map_err(|e| format!("failed to get {}: {}", relative_url, e))?;
+
+		if response.status != StatusCode::OK {
+			Err(format!("failed to get {}: served responded with status code {:?}: {}", relative_url, response.status, response.body))
+		} else {
+			serde_json::from_str(&response.body).map_err(|e| format!("failed to get {}: error parsing response {}", relative_url, e))
+		}
+	}
+
+	async fn post_created<T: serde::de::DeserializeOwned>(&self, relative_url: &str, body: &impl serde::Serialize) -> Result<T, String> {
+		let response = self.send(reqwest::Method::POST, relative_url, Some(to_json(body)?)).await
+			.map_err(|e| format!("failed to post {}: {}", relative_url, e))?;
 
-		if response.status() != StatusCode::CREATED {
-			Err(format!("failed to post {}: served responded with status code {:?}", relative_url, response.status()))
+		if response.status != StatusCode::CREATED {
+			Err(format!("failed to post {}: served responded with status code {:?}", relative_url, response.status))
+		} else {
+			serde_json::from_str(&response.body).map_err(|e| format!("failed to post {}: error parsing response {}", relative_url, e))
+		}
+	}
+
+	async fn put(&self, relative_url: &str, id: u64, body: &impl serde::Serialize) -> Result<(), String> {
+		let response = self.send(reqwest::Method::PUT, &format!("{}/{}", relative_url, id), Some(to_json(body)?)).await
+			.map_err(|e| format!("failed to update {}/{}: {}", relative_url, id, e))?;
+
+		if response.status != StatusCode::OK {
+			Err(format!("failed to update {}/{}: served responded with status code {:?}", relative_url, id, response.status))
 		} else {
 			Ok(())
 		}
 	}
 
 	async fn delete(&self, relative_url: &str, id: u64) -> Result<(), String> {
-		log::debug!("DELETE {}/{}/{}", self.api_root, relative_url, id);
-		let client = reqwest::Client::new();
-		let response = client.delete(&format!("{}/{}/{}", self.api_root, relative_url, id))
-			.basic_auth(&self.auth_token, Some(""))
-			.send()
-			.await
-			.map_err(|e| format!("failed to delete {}/{}: error sending request: {}", relative_url, id, e))?;
+		let response = self.send(reqwest::Method::DELETE, &format!("{}/{}", relative_url, id), None).await
+			.map_err(|e| format!("failed to delete {}/{}: {}", relative_url, id, e))?;
 
-		if response.status() != StatusCode::OK {
-			Err(format!("failed to delete {}/{}: served responded with status code {:?}", relative_url, id, response.status()))
+		if response.status != StatusCode::OK {
+			Err(format!("failed to delete {}/{}: served responded with status code {:?}", relative_url, id, response.status))
 		} else {
 			Ok(())
 		}
@@ -144,14 +641,69 @@ impl ApiClient {
 #[derive(Debug, Default)]
 pub struct ProjectsFilter {
 	pub active: Option<bool>,
+	pub client_id: Option<u64>,
+	pub billable: Option<bool>,
+	pub name_like: Option<String>,
+	pub order_by: Option<OrderBy>,
+	pub page: Option<u64>,
+	pub per_page: Option<u64>,
 }
 
 impl ProjectsFilter {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
 	fn build_query(&self) -> String {
 		let mut builder = FilterBuilder::new();
 		builder.test_equal("active", self.active);
+		builder.test_equal("client_id", self.client_id);
+		builder.test_equal("billable", self.billable);
+		builder.test_like("name", self.name_like.as_ref());
+		builder.order_by(self.order_by.as_ref());
+		builder.page(self.page, self.per_page);
 		builder.finish()
 	}
+
+	pub fn active(mut self, val: bool) -> Self {
+		self.active = Some(val);
+		self
+	}
+
+	pub fn client_id(mut self, val: u64) -> Self {
+		self.client_id = Some(val);
+		self
+	}
+
+	#[allow(dead_code)]
+	pub fn billable(mut self, val: bool) -> Self {
+		self.billable = Some(val);
+		self
+	}
+
+	/// Only match projects with a name matching a pattern.
+	///
+	/// The pattern uses `%` as wildcard.
+	pub fn name_like(mut self, val: impl Into<String>) -> Self {
+		self.name_like = Some(val.into());
+		self
+	}
+
+	#[allow(dead_code)]
+	pub fn order_by(mut self, val: OrderBy) -> Self {
+		self.order_by = Some(val);
+		self
+	}
+
+	/// Only get one page of results.
+	///
+	/// The first page has number 1.
+	#[allow(dead_code)]
+	pub fn page(mut self, page: u64, per_page: u64) -> Self {
+		self.page = Some(page);
+		self.per_page = Some(per_page);
+		self
+	}
 }
 
 #[derive(Debug, Default)]
@@ -161,6 +713,15 @@ pub struct TimeEntryFilter {
 	pub project_id: Option<u64>,
 	pub client_id: Option<u64>,
 	pub period: Option<std::ops::Range<uurlog::Date>>,
+	pub timezone: Option<chrono_tz::Tz>,
+	pub from: Option<uurlog::Date>,
+	pub until: Option<uurlog::Date>,
+	pub billed: Option<bool>,
+	pub description_like: Option<String>,
+	pub exclude_task_ids: Vec<u64>,
+	pub order_by: Option<OrderBy>,
+	pub page: Option<u64>,
+	pub per_page: Option<u64>,
 }
 
 impl TimeEntryFilter {
@@ -174,7 +735,29 @@ impl TimeEntryFilter {
 		builder.test_equal("task_id", self.task_id);
 		builder.test_equal("project_id", self.project_id);
 		builder.test_equal("client_id", self.client_id);
-		builder.test_in("time_interval", self.period.as_ref().map(to_time_interval));
+		let timezone = self.timezone.unwrap_or(chrono_tz::Tz::UTC);
+		builder.test_in("time_interval", self.period.as_ref().map(|x| to_time_interval(x, timezone)));
+		builder.test_equal("billed", self.billed);
+		builder.test_like("description", self.description_like.as_ref());
+		if !self.exclude_task_ids.is_empty() {
+			builder.test_not_in("task_id", Some(&self.exclude_task_ids[..]));
+		}
+
+		// Bulk entries only have a date, timer entries only have a start time.
+		if let Some(from) = self.from {
+			builder.any(|builder| {
+				builder.test_greater_equal("date", Some(from));
+				builder.test_greater_equal("start_time", Some(format_timestamp(timezone::start_of_day(from, timezone))));
+			});
+		}
+		if let Some(until) = self.until {
+			builder.any(|builder| {
+				builder.test_less_equal("date", Some(until));
+				builder.test_less_equal("start_time", Some(format_timestamp(timezone::start_of_day(until.next(), timezone) - chrono::Duration::seconds(1))));
+			});
+		}
+		builder.order_by(self.order_by.as_ref());
+		builder.page(self.page, self.per_page);
 		builder.finish()
 	}
 
@@ -183,19 +766,16 @@ impl TimeEntryFilter {
 		self
 	}
 
-	#[allow(dead_code)]
 	pub fn task_id(mut self, val: u64) -> Self {
 		self.task_id = Some(val);
 		self
 	}
 
-	#[allow(dead_code)]
 	pub fn project_id(mut self, val: u64) -> Self {
 		self.project_id = Some(val);
 		self
 	}
 
-	#[allow(dead_code)]
 	pub fn client_id(mut self, val: u64) -> Self {
 		self.client_id = Some(val);
 		self
@@ -205,45 +785,404 @@ impl TimeEntryFilter {
 		self.period = Some(val);
 		self
 	}
+
+	/// Interpret dates in the filter in the given timezone.
+	///
+	/// If no timezone is set, dates are interpreted as UTC dates.
+	pub fn timezone(mut self, val: chrono_tz::Tz) -> Self {
+		self.timezone = Some(val);
+		self
+	}
+
+	/// Only match entries on or after a date.
+	#[allow(dead_code)]
+	pub fn from(mut self, val: uurlog::Date) -> Self {
+		self.from = Some(val);
+		self
+	}
+
+	/// Only match entries on or before a date.
+	#[allow(dead_code)]
+	pub fn until(mut self, val: uurlog::Date) -> Self {
+		self.until = Some(val);
+		self
+	}
+
+	pub fn billed(mut self, val: bool) -> Self {
+		self.billed = Some(val);
+		self
+	}
+
+	/// Only match entries with a description matching a pattern.
+	///
+	/// The pattern uses `%` as wildcard.
+	#[allow(dead_code)]
+	pub fn description_like(mut self, val: impl Into<String>) -> Self {
+		self.description_like = Some(val.into());
+		self
+	}
+
+	/// Exclude entries for a task.
+	///
+	/// Can be called multiple times to exclude multiple tasks.
+	#[allow(dead_code)]
+	pub fn exclude_task_id(mut self, val: u64) -> Self {
+		self.exclude_task_ids.push(val);
+		self
+	}
+
+	#[allow(dead_code)]
+	pub fn order_by(mut self, val: OrderBy) -> Self {
+		self.order_by = Some(val);
+		self
+	}
+
+	/// Only get one page of results.
+	///
+	/// The first page has number 1.
+	#[allow(dead_code)]
+	pub fn page(mut self, page: u64, per_page: u64) -> Self {
+		self.page = Some(page);
+		self.per_page = Some(per_page);
+		self
+	}
+}
+
+#[derive(Debug, Default)]
+pub struct ExpenseFilter {
+	pub user_id: Option<u64>,
+	pub project_id: Option<u64>,
+	pub from: Option<chrono::NaiveDate>,
+	pub until: Option<chrono::NaiveDate>,
+}
+
+impl ExpenseFilter {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	fn build_query(&self) -> String {
+		let mut builder = FilterBuilder::new();
+		builder.test_equal("user_id", self.user_id);
+		builder.test_equal("project_id", self.project_id);
+		builder.test_greater_equal("date", self.from);
+		builder.test_less_equal("date", self.until);
+		builder.finish()
+	}
+
+	pub fn user_id(mut self, val: u64) -> Self {
+		self.user_id = Some(val);
+		self
+	}
+
+	#[allow(dead_code)]
+	pub fn project_id(mut self, val: u64) -> Self {
+		self.project_id = Some(val);
+		self
+	}
+
+	/// Only match expenses on or after a date.
+	pub fn from(mut self, val: chrono::NaiveDate) -> Self {
+		self.from = Some(val);
+		self
+	}
+
+	/// Only match expenses on or before a date.
+	pub fn until(mut self, val: chrono::NaiveDate) -> Self {
+		self.until = Some(val);
+		self
+	}
+}
+
+/// The order in which to return results.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OrderBy {
+	pub field: String,
+	pub direction: SortDirection,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SortDirection {
+	Ascending,
+	Descending,
+}
+
+impl OrderBy {
+	#[allow(dead_code)]
+	pub fn ascending(field: impl Into<String>) -> Self {
+		Self {
+			field: field.into(),
+			direction: SortDirection::Ascending,
+		}
+	}
+
+	#[allow(dead_code)]
+	pub fn descending(field: impl Into<String>) -> Self {
+		Self {
+			field: field.into(),
+			direction: SortDirection::Descending,
+		}
+	}
 }
 
 struct FilterBuilder {
 	filter: String,
+	separator: &'static str,
+	parameters: Vec<String>,
 }
 
 impl FilterBuilder {
 	fn new() -> Self {
-		Self { filter: String::new() }
+		Self::with_separator(" and ")
+	}
+
+	fn with_separator(separator: &'static str) -> Self {
+		Self {
+			filter: String::new(),
+			separator,
+			parameters: Vec::new(),
+		}
 	}
 
 	fn add_filter(&mut self, filter: std::fmt::Arguments) {
-		if self.filter.is_empty() {
-			self.filter = format!("where={}", filter);
-		} else {
-			self.filter += &format!(" and {}", filter);
+		if !self.filter.is_empty() {
+			self.filter += self.separator;
 		}
+		self.filter += &filter.to_string();
 	}
 
-	fn test_equal(&mut self, key: &str, value: Option<impl std::fmt::Display>) {
+	fn test(&mut self, key: &str, operator: &str, value: Option<impl FilterValue>) {
 		if let Some(value) = value {
-			let value = value.to_string();
-			self.add_filter(format_args!("{}={}", urlencoding::encode(key), urlencoding::encode(&value)));
+			self.add_filter(format_args!("{}{}{}", key, operator, value.to_filter_value()));
 		}
 	}
 
-	fn test_in(&mut self, key: &str, collection: Option<impl std::fmt::Display>) {
-		if let Some(collection) = collection {
-			let collection = collection.to_string();
-			self.add_filter(format_args!("{} in {}", urlencoding::encode(key), urlencoding::encode(&collection)));
+	fn test_equal(&mut self, key: &str, value: Option<impl FilterValue>) {
+		self.test(key, "=", value)
+	}
+
+	#[allow(dead_code)]
+	fn test_not_equal(&mut self, key: &str, value: Option<impl FilterValue>) {
+		self.test(key, "!=", value)
+	}
+
+	fn test_greater_equal(&mut self, key: &str, value: Option<impl FilterValue>) {
+		self.test(key, ">=", value)
+	}
+
+	fn test_less_equal(&mut self, key: &str, value: Option<impl FilterValue>) {
+		self.test(key, "<=", value)
+	}
+
+	fn test_like(&mut self, key: &str, pattern: Option<impl FilterValue>) {
+		self.test(key, " like ", pattern)
+	}
+
+	#[allow(dead_code)]
+	fn test_not_like(&mut self, key: &str, pattern: Option<impl FilterValue>) {
+		self.test(key, " not like ", pattern)
+	}
+
+	fn test_in(&mut self, key: &str, collection: Option<impl FilterValue>) {
+		self.test(key, " in ", collection)
+	}
+
+	fn test_not_in(&mut self, key: &str, collection: Option<impl FilterValue>) {
+		self.test(key, " not in ", collection)
+	}
+
+	/// Add a group of conditions of which at least one must match.
+	fn any(&mut self, build: impl FnOnce(&mut FilterBuilder)) {
+		let mut group = FilterBuilder::with_separator(" or ");
+		build(&mut group);
+		if !group.filter.is_empty() {
+			self.add_filter(format_args!("({})", group.filter));
+		}
+	}
+
+	fn order_by(&mut self, order_by: Option<&OrderBy>) {
+		if let Some(order_by) = order_by {
+			let direction = match order_by.direction {
+				SortDirection::Ascending => "asc",
+				SortDirection::Descending => "desc",
+			};
+			self.parameters.push(format!("order_by={}", urlencoding::encode(&order_by.field)));
+			self.parameters.push(format!("order={}", direction));
 		}
 	}
 
+	fn page(&mut self, page: Option<u64>, per_page: Option<u64>) {
+		if let Some(page) = page {
+			self.parameters.push(format!("page={}", page));
+		}
+		if let Some(per_page) = per_page {
+			self.parameters.push(format!("per_page={}", per_page));
+		}
+	}
 
+	/// Get the URL-encoded query string for the filter.
 	fn finish(self) -> String {
-		self.filter
+		let mut query = Vec::with_capacity(self.parameters.len() + 1);
+		if !self.filter.is_empty() {
+			query.push(format!("where={}", urlencoding::encode(&self.filter)));
+		}
+		query.extend(self.parameters);
+		query.join("&")
+	}
+}
+
+/// A value that can be used in a filter expression.
+trait FilterValue {
+	/// Format the value as it should appear in a filter expression.
+	fn to_filter_value(&self) -> String;
+}
+
+impl FilterValue for u64 {
+	fn to_filter_value(&self) -> String {
+		self.to_string()
 	}
 }
 
-fn to_time_interval(period: &std::ops::Range<uurlog::Date>) -> String {
-	format!("(\"{}T00:00:00Z\", \"{}T00:00:00Z\")", period.start, period.end)
+impl FilterValue for bool {
+	fn to_filter_value(&self) -> String {
+		self.to_string()
+	}
+}
+
+impl FilterValue for str {
+	fn to_filter_value(&self) -> String {
+		quote(self)
+	}
+}
+
+impl FilterValue for String {
+	fn to_filter_value(&self) -> String {
+		quote(self)
+	}
+}
+
+impl<T: FilterValue + ?Sized> FilterValue for &T {
+	fn to_filter_value(&self) -> String {
+		T::to_filter_value(self)
+	}
+}
+
+impl<T: FilterValue> FilterValue for [T] {
+	fn to_filter_value(&self) -> String {
+		let values: Vec<_> = self.iter().map(|x| x.to_filter_value()).collect();
+		format!("({})", values.join(", "))
+	}
+}
+
+impl<T: FilterValue, const N: usize> FilterValue for [T; N] {
+	fn to_filter_value(&self) -> String {
+		self[..].to_filter_value()
+	}
+}
+
+impl FilterValue for uurlog::Date {
+	fn to_filter_value(&self) -> String {
+		quote(&self.to_string())
+	}
+}
+
+impl FilterValue for chrono::NaiveDate {
+	fn to_filter_value(&self) -> String {
+		quote(&self.format("%Y-%m-%d").to_string())
+	}
+}
+
+/// Quote a string for use in a filter expression.
+///
+/// Double quotes and backslashes in the string are escaped with a backslash.
+fn quote(value: &str) -> String {
+	let mut result = String::with_capacity(value.len() + 2);
+	result.push('"');
+	for c in value.chars() {
+		if c == '"' || c == '\\' {
+			result.push('\\');
+		}
+		result.push(c);
+	}
+	result.push('"');
+	result
+}
+
+/// Get the time interval covering a period of local dates in a timezone.
+fn to_time_interval(period: &std::ops::Range<uurlog::Date>, timezone: chrono_tz::Tz) -> [String; 2] {
+	[
+		format_timestamp(timezone::start_of_day(period.start, timezone)),
+		format_timestamp(timezone::start_of_day(period.end, timezone)),
+	]
+}
+
+/// Deserialize a list, skipping items that can not be deserialized with a warning.
+///
+/// This prevents a single unexpected item from making a whole listing unusable.
+fn skip_invalid<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+	D: serde::Deserializer<'de>,
+	T: serde::de::DeserializeOwned,
+{
+	use serde::Deserialize;
+	let values = Vec::<serde_json::Value>::deserialize(deserializer)?;
+	let mut result = Vec::with_capacity(values.len());
+	for value in values {
+		let id = value.get("id").cloned().unwrap_or(serde_json::Value::Null);
+		match serde_json::from_value(value) {
+			Ok(item) => result.push(item),
+			Err(e) => log::warn!("skipping item with ID {} that could not be parsed: {}", id, e),
+		}
+	}
+	Ok(result)
+}
+
+/// Serialize a request body.
+fn to_json(body: &impl serde::Serialize) -> Result<serde_json::Value, String> {
+	serde_json::to_value(body).map_err(|e| format!("failed to serialize request body: {}", e))
+}
+
+/// Format a timestamp for the Paymo API.
+fn format_timestamp(timestamp: chrono::DateTime<chrono::Utc>) -> String {
+	timestamp.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn quote_escapes_quotes_and_backslashes() {
+		assert_eq!(quote(r#"say "hi""#), r#""say \"hi\"""#);
+		assert_eq!(quote(r"C:\temp\"), r#""C:\\temp\\""#);
+		assert_eq!(quote(r#"\""#), r#""\\\"""#);
+	}
+
+	#[test]
+	fn quote_keeps_parentheses_inside_quotes() {
+		assert_eq!(quote("meeting (draft)"), r#""meeting (draft)""#);
+		assert_eq!(quote(") or (1=1"), r#"") or (1=1""#);
+	}
+
+	#[test]
+	fn filter_with_in_and_like() {
+		let mut builder = FilterBuilder::new();
+		builder.test_in("task_id", Some([1u64, 2]));
+		builder.test_like("description", Some(r#"50% "x" (draft)"#));
+		assert_eq!(builder.filter, r#"task_id in (1, 2) and description like "50% \"x\" (draft)""#);
+		assert_eq!(
+			builder.finish(),
+			"where=task_id%20in%20%281%2C%202%29%20and%20description%20like%20%2250%25%20%5C%22x%5C%22%20%28draft%29%22",
+		);
+	}
+
+	#[test]
+	fn time_entry_filter_query() {
+		let query = TimeEntryFilter::new()
+			.user_id(3)
+			.description_like("a \\ b")
+			.exclude_task_id(7)
+			.build_query();
+		assert_eq!(query, "where=user_id%3D3%20and%20description%20like%20%22a%20%5C%5C%20b%22%20and%20task_id%20not%20in%20%287%29");
+	}
 }
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+
+use crate::api_client::{self, ApiClient};
+use crate::locale::Locale;
+use crate::types;
+
+/// Change in logged time for a task or project, in seconds.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TimeChange {
+	pub added: u64,
+	pub deleted: u64,
+}
+
+/// Get the total number of hours logged on the server by all users matching a filter.
+pub async fn logged_hours(api: &ApiClient, filter: &api_client::TimeEntryFilter) -> Result<f64, String> {
+	let entries = api.get_time_entries(filter).await?;
+	let seconds: u64 = entries.iter().map(|x| u64::from(x.total_seconds())).sum();
+	Ok(seconds as f64 / 3600.0)
+}
+
+/// Warn about tasks and projects that will exceed their budget after applying changes.
+///
+/// The changes are indexed by task ID, and `tasks` must contain all tasks with changes.
+pub async fn warn_budget_exceedance(
+	api: &ApiClient,
+	tasks: &BTreeMap<u64, types::Task>,
+	changes: &BTreeMap<u64, TimeChange>,
+	locale: &Locale,
+) -> Result<(), String> {
+	let mut project_changes = BTreeMap::<u64, TimeChange>::new();
+
+	for (task_id, change) in changes {
+		let task = &tasks[task_id];
+		let project_change = project_changes.entry(task.project_id).or_default();
+		project_change.added += change.added;
+		project_change.deleted += change.deleted;
+
+		if let Some(budget) = task.budget_hours {
+			let logged = logged_hours(api, &api_client::TimeEntryFilter::new().task_id(task.id)).await?;
+			let total = hours_after_change(logged, change);
+			if total > budget && change.added > change.deleted {
+				log::warn!("task {} ({}) will be over budget: {} of {} hours", task.id, task.name, locale.number(total, 2), locale.number(budget, 2));
+			}
+		}
+	}
+
+	for (project_id, change) in &project_changes {
+		let project = api.get_project(*project_id).await?;
+		if let Some(budget) = project.budget_hours {
+			let logged = logged_hours(api, &api_client::TimeEntryFilter::new().project_id(project.id)).await?;
+			let total = hours_after_change(logged, change);
+			if total > budget && change.added > change.deleted {
+				log::warn!("project {} ({}) will be over budget: {} of {} hours", project.id, project.name, locale.number(total, 2), locale.number(budget, 2));
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Get the number of logged hours after applying a change.
+fn hours_after_change(logged: f64, change: &TimeChange) -> f64 {
+	logged + (change.added as f64 - change.deleted as f64) / 3600.0
+}
+
+/// Thresholds for reporting logged hours as a percentage of the budget.
+#[derive(Debug, Copy, Clone)]
+pub struct Thresholds {
+	/// Report a warning when logged hours reach this percentage of the budget.
+	pub warn: f64,
+
+	/// Report a failure when logged hours exceed this percentage of the budget.
+	pub fail: f64,
+}
+
+/// Print budgeted and logged hours for tasks and their projects.
+///
+/// Returns `true` if any task or project exceeds the failure threshold.
+pub async fn check_budget(api: &ApiClient, task_ids: impl IntoIterator<Item = u64>, thresholds: Thresholds, locale: &Locale) -> Result<bool, String> {
+	let mut tasks_by_project = BTreeMap::<u64, Vec<types::Task>>::new();
+	for task_id in task_ids {
+		let task = api.get_task(task_id).await?;
+		tasks_by_project.entry(task.project_id).or_default().push(task);
+	}
+
+	let mut failed = false;
+	for (project_id, tasks) in &tasks_by_project {
+		let project = api.get_project(*project_id).await?;
+		let logged = logged_hours(api, &api_client::TimeEntryFilter::new().project_id(project.id)).await?;
+		println!("{} ({}): {}", project.name, project.id, budget_status(logged, project.budget_hours, thresholds, locale, &mut failed));
+
+		for task in tasks {
+			let logged = logged_hours(api, &api_client::TimeEntryFilter::new().task_id(task.id)).await?;
+			println!("  {} ({}): {}", task.name, task.id, budget_status(logged, task.budget_hours, thresholds, locale, &mut failed));
+		}
+	}
+
+	Ok(failed)
+}
+
+/// Format the budget status of a task or project.
+///
+/// Sets `failed` to true if the logged hours exceed the failure threshold.
+fn budget_status(logged: f64, budget: Option<f64>, thresholds: Thresholds, locale: &Locale, failed: &mut bool) -> String {
+	let budget = match budget {
+		Some(x) if x > 0.0 => x,
+		_ => return format!("{} hours, no budget", locale.number(logged, 2)),
+	};
+
+	let percentage = logged / budget * 100.0;
+	let status = if percentage > thresholds.fail {
+		*failed = true;
+		" OVER BUDGET"
+	} else if percentage >= thresholds.warn {
+		" WARNING"
+	} else {
+		""
+	};
+	format!("{} of {} hours ({}%){}", locale.number(logged, 2), locale.number(budget, 2), locale.number(percentage, 0), status)
+}
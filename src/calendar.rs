@@ -0,0 +1,141 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+
+/// Calendar of working days.
+#[derive(Debug, Clone)]
+pub struct WorkingDays {
+	/// Days of the week that are not working days.
+	pub weekend: Vec<Weekday>,
+
+	/// Dates that are not working days.
+	pub holidays: BTreeSet<NaiveDate>,
+
+	/// Public holidays of a country that are not working days.
+	pub holiday_calendar: Option<HolidayCalendar>,
+}
+
+/// A calendar of public holidays for a country.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+pub enum HolidayCalendar {
+	/// Belgium.
+	#[serde(rename = "BE")]
+	Belgium,
+
+	/// Germany, only the holidays observed in all states.
+	#[serde(rename = "DE")]
+	Germany,
+
+	/// The Netherlands.
+	#[serde(rename = "NL")]
+	Netherlands,
+}
+
+impl WorkingDays {
+	/// Check if a date falls in the weekend.
+	pub fn is_weekend(&self, date: NaiveDate) -> bool {
+		self.weekend.contains(&date.weekday())
+	}
+
+	/// Check if a date is a holiday.
+	pub fn is_holiday(&self, date: NaiveDate) -> bool {
+		self.holidays.contains(&date) || self.holiday_calendar.map(|x| x.is_holiday(date)).unwrap_or(false)
+	}
+
+	/// Check if a date is a working day.
+	pub fn is_working_day(&self, date: NaiveDate) -> bool {
+		!self.is_weekend(date) && !self.is_holiday(date)
+	}
+}
+
+/// All days of the week, starting on Monday.
+pub const WEEKDAYS: [Weekday; 7] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+
+/// Get the days of the week that are not working days from a list of working days.
+///
+/// The working days are given as comma separated ISO weekday numbers, with 1 for Monday and 7 for Sunday.
+pub fn weekend_from_working_days(working_days: &str) -> Result<Vec<Weekday>, String> {
+	let mut weekend = WEEKDAYS.to_vec();
+	for day in working_days.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+		let day: u32 = day.parse()
+			.map_err(|_| format!("invalid working day: expected a number from 1 to 7, got {}", day))?;
+		if !(1..=7).contains(&day) {
+			return Err(format!("invalid working day: expected a number from 1 to 7, got {}", day));
+		}
+		weekend.retain(|x| x.number_from_monday() != day);
+	}
+	Ok(weekend)
+}
+
+impl HolidayCalendar {
+	/// Check if a date is a public holiday.
+	pub fn is_holiday(self, date: NaiveDate) -> bool {
+		let year = date.year();
+		let easter = easter_sunday(year);
+		let fixed = |month, day| NaiveDate::from_ymd_opt(year, month, day) == Some(date);
+		let after_easter = |days| easter + Duration::days(days) == date;
+
+		let common = fixed(1, 1) || after_easter(1) || after_easter(39) || after_easter(50) || fixed(12, 25);
+		match self {
+			Self::Belgium => {
+				common
+					|| fixed(5, 1)
+					|| fixed(7, 21)
+					|| fixed(8, 15)
+					|| fixed(11, 1)
+					|| fixed(11, 11)
+			},
+			Self::Germany => {
+				common
+					|| after_easter(-2)
+					|| fixed(5, 1)
+					|| fixed(10, 3)
+					|| fixed(12, 26)
+			},
+			Self::Netherlands => {
+				// King's day moves to the 26th if the 27th is a Sunday.
+				let kings_day = match NaiveDate::from_ymd_opt(year, 4, 27).map(|x| x.weekday()) {
+					Some(Weekday::Sun) => fixed(4, 26),
+					_ => fixed(4, 27),
+				};
+				common
+					|| after_easter(0)
+					|| after_easter(49)
+					|| kings_day
+					|| (year % 5 == 0 && fixed(5, 5))
+					|| fixed(12, 26)
+			},
+		}
+	}
+}
+
+/// Compute the date of Easter Sunday in the Gregorian calendar.
+fn easter_sunday(year: i32) -> NaiveDate {
+	// Anonymous Gregorian algorithm.
+	let a = year % 19;
+	let b = year / 100;
+	let c = year % 100;
+	let d = b / 4;
+	let e = b % 4;
+	let f = (b + 8) / 25;
+	let g = (b - f + 1) / 3;
+	let h = (19 * a + b - d - g + 15) % 30;
+	let i = c / 4;
+	let k = c % 4;
+	let l = (32 + 2 * e + 2 * i - h - k) % 7;
+	let m = (a + 11 * h + 22 * l) / 451;
+	let month = (h + l - 7 * m + 114) / 31;
+	let day = (h + l - 7 * m + 114) % 31 + 1;
+	NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
+}
+
+/// Get all dates in a period.
+pub fn dates(period: &std::ops::Range<uurlog::Date>) -> impl Iterator<Item = NaiveDate> {
+	let end = crate::timezone::to_naive_date(period.end);
+	let mut next = Some(crate::timezone::to_naive_date(period.start));
+	std::iter::from_fn(move || {
+		let date = next.filter(|x| *x < end)?;
+		next = date.succ_opt();
+		Some(date)
+	})
+}
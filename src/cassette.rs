@@ -0,0 +1,177 @@
+//! Recording API responses to disk, and replaying them later instead of contacting Paymo.
+//!
+//! Each response is stored in a separate JSON file, named after the request method, URL and body.
+//! Identical requests are numbered in the order they were made.
+//! Recordings contain the full responses, but not the API token.
+
+use crate::fingerprint;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// The name of the file with information about a recording.
+const INFO_FILE: &str = "recording.json";
+
+/// A directory with recorded responses.
+#[derive(Debug)]
+pub struct Cassette {
+	directory: PathBuf,
+	mode: Mode,
+
+	/// The number of times each request has been made so far.
+	counters: Mutex<BTreeMap<String, usize>>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Mode {
+	Record,
+	Replay,
+}
+
+/// A response from the API or from a recording.
+#[derive(Debug)]
+pub struct Response {
+	pub status: StatusCode,
+
+	/// The rate limit headers of the response.
+	pub rate_limit: Vec<(String, String)>,
+
+	pub body: String,
+}
+
+/// Information about a recording.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Info {
+	/// The local time at which the responses were recorded.
+	recorded_at: chrono::DateTime<chrono::FixedOffset>,
+}
+
+/// The contents of a recording file.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Recording {
+	method: String,
+	url: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	request_body: Option<Value>,
+	status: u16,
+	#[serde(default)]
+	rate_limit: BTreeMap<String, String>,
+
+	/// The response body, as JSON if possible and as string otherwise.
+	response_body: Value,
+}
+
+impl Cassette {
+	/// Record responses to files in a directory.
+	pub fn record(directory: impl Into<PathBuf>) -> Self {
+		Self::new(directory.into(), Mode::Record)
+	}
+
+	/// Replay responses from files in a directory.
+	pub fn replay(directory: impl Into<PathBuf>) -> Self {
+		Self::new(directory.into(), Mode::Replay)
+	}
+
+	fn new(directory: PathBuf, mode: Mode) -> Self {
+		Self {
+			directory,
+			mode,
+			counters: Mutex::new(BTreeMap::new()),
+		}
+	}
+
+	/// Get a cassette for a subdirectory, with separate request counters.
+	pub fn subdirectory(&self, name: &str) -> Self {
+		Self::new(self.directory.join(name), self.mode)
+	}
+
+	/// Check if responses are replayed instead of recorded.
+	pub fn is_replay(&self) -> bool {
+		self.mode == Mode::Replay
+	}
+
+	/// Save the time of the recording.
+	pub fn save_info(&self, recorded_at: chrono::DateTime<chrono::FixedOffset>) -> Result<(), String> {
+		let path = self.directory.join(INFO_FILE);
+		let data = serde_json::to_vec_pretty(&Info { recorded_at }).unwrap();
+		std::fs::create_dir_all(&self.directory)
+			.map_err(|e| format!("failed to create directory {}: {}", self.directory.display(), e))?;
+		std::fs::write(&path, data)
+			.map_err(|e| format!("failed to write {}: {}", path.display(), e))
+	}
+
+	/// Get the time of the recording, if it was saved.
+	pub fn load_info(&self) -> Result<Option<chrono::DateTime<chrono::FixedOffset>>, String> {
+		let path = self.directory.join(INFO_FILE);
+		let data = match std::fs::read(&path) {
+			Ok(data) => data,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+			Err(e) => return Err(format!("failed to read {}: {}", path.display(), e)),
+		};
+		let info: Info = serde_json::from_slice(&data)
+			.map_err(|e| format!("failed to parse {}: {}", path.display(), e))?;
+		Ok(Some(info.recorded_at))
+	}
+
+	/// Get the recorded response for a request.
+	pub fn load(&self, method: &str, url: &str, request_body: Option<&Value>) -> Result<Response, String> {
+		let path = self.next_path(method, url, request_body);
+		let data = std::fs::read(&path)
+			.map_err(|e| format!("no recorded response for {} {}: failed to read {}: {}", method, url, path.display(), e))?;
+		let recording: Recording = serde_json::from_slice(&data)
+			.map_err(|e| format!("failed to parse recorded response {}: {}", path.display(), e))?;
+		let status = StatusCode::from_u16(recording.status)
+			.map_err(|e| format!("invalid status code in recorded response {}: {}", path.display(), e))?;
+		let body = match recording.response_body {
+			Value::String(body) => body,
+			body => body.to_string(),
+		};
+		Ok(Response {
+			status,
+			rate_limit: recording.rate_limit.into_iter().collect(),
+			body,
+		})
+	}
+
+	/// Save the response for a request.
+	pub fn save(&self, method: &str, url: &str, request_body: Option<&Value>, response: &Response) -> Result<(), String> {
+		let path = self.next_path(method, url, request_body);
+		let recording = Recording {
+			method: method.to_owned(),
+			url: url.to_owned(),
+			request_body: request_body.cloned(),
+			status: response.status.as_u16(),
+			rate_limit: response.rate_limit.iter().cloned().collect(),
+			response_body: serde_json::from_str(&response.body).unwrap_or_else(|_| Value::String(response.body.clone())),
+		};
+		let data = serde_json::to_vec_pretty(&recording).unwrap();
+		std::fs::create_dir_all(&self.directory)
+			.map_err(|e| format!("failed to create directory {}: {}", self.directory.display(), e))?;
+		std::fs::write(&path, data)
+			.map_err(|e| format!("failed to write recorded response to {}: {}", path.display(), e))
+	}
+
+	/// Get the path of the file for the next occurrence of a request.
+	fn next_path(&self, method: &str, url: &str, request_body: Option<&Value>) -> PathBuf {
+		let name = file_name(method, url, request_body);
+		let mut counters = self.counters.lock().unwrap();
+		let counter = counters.entry(name.clone()).or_insert(0);
+		*counter += 1;
+		self.directory.join(format!("{}-{}.json", name, counter))
+	}
+}
+
+/// Get the base name of the recording file for a request.
+///
+/// The path of the URL is kept readable, the query string and body are hashed.
+fn file_name(method: &str, url: &str, request_body: Option<&Value>) -> String {
+	let (path, query) = url.split_once('?').unwrap_or((url, ""));
+	let mut name = format!("{}-{}", method, path.replace('/', "_"));
+	if !query.is_empty() || request_body.is_some() {
+		let body = request_body.map(|x| x.to_string()).unwrap_or_default();
+		name += &format!("-{:016x}", fingerprint::fnv1a(&[query.as_bytes(), b"\n", body.as_bytes()]));
+	}
+	name
+}
@@ -0,0 +1,63 @@
+use chrono::NaiveDateTime;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::entry::Entry;
+
+/// Find entries with overlapping clock times.
+///
+/// Entries without clock times are ignored.
+/// Returns pairs of overlapping entries.
+pub fn find_overlaps<'a>(entries: impl IntoIterator<Item = &'a Entry>) -> Vec<(&'a Entry, &'a Entry)> {
+	let mut intervals: Vec<(NaiveDateTime, NaiveDateTime, &Entry)> = entries.into_iter()
+		.filter_map(|entry| {
+			let clock_times = entry.clock_times?;
+			let date = crate::timezone::to_naive_date(entry.date);
+			let start = date.and_time(clock_times.start);
+			let mut end = date.and_time(clock_times.end);
+			if end <= start {
+				end += chrono::Duration::days(1);
+			}
+			Some((start, end, entry))
+		})
+		.collect();
+	intervals.sort_by_key(|&(start, end, _)| (start, end));
+
+	let mut overlaps = Vec::new();
+	for (i, &(_, end, entry)) in intervals.iter().enumerate() {
+		for &(other_start, _, other) in &intervals[i + 1..] {
+			if other_start >= end {
+				break;
+			}
+			overlaps.push((entry, other));
+		}
+	}
+	overlaps
+}
+
+/// Find entries with the same date, tags, description and duration in more than one file.
+///
+/// Entries are given with the file they were read from.
+/// Identical entries within a single file are normal, like two short calls on the same day, so they are not reported.
+/// Returns the groups of identical entries.
+pub fn find_duplicates<'a>(entries: impl IntoIterator<Item = (&'a Path, &'a Entry)>) -> Vec<Vec<(&'a Path, &'a Entry)>> {
+	let mut groups = BTreeMap::<_, Vec<_>>::new();
+	for (file, entry) in entries {
+		let key = (entry.date, &entry.tags, &entry.description, entry.seconds);
+		groups.entry(key).or_default().push((file, entry));
+	}
+	groups.into_values()
+		.filter(|x| x.iter().any(|(file, _)| *file != x[0].0))
+		.collect()
+}
+
+/// Find days with more logged hours than a limit.
+///
+/// Returns the dates and the total logged seconds on those dates.
+pub fn find_long_days<'a>(entries: impl IntoIterator<Item = &'a Entry>, max_seconds: u32) -> Vec<(uurlog::Date, u32)> {
+	let mut per_day = BTreeMap::<uurlog::Date, u32>::new();
+	for entry in entries {
+		*per_day.entry(entry.date).or_default() += entry.seconds;
+	}
+	per_day.into_iter().filter(|&(_, seconds)| seconds > max_seconds).collect()
+}
@@ -0,0 +1,649 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::api_client::RetryPolicy;
+use crate::calendar::{HolidayCalendar, WorkingDays};
+use crate::i18n::Language;
+use crate::import::MidnightPolicy;
+use crate::task_ids::{TagNormalization, TaskIds};
+
+/// The configuration file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+	/// Other configuration files to read task mappings from, relative to this file.
+	///
+	/// Included files can only have `[[Task]]`, `[project]` and `[client]` sections, and further includes.
+	#[serde(default)]
+	pub include: Vec<PathBuf>,
+
+	/// General settings.
+	#[serde(default)]
+	pub general: GeneralConfig,
+
+	/// The tasks to map entries to.
+	#[serde(rename = "Task")]
+	#[serde(default)]
+	pub tasks: Vec<TaskConfig>,
+
+	/// Project-scoped task mappings, indexed by project tag.
+	#[serde(default)]
+	pub project: BTreeMap<String, ProjectConfig>,
+
+	/// Settings for clients, indexed by Paymo client ID.
+	#[serde(default)]
+	pub client: BTreeMap<String, ClientConfig>,
+
+	/// The column mapping for reading entries from CSV files.
+	#[serde(default)]
+	pub csv: CsvConfig,
+
+	/// Users to synchronize hour logs for with `--sync-team`.
+	#[serde(rename = "user")]
+	#[serde(default)]
+	pub users: Vec<UserConfig>,
+
+	/// Paymo accounts to synchronize to with `--profile`, indexed by profile name.
+	#[serde(default)]
+	pub profile: BTreeMap<String, ProfileConfig>,
+
+	/// Settings for requests to the Paymo API.
+	#[serde(default)]
+	pub http: HttpConfig,
+}
+
+/// General settings in the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeneralConfig {
+	/// Days of the week that are not working days.
+	///
+	/// Defaults to the working days of the Paymo company, or Saturday and Sunday if those are not available.
+	pub weekend: Option<Vec<chrono::Weekday>>,
+
+	/// Dates that are not working days.
+	#[serde(default)]
+	pub holidays: BTreeSet<chrono::NaiveDate>,
+
+	/// The country whose public holidays are not working days.
+	pub holiday_calendar: Option<HolidayCalendar>,
+
+	/// Refuse to change entries older than this many days.
+	pub max_entry_age_days: Option<u32>,
+
+	/// The expected number of working hours per day, overriding the value from Paymo.
+	pub workday_hours: Option<f64>,
+
+	/// Warn about days with more hours than this factor times the expected working hours.
+	#[serde(default = "default_max_workday_factor")]
+	pub max_workday_factor: f64,
+
+	/// The period to use when `--period` is not given, like `this-month` or `2024-03`.
+	pub default_period: Option<String>,
+
+	/// Glob patterns for the hour logs to synchronize when `--sync` is given without files.
+	#[serde(default)]
+	pub hours: Vec<String>,
+
+	/// Append a fingerprint of the local entry to the description of uploaded entries.
+	///
+	/// Entries with a fingerprint still match their local entry when the description is edited on Paymo.
+	#[serde(default)]
+	pub fingerprints: bool,
+
+	/// The language of messages, `en` or `nl`.
+	///
+	/// Defaults to the language of the system locale.
+	pub language: Option<Language>,
+
+	/// What to do with entries that cross midnight: `split` them or keep them on the `start` date.
+	#[serde(default)]
+	pub midnight: MidnightPolicy,
+}
+
+impl Default for GeneralConfig {
+	fn default() -> Self {
+		Self {
+			weekend: None,
+			holidays: BTreeSet::new(),
+			holiday_calendar: None,
+			max_entry_age_days: None,
+			workday_hours: None,
+			max_workday_factor: default_max_workday_factor(),
+			default_period: None,
+			hours: Vec::new(),
+			fingerprints: false,
+			language: None,
+			midnight: MidnightPolicy::default(),
+		}
+	}
+}
+
+fn default_max_workday_factor() -> f64 {
+	1.5
+}
+
+/// A task mapping in the configuration file.
+///
+/// A task is mapped either from a single tag (`name`),
+/// or from a combination of tags that must all be present on an entry (`tags`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskConfig {
+	/// The tag that maps to the task.
+	pub name: Option<String>,
+
+	/// A combination of tags that maps to the task.
+	#[serde(default)]
+	pub tags: Vec<String>,
+
+	/// The Paymo ID of the task.
+	pub id: u64,
+}
+
+/// A project in the configuration file.
+///
+/// Entries tagged with both the project tag and a task tag are mapped to that task.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+	/// The Paymo ID of the project.
+	///
+	/// Needed to fall back to the default task of the client of the project.
+	pub id: Option<u64>,
+
+	/// The tasks of the project, indexed by task tag.
+	#[serde(default)]
+	pub task: BTreeMap<String, ProjectTaskConfig>,
+}
+
+/// A task within a project in the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectTaskConfig {
+	/// The Paymo ID of the task.
+	pub id: u64,
+}
+
+/// Settings for a client in the configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClientConfig {
+	/// The task to use for entries with a project tag of this client that do not map to a specific task.
+	pub default_task: Option<u64>,
+}
+
+/// A Paymo account with its own API token and task mappings.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+	/// The file with the API token, relative to the configuration file.
+	pub token: PathBuf,
+
+	/// The root URL of the Paymo API for this account.
+	pub api_root: Option<String>,
+
+	/// The tasks to map entries to in this account.
+	#[serde(rename = "Task")]
+	#[serde(default)]
+	pub tasks: Vec<TaskConfig>,
+
+	/// Project-scoped task mappings in this account, indexed by project tag.
+	#[serde(default)]
+	pub project: BTreeMap<String, ProjectConfig>,
+
+	/// Settings for clients in this account, indexed by Paymo client ID.
+	#[serde(default)]
+	pub client: BTreeMap<String, ClientConfig>,
+}
+
+/// Settings for requests to the Paymo API.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+	/// When and how often to retry failed requests.
+	#[serde(default)]
+	pub retry: RetryPolicy,
+}
+
+/// A user to synchronize hour logs for.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserConfig {
+	/// The email address of the Paymo user.
+	pub email: String,
+
+	/// A glob pattern matching the hour logs of the user.
+	///
+	/// Relative patterns are resolved against the directory of the configuration file when it is read.
+	pub hours: String,
+}
+
+impl UserConfig {
+	/// Get the paths of all hour logs of the user, in sorted order.
+	pub fn hour_logs(&self) -> Result<Vec<PathBuf>, String> {
+		expand_glob(&self.hours)
+	}
+}
+
+/// The column mapping for reading entries from CSV files.
+///
+/// Columns are identified by the names in the header row.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+pub struct CsvConfig {
+	/// The field delimiter.
+	pub delimiter: char,
+
+	/// The column with the date of an entry.
+	pub date: String,
+
+	/// The format of the dates, using `strftime` syntax.
+	pub date_format: String,
+
+	/// The column with the duration, as decimal hours or as `H:MM`.
+	///
+	/// May be omitted if start and end columns are given.
+	pub duration: Option<String>,
+
+	/// The column with the start time (`HH:MM`) of an entry.
+	pub start: Option<String>,
+
+	/// The column with the end time (`HH:MM`) of an entry.
+	pub end: Option<String>,
+
+	/// The column with the description of an entry.
+	pub description: String,
+
+	/// The column with the tags of an entry.
+	pub tags: String,
+
+	/// The separator between tags in the tags column.
+	pub tag_separator: String,
+}
+
+impl Default for CsvConfig {
+	fn default() -> Self {
+		Self {
+			delimiter: ',',
+			date: String::from("date"),
+			date_format: String::from("%Y-%m-%d"),
+			duration: Some(String::from("duration")),
+			start: None,
+			end: None,
+			description: String::from("description"),
+			tags: String::from("tags"),
+			tag_separator: String::from(" "),
+		}
+	}
+}
+
+impl TaskConfig {
+	/// Check if two task mappings are for the same tag or tag combination.
+	fn same_tags(&self, other: &Self) -> bool {
+		let mut a = self.tags.clone();
+		let mut b = other.tags.clone();
+		a.sort();
+		b.sort();
+		self.name == other.name && a == b
+	}
+}
+
+/// Get the paths matching a glob pattern, in sorted order.
+///
+/// A leading `~/` is replaced by the home directory.
+pub fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>, String> {
+	let pattern = match pattern.strip_prefix("~/") {
+		Some(rest) => {
+			let home = std::env::var("HOME").map_err(|_| format!("can not expand {}: HOME is not set", pattern))?;
+			format!("{}/{}", home.trim_end_matches('/'), rest)
+		},
+		None => pattern.to_owned(),
+	};
+	let paths = glob::glob(&pattern).map_err(|e| e.to_string())?;
+	let mut paths = paths.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+	paths.sort();
+	Ok(paths)
+}
+
+/// Check that an included file only has the sections that are used from included files.
+///
+/// Other settings would be silently ignored, so they are refused instead.
+fn check_included_sections(value: &toml::Value) -> Result<(), String> {
+	const ALLOWED: &[&str] = &["include", "Task", "project", "client"];
+	let other: Vec<&str> = value.as_table()
+		.into_iter()
+		.flat_map(|x| x.keys())
+		.map(String::as_str)
+		.filter(|x| !ALLOWED.contains(x))
+		.collect();
+	if other.is_empty() {
+		Ok(())
+	} else {
+		Err(format!("included files can only have [[Task]], [project] and [client] sections, found: {}", other.join(", ")))
+	}
+}
+
+/// Resolve a relative glob pattern against a directory.
+///
+/// Absolute patterns and patterns starting with `~/` are returned unchanged.
+fn resolve_pattern(dir: &Path, pattern: &str) -> String {
+	if dir.as_os_str().is_empty() || pattern.starts_with("~/") || Path::new(pattern).is_absolute() {
+		return pattern.to_owned();
+	}
+	let dir = glob::Pattern::escape(&dir.to_string_lossy());
+	Path::new(&dir).join(pattern).to_string_lossy().into_owned()
+}
+
+/// Suggest a tag for a task or project name.
+///
+/// The name is converted to lowercase, with runs of other characters than letters and digits replaced by a dash.
+pub fn suggest_tag(name: &str) -> String {
+	let mut tag = String::with_capacity(name.len());
+	for c in name.chars() {
+		if c.is_alphanumeric() {
+			tag.extend(c.to_lowercase());
+		} else if !tag.is_empty() && !tag.ends_with('-') {
+			tag.push('-');
+		}
+	}
+	if tag.ends_with('-') {
+		tag.pop();
+	}
+	tag
+}
+
+/// Suggest a tag for a task that is not in `used` yet, and add it to `used`.
+///
+/// If the tag for the task name is already used, it is prefixed with the tag for the project name,
+/// and if that is also used, a number is appended.
+/// Tasks without letters or digits in their name get the tag `task-ID`.
+pub fn suggest_task_tag(project_name: &str, task_name: &str, task_id: u64, used: &mut BTreeSet<String>) -> String {
+	let mut tag = suggest_tag(task_name);
+	if tag.is_empty() {
+		tag = format!("task-{}", task_id);
+	}
+	let project_tag = suggest_tag(project_name);
+	if used.contains(&tag) && !project_tag.is_empty() {
+		tag = format!("{}-{}", project_tag, tag);
+	}
+	if used.contains(&tag) {
+		tag = (2..)
+			.map(|i| format!("{}-{}", tag, i))
+			.find(|x| !used.contains(x))
+			.unwrap();
+	}
+	used.insert(tag.clone());
+	tag
+}
+
+/// Get the path of the file with configuration overrides for an hours file.
+///
+/// The overrides for `FILE` are read from `FILE.toml`.
+pub fn overrides_path(hours_file: &Path) -> PathBuf {
+	let mut path = hours_file.as_os_str().to_owned();
+	path.push(".toml");
+	path.into()
+}
+
+/// Check that task mappings have valid IDs and non-empty tags.
+fn validate_task_mappings(
+	tasks: &[TaskConfig],
+	projects: &BTreeMap<String, ProjectConfig>,
+	clients: &BTreeMap<String, ClientConfig>,
+) -> Result<(), String> {
+	for task in tasks {
+		let tags = task.name.iter().chain(&task.tags);
+		if task.id == 0 {
+			return Err(format!("task mapping for {} has ID 0, expected a positive task ID", tags.cloned().collect::<Vec<_>>().join(", ")));
+		}
+		if tags.clone().any(|x| x.trim().is_empty()) {
+			return Err(format!("task with ID {} has an empty name or tag", task.id));
+		}
+	}
+	for (project_tag, project) in projects {
+		if project_tag.trim().is_empty() {
+			return Err("project with an empty tag".into());
+		}
+		if project.id == Some(0) {
+			return Err(format!("project {} has ID 0, expected a positive project ID", project_tag));
+		}
+		for (task_tag, task) in &project.task {
+			if task_tag.trim().is_empty() {
+				return Err(format!("task with an empty tag in project {}", project_tag));
+			}
+			if task.id == 0 {
+				return Err(format!("task {} in project {} has ID 0, expected a positive task ID", task_tag, project_tag));
+			}
+		}
+	}
+	for (client_id, client) in clients {
+		match client_id.parse::<u64>() {
+			Ok(0) | Err(_) => return Err(format!("invalid client ID: expected a positive number, got {}", client_id)),
+			Ok(_) => (),
+		}
+		if client.default_task == Some(0) {
+			return Err(format!("client {} has default task 0, expected a positive task ID", client_id));
+		}
+	}
+	Ok(())
+}
+
+/// Expand environment variables in all strings in a TOML value.
+fn expand_env_vars(value: &mut toml::Value) -> Result<(), String> {
+	match value {
+		toml::Value::String(x) => *x = expand_env_string(x)?,
+		toml::Value::Array(x) => x.iter_mut().try_for_each(expand_env_vars)?,
+		toml::Value::Table(x) => x.iter_mut().try_for_each(|(_, x)| expand_env_vars(x))?,
+		_ => (),
+	}
+	Ok(())
+}
+
+/// Expand references to environment variables (`${NAME}`) in a string.
+fn expand_env_string(input: &str) -> Result<String, String> {
+	let mut result = String::with_capacity(input.len());
+	let mut rest = input;
+	while let Some(i) = rest.find('$') {
+		result.push_str(&rest[..i]);
+		rest = &rest[i + 1..];
+		if let Some(tail) = rest.strip_prefix('$') {
+			result.push('$');
+			rest = tail;
+		} else if let Some(tail) = rest.strip_prefix('{') {
+			let end = tail.find('}')
+				.ok_or_else(|| format!("unterminated environment variable reference in {:?}", input))?;
+			let name = &tail[..end];
+			let value = std::env::var(name)
+				.map_err(|e| format!("failed to expand ${{{}}}: {}", name, e))?;
+			result.push_str(&value);
+			rest = &tail[end + 1..];
+		} else {
+			result.push('$');
+		}
+	}
+	result.push_str(rest);
+	Ok(result)
+}
+
+impl Config {
+	/// Read the configuration from a TOML file.
+	///
+	/// References to environment variables in string values (`${NAME}`) are replaced by the value of the variable.
+	/// Use `$$` for a literal `$`.
+	///
+	/// Task mappings from included files are added to the mappings of the file itself.
+	pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+		Self::from_file_with_includes(path.as_ref(), &mut Vec::new())
+	}
+
+	/// Read the configuration from a TOML file, with `parents` being the files that include it.
+	fn from_file_with_includes(path: &Path, parents: &mut Vec<PathBuf>) -> Result<Self, String> {
+		let data = std::fs::read_to_string(path)
+			.map_err(|e| format!("{}", e))?;
+		let mut value: toml::Value = toml::from_str(&data)
+			.map_err(|e| format!("{}", e))?;
+		expand_env_vars(&mut value)?;
+		if !parents.is_empty() {
+			check_included_sections(&value)?;
+		}
+		let mut config: Self = match value.try_into() {
+			Ok(x) => x,
+			// Errors from the expanded value do not know their location, so try the raw file for a better error message.
+			Err(e) => return Err(toml::from_str::<Self>(&data).err().unwrap_or(e).to_string()),
+		};
+
+		config.validate()?;
+
+		let dir = path.parent().unwrap_or_else(|| Path::new(""));
+		for user in &mut config.users {
+			user.hours = resolve_pattern(dir, &user.hours);
+		}
+
+		if config.include.is_empty() {
+			return Ok(config);
+		}
+
+		let canonical = path.canonicalize().map_err(|e| format!("{}", e))?;
+		if parents.contains(&canonical) {
+			return Err(format!("{} includes itself", path.display()));
+		}
+		parents.push(canonical);
+
+		for include in std::mem::take(&mut config.include) {
+			let include = dir.join(include);
+			let included = Self::from_file_with_includes(&include, parents)
+				.map_err(|e| format!("failed to read included file {}: {}", include.display(), e))?;
+			config.add_included(included);
+		}
+
+		parents.pop();
+		Ok(config)
+	}
+
+	/// Check the configuration for values that can be parsed, but are not valid.
+	fn validate(&self) -> Result<(), String> {
+		validate_task_mappings(&self.tasks, &self.project, &self.client)?;
+		for (name, profile) in &self.profile {
+			validate_task_mappings(&profile.tasks, &profile.project, &profile.client)
+				.map_err(|e| format!("in profile {}: {}", name, e))?;
+			if profile.token.as_os_str().is_empty() {
+				return Err(format!("in profile {}: the token file must not be empty", name));
+			}
+		}
+		for user in &self.users {
+			if user.email.is_empty() {
+				return Err("user with an empty email address".into());
+			}
+			if user.hours.is_empty() {
+				return Err(format!("user {} has an empty hours pattern", user.email));
+			}
+		}
+		if let Some(period) = &self.general.default_period {
+			crate::partial_date::PartialDate::validate(period)
+				.map_err(|e| format!("invalid default_period {:?}: {}", period, e))?;
+		}
+		if self.general.max_workday_factor <= 0.0 {
+			return Err(format!("max_workday_factor must be positive, got {}", self.general.max_workday_factor));
+		}
+		Ok(())
+	}
+
+	/// Add the task mappings of an included configuration file.
+	///
+	/// Project and client settings that are already present take precedence.
+	fn add_included(&mut self, included: Config) {
+		self.tasks.extend(included.tasks);
+		for (project_tag, project) in included.project {
+			let merged = self.project.entry(project_tag).or_default();
+			merged.id = merged.id.or(project.id);
+			for (task_tag, task) in project.task {
+				merged.task.entry(task_tag).or_insert(task);
+			}
+		}
+		for (client_id, client) in included.client {
+			self.client.entry(client_id).or_insert(client);
+		}
+	}
+
+	/// Apply overrides from another configuration.
+	///
+	/// Task mappings in `overrides` replace mappings for the same tag or tag combination.
+	pub fn with_overrides(mut self, overrides: Config) -> Self {
+		for task in overrides.tasks {
+			self.tasks.retain(|x| !x.same_tags(&task));
+			self.tasks.push(task);
+		}
+		for (project_tag, project) in overrides.project {
+			let merged = self.project.entry(project_tag).or_default();
+			merged.id = project.id.or(merged.id);
+			merged.task.extend(project.task);
+		}
+		self.client.extend(overrides.client);
+		self
+	}
+
+	/// Get the configuration for a profile.
+	///
+	/// The task mappings of the profile replace the top-level task mappings.
+	/// Other settings are shared by all profiles.
+	pub fn for_profile(&self, name: &str) -> Result<(Self, &ProfileConfig), String> {
+		let profile = self.profile.get(name)
+			.ok_or_else(|| format!("no profile named {} in the configuration file", name))?;
+		let config = Self {
+			tasks: profile.tasks.clone(),
+			project: profile.project.clone(),
+			client: profile.client.clone(),
+			..self.clone()
+		};
+		Ok((config, profile))
+	}
+
+	/// Get the calendar of working days.
+	///
+	/// The `default_weekend` is used if the configuration does not specify the weekend.
+	pub fn working_days(&self, default_weekend: Vec<chrono::Weekday>) -> WorkingDays {
+		WorkingDays {
+			weekend: self.general.weekend.clone().unwrap_or(default_weekend),
+			holidays: self.general.holidays.clone(),
+			holiday_calendar: self.general.holiday_calendar,
+		}
+	}
+
+	/// Build the mapping from tags to task IDs.
+	pub fn task_ids(&self, normalization: TagNormalization) -> Result<TaskIds, String> {
+		let mut task_ids = TaskIds::new(normalization);
+		for task in &self.tasks {
+			match (&task.name, task.tags.is_empty()) {
+				(Some(name), true) => task_ids.insert_tag(name, task.id)?,
+				(None, false) => task_ids.insert_combination(&task.tags, task.id)?,
+				(Some(name), false) => return Err(format!("task {} has both a name and tags, expected only one of them", name)),
+				(None, true) => return Err(format!("task with ID {} needs either a name or tags", task.id)),
+			}
+		}
+		for (project_tag, project) in &self.project {
+			if let Some(project_id) = project.id {
+				task_ids.insert_project(project_tag, project_id)?;
+			}
+			for (task_tag, task) in &project.task {
+				task_ids.insert_combination(&[project_tag.clone(), task_tag.clone()], task.id)?;
+			}
+		}
+		Ok(task_ids)
+	}
+
+	/// Get the default task for each client that has one, indexed by client ID.
+	pub fn client_default_tasks(&self) -> Result<BTreeMap<u64, u64>, String> {
+		let mut result = BTreeMap::new();
+		for (client_id, client) in &self.client {
+			let client_id: u64 = client_id.parse()
+				.map_err(|_| format!("invalid client ID: expected unsigned number, got {}", client_id))?;
+			if let Some(default_task) = client.default_task {
+				result.insert(client_id, default_task);
+			}
+		}
+		Ok(result)
+	}
+}
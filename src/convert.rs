@@ -0,0 +1,99 @@
+use std::convert::TryFrom;
+use std::io::Write;
+
+use crate::config::CsvConfig;
+use crate::entry::Entry;
+use crate::import::json::JsonEntry;
+
+/// The file format to convert entries to.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OutputFormat {
+	/// A uurlog hour log.
+	Uurlog,
+
+	/// A CSV file, written with the column mapping from the configuration.
+	Csv,
+
+	/// A JSON array of entries.
+	Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+	type Err = String;
+
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		match data {
+			"uurlog" => Ok(Self::Uurlog),
+			"csv" => Ok(Self::Csv),
+			"json" => Ok(Self::Json),
+			_ => Err(format!("unknown output format: {}, expected uurlog, csv or json", data)),
+		}
+	}
+}
+
+/// Write entries in the given format.
+pub fn write_entries(output: &mut impl Write, format: OutputFormat, entries: &[Entry], csv: &CsvConfig) -> Result<(), String> {
+	match format {
+		OutputFormat::Uurlog => write_uurlog(output, entries),
+		OutputFormat::Csv => write_csv(output, entries, csv),
+		OutputFormat::Json => {
+			let entries: Vec<JsonEntry> = entries.iter().map(JsonEntry::from).collect();
+			serde_json::to_writer_pretty(&mut *output, &entries).map_err(|e| e.to_string())?;
+			writeln!(output).map_err(|e| e.to_string())
+		},
+	}
+}
+
+/// Write entries as uurlog hour log, with one `DATE: H:MM, TAGS: DESCRIPTION` line per entry.
+///
+/// Durations are rounded to whole minutes like [`Entry::hours`].
+/// Clock times are written in front of the description, so that they are read back with --clock-times.
+fn write_uurlog(output: &mut impl Write, entries: &[Entry]) -> Result<(), String> {
+	for entry in entries {
+		if let Some(tag) = entry.tags.iter().find(|x| x.contains(',') || x.contains(':')) {
+			return Err(format!("tag {:?} can not be written to an hour log, because it contains a comma or colon", tag));
+		}
+		let clock_times = entry.clock_times
+			.map(|x| format!("{}-{} ", x.start.format("%H:%M"), x.end.format("%H:%M")))
+			.unwrap_or_default();
+		writeln!(output, "{}: {}, {}: {}{}", entry.date, entry.hours(), entry.tags.join(", "), clock_times, entry.description)
+			.map_err(|e| e.to_string())?;
+	}
+	Ok(())
+}
+
+/// Write entries as CSV, using the same column mapping as for reading CSV files.
+fn write_csv(output: &mut impl Write, entries: &[Entry], mapping: &CsvConfig) -> Result<(), String> {
+	let delimiter = u8::try_from(mapping.delimiter)
+		.map_err(|_| format!("invalid CSV delimiter: {:?}, expected an ASCII character", mapping.delimiter))?;
+	let mut writer = ::csv::WriterBuilder::new()
+		.delimiter(delimiter)
+		.from_writer(output);
+
+	let mut header = vec![mapping.date.as_str()];
+	header.extend(mapping.duration.as_deref());
+	header.extend(mapping.start.as_deref());
+	header.extend(mapping.end.as_deref());
+	header.push(&mapping.tags);
+	header.push(&mapping.description);
+	writer.write_record(&header).map_err(|e| e.to_string())?;
+
+	for entry in entries {
+		let date = crate::timezone::to_naive_date(entry.date);
+		let mut record = vec![date.format(&mapping.date_format).to_string()];
+		if mapping.duration.is_some() {
+			record.push(entry.hours().to_string());
+		}
+		if mapping.start.is_some() {
+			record.push(entry.clock_times.map(|x| x.start.format("%H:%M").to_string()).unwrap_or_default());
+		}
+		if mapping.end.is_some() {
+			record.push(entry.clock_times.map(|x| x.end.format("%H:%M").to_string()).unwrap_or_default());
+		}
+		record.push(entry.tags.join(&mapping.tag_separator));
+		record.push(entry.description.clone());
+		writer.write_record(&record).map_err(|e| e.to_string())?;
+	}
+
+	writer.flush().map_err(|e| e.to_string())
+}
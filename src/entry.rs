@@ -0,0 +1,146 @@
+use chrono::{NaiveTime, Timelike};
+
+/// An entry to synchronize to Paymo.
+#[derive(Debug, Clone)]
+pub struct Entry {
+	pub date: uurlog::Date,
+
+	/// The duration in seconds.
+	///
+	/// Hour logs have whole minutes, but other sources can have seconds.
+	pub seconds: u32,
+
+	pub clock_times: Option<ClockTimes>,
+	pub tags: Vec<String>,
+	pub description: String,
+
+	/// The line in the source file the entry was read from, if known.
+	pub line: Option<usize>,
+}
+
+/// The start and end time of an entry.
+///
+/// If the end time is before the start time, the entry ends on the next day.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ClockTimes {
+	pub start: NaiveTime,
+	pub end: NaiveTime,
+}
+
+impl Entry {
+	/// Convert an entry from an hour log.
+	///
+	/// If `parse_clock_times` is true, a description starting with a time range like `09:00-10:30`
+	/// is split in the clock times and the remaining description.
+	pub fn from_uurlog(entry: uurlog::Entry, parse_clock_times: bool) -> Self {
+		let mut clock_times = None;
+		let mut description = entry.description;
+		if parse_clock_times {
+			if let Some((times, rest)) = split_clock_times(&description) {
+				clock_times = Some(times);
+				description = rest.to_string();
+			}
+		}
+
+		Self {
+			date: entry.date,
+			seconds: entry.hours.total_minutes() * 60,
+			clock_times,
+			tags: entry.tags,
+			description,
+			line: None,
+		}
+	}
+}
+
+impl Entry {
+	/// Get the duration rounded to whole minutes, as used in hour logs.
+	pub fn hours(&self) -> uurlog::Hours {
+		uurlog::Hours::from_minutes((self.seconds + 30) / 60)
+	}
+}
+
+impl ClockTimes {
+	/// Get the number of seconds between the start and end time.
+	pub fn total_seconds(&self) -> u32 {
+		let seconds = (self.end - self.start).num_seconds();
+		if seconds <= 0 {
+			(seconds + 24 * 3600) as u32
+		} else {
+			seconds as u32
+		}
+	}
+}
+
+/// Merge entries on the same day with the same tags and description into a single entry.
+///
+/// The merged entry takes the place of the first entry and gets the total duration.
+/// It keeps clock times only if the merged entries directly follow each other.
+pub fn merge_entries(entries: Vec<Entry>) -> Vec<Entry> {
+	let mut merged: Vec<Entry> = Vec::with_capacity(entries.len());
+	for entry in entries {
+		let existing = merged.iter_mut()
+			.find(|x| x.date == entry.date && x.tags == entry.tags && x.description == entry.description);
+		let existing = match existing {
+			Some(x) => x,
+			None => {
+				merged.push(entry);
+				continue;
+			},
+		};
+		existing.seconds += entry.seconds;
+		existing.clock_times = match (existing.clock_times, entry.clock_times) {
+			(Some(a), Some(b)) if a.end == b.start => Some(ClockTimes { start: a.start, end: b.end }),
+			_ => None,
+		};
+	}
+	merged
+}
+
+/// Split a description in a leading time range and the rest of the description.
+fn split_clock_times(description: &str) -> Option<(ClockTimes, &str)> {
+	let description = description.trim_start();
+	let (range, rest) = match description.find(char::is_whitespace) {
+		Some(i) => (&description[..i], description[i..].trim_start()),
+		None => (description, ""),
+	};
+
+	let (start, end) = range.split_once('-')?;
+	let start = NaiveTime::parse_from_str(start, "%H:%M").ok()?;
+	let end = NaiveTime::parse_from_str(end, "%H:%M").ok()?;
+	Some((ClockTimes { start, end }, rest))
+}
+
+impl std::fmt::Display for Entry {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}, {}", self.date, format_duration(self.seconds))?;
+		if let Some(clock_times) = &self.clock_times {
+			write!(f, " ({})", clock_times)?;
+		}
+		write!(f, ", {}: {}", self.tags.join(", "), crate::redact::description(&self.description))
+	}
+}
+
+/// Format a duration in seconds as `H:MM`, or as `H:MM:SS` if it is not a whole number of minutes.
+pub fn format_duration(seconds: u32) -> String {
+	let hours = uurlog::Hours::from_minutes(seconds / 60);
+	match seconds % 60 {
+		0 => hours.to_string(),
+		seconds => format!("{}:{:02}", hours, seconds),
+	}
+}
+
+impl std::fmt::Display for ClockTimes {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}-{}", format_time(self.start), format_time(self.end))
+	}
+}
+
+/// Format a time of day as `HH:MM`, or as `HH:MM:SS` if it is not a whole minute.
+fn format_time(time: NaiveTime) -> String {
+	if time.second() == 0 {
+		time.format("%H:%M").to_string()
+	} else {
+		time.format("%H:%M:%S").to_string()
+	}
+}
@@ -0,0 +1,84 @@
+//! Expense files, listing costs like receipts and travel to synchronize to Paymo.
+
+use chrono::NaiveDate;
+use std::path::Path;
+
+/// An expense from an expense file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expense {
+	pub date: NaiveDate,
+
+	/// The amount in the currency of the company.
+	pub amount: f64,
+
+	/// The tags to determine the project from, like the tags of hour log entries.
+	pub tags: Vec<String>,
+
+	pub description: String,
+}
+
+impl Expense {
+	/// Get the amount in cents, for comparing amounts without rounding errors.
+	pub fn cents(&self) -> i64 {
+		to_cents(self.amount)
+	}
+}
+
+impl std::fmt::Display for Expense {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}, {:.2}, {}: {}", self.date, self.amount, self.tags.join(", "), crate::redact::description(&self.description))
+	}
+}
+
+/// Convert an amount to cents.
+pub fn to_cents(amount: f64) -> i64 {
+	(amount * 100.0).round() as i64
+}
+
+/// A record of an expense file.
+#[derive(serde::Deserialize)]
+struct Record {
+	date: String,
+	amount: String,
+	#[serde(default)]
+	tags: String,
+	#[serde(default)]
+	description: String,
+}
+
+/// Read expenses from a CSV file.
+///
+/// The file must have a header with the columns `date`, `amount`, `tags` and `description`.
+/// Dates are formatted as `YYYY-MM-DD`, amounts use a decimal point, and tags are separated by spaces.
+pub fn read(path: &Path) -> Result<Vec<Expense>, String> {
+	let mut reader = ::csv::ReaderBuilder::new()
+		.trim(::csv::Trim::All)
+		.from_path(path)
+		.map_err(|e| e.to_string())?;
+
+	let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+	let mut expenses = Vec::new();
+	for record in reader.records() {
+		let record = record.map_err(|e| e.to_string())?;
+		let line = record.position().map(|x| x.line()).unwrap_or(0);
+		let error = |message: String| format!("line {}: {}", line, message);
+		let record: Record = record.deserialize(Some(&headers))
+			.map_err(|e| error(e.to_string()))?;
+
+		let date = NaiveDate::parse_from_str(&record.date, "%Y-%m-%d")
+			.map_err(|e| error(format!("invalid date {:?}: {}", record.date, e)))?;
+		let amount: f64 = record.amount.parse()
+			.ok()
+			.filter(|x: &f64| x.is_finite() && *x > 0.0)
+			.ok_or_else(|| error(format!("invalid amount {:?}, expected a positive number", record.amount)))?;
+
+		expenses.push(Expense {
+			date,
+			amount,
+			tags: record.tags.split_whitespace().map(String::from).collect(),
+			description: record.description,
+		});
+	}
+
+	Ok(expenses)
+}
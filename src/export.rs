@@ -0,0 +1,183 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::types;
+use crate::workspace::Workspace;
+
+/// The file format to export time entries in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExportFormat {
+	/// iCalendar, with one event per entry.
+	Ics,
+
+	/// CSV, with one row per entry.
+	Csv,
+
+	/// A JSON array of entries.
+	Json,
+}
+
+impl std::str::FromStr for ExportFormat {
+	type Err = String;
+
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		match data {
+			"ics" => Ok(Self::Ics),
+			"csv" => Ok(Self::Csv),
+			"json" => Ok(Self::Json),
+			_ => Err(format!("unknown export format: {}, expected ics, csv or json", data)),
+		}
+	}
+}
+
+/// Write time entries to a file or stream in the given format.
+pub fn write_entries(
+	output: &mut impl Write,
+	format: ExportFormat,
+	entries: &[types::TimeEntry],
+	workspace: &Workspace,
+	timezone: chrono_tz::Tz,
+) -> std::io::Result<()> {
+	match format {
+		ExportFormat::Ics => write_ics(output, entries, workspace, timezone),
+		ExportFormat::Csv => {
+			let mut writer = csv::Writer::from_writer(output);
+			for entry in entries {
+				writer.serialize(ExportedEntry::new(entry, workspace, timezone))?;
+			}
+			writer.flush()
+		},
+		ExportFormat::Json => {
+			let entries: Vec<_> = entries.iter().map(|x| ExportedEntry::new(x, workspace, timezone)).collect();
+			serde_json::to_writer_pretty(&mut *output, &entries)?;
+			writeln!(output)
+		},
+	}
+}
+
+/// A time entry with the names of its task, project and client.
+#[derive(Debug, Serialize)]
+struct ExportedEntry<'a> {
+	id: u64,
+	date: Option<chrono::NaiveDate>,
+	start_time: Option<DateTime<Utc>>,
+	end_time: Option<DateTime<Utc>>,
+	seconds: u32,
+	hours: String,
+	client_id: Option<u64>,
+	client: Option<&'a str>,
+	project_id: u64,
+	project: Option<&'a str>,
+	task_id: u64,
+	task: Option<&'a str>,
+	billed: bool,
+	description: &'a str,
+}
+
+impl<'a> ExportedEntry<'a> {
+	fn new(entry: &'a types::TimeEntry, workspace: &'a Workspace, timezone: chrono_tz::Tz) -> Self {
+		let seconds = entry.total_seconds();
+		let client = workspace.client_of_task(entry.task_id);
+		Self {
+			id: entry.id,
+			date: entry.local_date(timezone),
+			start_time: entry.start_time,
+			end_time: entry.end_time,
+			seconds,
+			hours: crate::report::format_seconds(seconds.into()),
+			client_id: client.map(|x| x.id),
+			client: client.map(|x| x.name.as_str()),
+			project_id: entry.project_id,
+			project: workspace.projects.get(&entry.project_id).map(|x| x.name.as_str()),
+			task_id: entry.task_id,
+			task: workspace.tasks.get(&entry.task_id).map(|x| x.name.as_str()),
+			billed: entry.billed,
+			description: &entry.description,
+		}
+	}
+}
+
+/// Write time entries as iCalendar events.
+///
+/// Entries with a start and end time become timed events, other entries become all-day events.
+fn write_ics(output: &mut impl Write, entries: &[types::TimeEntry], workspace: &Workspace, timezone: chrono_tz::Tz) -> std::io::Result<()> {
+	let now = format_ics_timestamp(Utc::now());
+	write_ics_line(output, "BEGIN:VCALENDAR")?;
+	write_ics_line(output, "VERSION:2.0")?;
+	write_ics_line(output, "PRODID:-//uurlog-paymo//EN")?;
+
+	for entry in entries {
+		let task = workspace.tasks.get(&entry.task_id).map(|x| x.name.as_str()).unwrap_or("unknown task");
+		let project = workspace.project_of_task(entry.task_id).map(|x| x.name.as_str()).unwrap_or("unknown project");
+		let hours = crate::report::format_seconds(u64::from(entry.total_seconds()));
+
+		write_ics_line(output, "BEGIN:VEVENT")?;
+		write_ics_line(output, &format!("UID:paymo-entry-{}@uurlog-paymo", entry.id))?;
+		write_ics_line(output, &format!("DTSTAMP:{}", now))?;
+
+		match (entry.start_time, entry.end_time) {
+			(Some(start), Some(end)) => {
+				write_ics_line(output, &format!("DTSTART:{}", format_ics_timestamp(start)))?;
+				write_ics_line(output, &format!("DTEND:{}", format_ics_timestamp(end)))?;
+			},
+			_ => {
+				let date = match entry.local_date(timezone) {
+					Some(date) => date,
+					None => {
+						log::warn!("skipping time entry {} without date", entry.id);
+						continue;
+					},
+				};
+				let next = date.succ_opt().unwrap();
+				write_ics_line(output, &format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")))?;
+				write_ics_line(output, &format!("DTEND;VALUE=DATE:{}", next.format("%Y%m%d")))?;
+			},
+		}
+
+		write_ics_line(output, &format!("SUMMARY:{}", escape_ics_text(&format!("{}: {}", task, entry.description))))?;
+		write_ics_line(output, &format!("DESCRIPTION:{}", escape_ics_text(&format!("{} hours on {} / {}", hours, project, task))))?;
+		write_ics_line(output, "END:VEVENT")?;
+	}
+
+	write_ics_line(output, "END:VCALENDAR")
+}
+
+/// Format a UTC timestamp for iCalendar.
+fn format_ics_timestamp(timestamp: DateTime<Utc>) -> String {
+	timestamp.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape text for an iCalendar property value.
+fn escape_ics_text(text: &str) -> String {
+	let mut result = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'\\' | ';' | ',' => {
+				result.push('\\');
+				result.push(c);
+			},
+			'\n' => result.push_str("\\n"),
+			'\r' => (),
+			c => result.push(c),
+		}
+	}
+	result
+}
+
+/// Write a single iCalendar content line, folding it at 75 bytes.
+fn write_ics_line(output: &mut impl Write, line: &str) -> std::io::Result<()> {
+	let mut start = 0;
+	let mut limit = 75;
+	for (i, c) in line.char_indices() {
+		if i + c.len_utf8() - start > limit {
+			output.write_all(&line.as_bytes()[start..i])?;
+			output.write_all(b"\r\n ")?;
+			start = i;
+			// Continuation lines start with a space, which counts towards the limit.
+			limit = 74;
+		}
+	}
+	output.write_all(&line.as_bytes()[start..])?;
+	output.write_all(b"\r\n")
+}
@@ -0,0 +1,46 @@
+//! Fingerprints of local entries, embedded in the description of uploaded entries.
+//!
+//! A fingerprint identifies the local entry that an entry on Paymo was created from,
+//! so that the entries still match when the description is edited on Paymo.
+
+use crate::entry::Entry;
+
+/// The marker before the fingerprint at the end of a description.
+const MARKER: &str = " [uurlog:";
+
+/// Compute the fingerprint of a local entry from its date, tags and description.
+pub fn compute(entry: &Entry) -> String {
+	let date = entry.date.to_string();
+	let tags = entry.tags.join(" ");
+	let hash = fnv1a(&[date.as_bytes(), b"\n", tags.as_bytes(), b"\n", entry.description.as_bytes()]);
+	format!("{:08x}", (hash ^ (hash >> 32)) as u32)
+}
+
+/// Get the description of an entry with its fingerprint appended.
+pub fn description_with_fingerprint(entry: &Entry) -> String {
+	format!("{}{}{}]", entry.description, MARKER, compute(entry))
+}
+
+/// Get the fingerprint at the end of a description, if it has one.
+pub fn find(description: &str) -> Option<&str> {
+	let rest = description.strip_suffix(']')?;
+	let start = rest.rfind(MARKER)? + MARKER.len();
+	let fingerprint = &rest[start..];
+	if fingerprint.len() == 8 && fingerprint.bytes().all(|x| x.is_ascii_hexdigit()) {
+		Some(fingerprint)
+	} else {
+		None
+	}
+}
+
+/// Compute the 64-bit FNV-1a hash of some data.
+///
+/// Unlike the hasher of the standard library, the result is guaranteed to be stable.
+pub fn fnv1a(parts: &[&[u8]]) -> u64 {
+	let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+	for &byte in parts.iter().copied().flatten() {
+		hash ^= u64::from(byte);
+		hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+	}
+	hash
+}
@@ -0,0 +1,100 @@
+//! Access to the staged files of a git repository, for the pre-commit hook.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Run a git command in the current directory and get its standard output.
+fn git(args: &[&str]) -> Result<Vec<u8>, String> {
+	let output = Command::new("git")
+		.args(args)
+		.output()
+		.map_err(|e| format!("failed to run git: {}", e))?;
+	if !output.status.success() {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		return Err(format!("git {} failed: {}", args.join(" "), stderr.trim()));
+	}
+	Ok(output.stdout)
+}
+
+/// A file that is added, copied, modified or renamed in the index.
+#[derive(Debug, Clone)]
+pub struct StagedFile {
+	/// The absolute path of the file in the working tree.
+	pub path: PathBuf,
+
+	/// The path relative to the top level of the repository, as used by git.
+	name: String,
+}
+
+impl StagedFile {
+	/// Get the staged contents of the file, which may differ from the file in the working tree.
+	pub fn contents(&self) -> Result<Vec<u8>, String> {
+		git(&["show", &format!(":{}", self.name)])
+	}
+
+	/// Write the staged contents to a new file in a private temporary directory.
+	///
+	/// The copy has the same file name as the original.
+	pub fn write_temp_copy(&self) -> Result<TempCopy, String> {
+		let contents = self.contents()?;
+
+		// Create a new directory that only the current user can access, failing if it already exists.
+		let dir = std::env::temp_dir().join(format!("uurlog-paymo-{}", crate::serve::random_token()));
+		let mut builder = std::fs::DirBuilder::new();
+		#[cfg(unix)]
+		std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+		builder.create(&dir)
+			.map_err(|e| format!("failed to create temporary directory {}: {}", dir.display(), e))?;
+
+		let file_name = self.path.file_name().unwrap_or_else(|| "staged".as_ref());
+		let copy = TempCopy {
+			path: dir.join(file_name),
+			dir,
+		};
+		std::fs::OpenOptions::new()
+			.write(true)
+			.create_new(true)
+			.open(&copy.path)
+			.and_then(|mut file| file.write_all(&contents))
+			.map_err(|e| format!("failed to write {}: {}", copy.path.display(), e))?;
+		Ok(copy)
+	}
+}
+
+/// A copy of a staged file in a private temporary directory.
+///
+/// The file and directory are removed when the copy is dropped.
+#[derive(Debug)]
+pub struct TempCopy {
+	/// The path of the copy.
+	pub path: PathBuf,
+
+	/// The directory containing the copy.
+	dir: PathBuf,
+}
+
+impl Drop for TempCopy {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_file(&self.path);
+		let _ = std::fs::remove_dir(&self.dir);
+	}
+}
+
+/// Get the files that are added, copied, modified or renamed in the index.
+pub fn staged_files() -> Result<Vec<StagedFile>, String> {
+	let toplevel = git(&["rev-parse", "--show-toplevel"])?;
+	let toplevel = PathBuf::from(String::from_utf8_lossy(&toplevel).trim_end_matches('\n'));
+	let names = git(&["diff", "--cached", "--name-only", "--diff-filter=ACMR", "-z"])?;
+	Ok(names
+		.split(|&c| c == 0)
+		.filter(|x| !x.is_empty())
+		.map(|x| {
+			let name = String::from_utf8_lossy(x).into_owned();
+			StagedFile {
+				path: toplevel.join(&name),
+				name,
+			}
+		})
+		.collect())
+}
@@ -0,0 +1,108 @@
+//! Logging of all API requests and responses with their full bodies, for diagnosing failures after the fact.
+//!
+//! The log is a file with one JSON record per line.
+//! The API token is sent in a header, which is never logged.
+
+use crate::cassette;
+use crate::redact;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// A file to log requests and responses to.
+#[derive(Debug)]
+pub struct HttpLog {
+	path: PathBuf,
+
+	/// The open file, locked to prevent concurrent requests from writing interleaved records.
+	file: Mutex<File>,
+}
+
+/// A request with its response or error, as written to the log.
+#[derive(Serialize)]
+struct Record<'a> {
+	/// The moment the request was sent.
+	time: chrono::DateTime<chrono::Utc>,
+
+	method: &'a str,
+	url: &'a str,
+
+	#[serde(skip_serializing_if = "Option::is_none")]
+	request_body: Option<&'a Value>,
+
+	/// The time until the full response was received, in milliseconds.
+	duration_ms: u64,
+
+	#[serde(skip_serializing_if = "Option::is_none")]
+	status: Option<u16>,
+
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	rate_limit: BTreeMap<&'a str, &'a str>,
+
+	/// The response body, as JSON if possible and as string otherwise.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	response_body: Option<Value>,
+
+	/// The error if no response was received.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	error: Option<&'a str>,
+}
+
+impl HttpLog {
+	/// Open a log file for appending.
+	pub fn open(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+		let path = path.into();
+		let file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+		Ok(Self {
+			path,
+			file: Mutex::new(file),
+		})
+	}
+
+	/// Append a request and its response or error to the log.
+	///
+	/// Failing to write the log does not fail the request, so it is only logged.
+	pub fn record(
+		&self,
+		time: chrono::DateTime<chrono::Utc>,
+		method: &str,
+		url: &str,
+		request_body: Option<&Value>,
+		duration: std::time::Duration,
+		result: &Result<cassette::Response, String>,
+	) {
+		let url = redact::url(url);
+		let mut record = Record {
+			time,
+			method,
+			url: &url,
+			request_body,
+			duration_ms: duration.as_millis() as u64,
+			status: None,
+			rate_limit: BTreeMap::new(),
+			response_body: None,
+			error: None,
+		};
+		match result {
+			Ok(response) => {
+				record.status = Some(response.status.as_u16());
+				record.rate_limit = response.rate_limit.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+				record.response_body = Some(serde_json::from_str(&response.body).unwrap_or_else(|_| Value::String(response.body.clone())));
+			},
+			Err(e) => record.error = Some(e),
+		}
+		if let Err(e) = self.append(&record) {
+			log::warn!("failed to write to HTTP log {}: {}", self.path.display(), e);
+		}
+	}
+
+	fn append(&self, record: &Record) -> std::io::Result<()> {
+		let mut line = serde_json::to_vec(record)?;
+		line.push(b'\n');
+		self.file.lock().unwrap().write_all(&line)
+	}
+}
@@ -0,0 +1,232 @@
+//! Translation of user-facing messages.
+//!
+//! Messages are written in English in the code, and the English text is used to look up the translation.
+//! Messages without a translation are shown in English.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// A language for user-facing messages.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Deserialize)]
+#[repr(u8)]
+pub enum Language {
+	#[serde(rename = "en")]
+	English,
+
+	#[serde(rename = "nl")]
+	Dutch,
+}
+
+/// The language of user-facing messages.
+static LANGUAGE: AtomicU8 = AtomicU8::new(Language::English as u8);
+
+/// Set the language of user-facing messages.
+pub fn set_language(language: Language) {
+	LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+/// Get the language of user-facing messages.
+pub fn language() -> Language {
+	if LANGUAGE.load(Ordering::Relaxed) == Language::Dutch as u8 {
+		Language::Dutch
+	} else {
+		Language::English
+	}
+}
+
+impl Language {
+	/// Detect the language from the `LC_ALL`, `LC_MESSAGES` and `LANG` environment variables.
+	///
+	/// Falls back to English if none of them select a supported language.
+	pub fn from_env() -> Self {
+		let locale = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+			.filter_map(|name| std::env::var(name).ok())
+			.find(|value| !value.is_empty());
+		match locale {
+			Some(locale) if locale.starts_with("nl") => Self::Dutch,
+			_ => Self::English,
+		}
+	}
+}
+
+/// Translate a message and fill in the `{}` placeholders with the arguments, in order.
+macro_rules! tr {
+	($message:literal $(, $arg:expr)* $(,)?) => {
+		$crate::i18n::format($message, &[$(&$arg as &dyn std::fmt::Display),*])
+	};
+}
+pub(crate) use tr;
+
+/// Translate a message template and fill in the `{}` placeholders.
+///
+/// Used by the [`tr!`] macro.
+pub fn format(message: &'static str, args: &[&dyn std::fmt::Display]) -> String {
+	let template = translate(message);
+	let mut args = args.iter();
+	let mut result = String::with_capacity(template.len());
+	let mut parts = template.split("{}");
+	if let Some(first) = parts.next() {
+		result.push_str(first);
+	}
+	for part in parts {
+		if let Some(arg) = args.next() {
+			result.push_str(&arg.to_string());
+		}
+		result.push_str(part);
+	}
+	result
+}
+
+/// Get the translation of a message in the current language.
+fn translate(message: &'static str) -> &'static str {
+	let translations = match language() {
+		Language::English => return message,
+		Language::Dutch => DUTCH,
+	};
+	translations.iter()
+		.find(|(english, _)| *english == message)
+		.map(|(_, translated)| *translated)
+		.unwrap_or(message)
+}
+
+/// Dutch translations of user-facing messages.
+///
+/// The placeholders must appear in the same order as in the English message.
+const DUTCH: &[(&str, &str)] = &[
+	("no period given, use --period or set default_period in the configuration file", "geen periode opgegeven, gebruik --period of stel default_period in het configuratiebestand in"),
+	("invalid period {}: {}", "ongeldige periode {}: {}"),
+	("failed to read configuration from {}: {}", "kan de configuratie niet lezen uit {}: {}"),
+	("recording directory {} does not exist", "opnamemap {} bestaat niet"),
+	("failed to apply changes for {} of {} profiles: {}", "wijzigingen voor {} van {} profielen zijn mislukt: {}"),
+	("failed to read token from {}: {}", "kan het token niet lezen uit {}: {}"),
+	("no hour logs given, add them to --sync or set hours in the configuration file", "geen urenlogs opgegeven, geef ze mee aan --sync of stel hours in het configuratiebestand in"),
+	("no hour logs found matching {}", "geen urenlogs gevonden voor {}"),
+	("no hour logs found matching the hours in the configuration file", "geen urenlogs gevonden voor hours in het configuratiebestand"),
+	("one or more tasks or projects are over budget", "een of meer taken of projecten zitten boven het budget"),
+	("the API token was not accepted", "het API-token is niet geaccepteerd"),
+	("the API responded with an error", "de API gaf een foutmelding"),
+	("no profiles configured, add [profile.NAME] sections to the configuration file", "geen profielen ingesteld, voeg [profile.NAME]-secties toe aan het configuratiebestand"),
+	("no users configured, add [[user]] sections to the configuration file", "geen gebruikers ingesteld, voeg [[user]]-secties toe aan het configuratiebestand"),
+	("failed to synchronize hours for {} of {} users: {}", "synchroniseren van uren is mislukt voor {} van {} gebruikers: {}"),
+	("Synchronizing hours of {} ({})", "Uren van {} ({}) synchroniseren"),
+	("found {} overlapping entries, use --allow-overlap to synchronize them anyway", "{} overlappende regels gevonden, gebruik --allow-overlap om ze toch te synchroniseren"),
+	("found {} duplicate entries, use --allow-duplicates to synchronize them anyway", "{} dubbele regels gevonden, gebruik --allow-duplicates om ze toch te synchroniseren"),
+	("found {} entries dated in the future, use --allow-future to synchronize them anyway", "{} regels met een datum in de toekomst gevonden, gebruik --allow-future om ze toch te synchroniseren"),
+	("found {} changes to entries older than {} days, use --allow-old to synchronize them anyway", "{} wijzigingen aan regels ouder dan {} dagen gevonden, gebruik --allow-old om ze toch te synchroniseren"),
+	("{} entries on Paymo have a duration that differs from the hour log by at most {} seconds and will be replaced, use --duration-tolerance {} to keep them", "{} regels op Paymo hebben een duur die maximaal {} seconden afwijkt van het urenlog en worden vervangen, gebruik --duration-tolerance {} om ze te behouden"),
+	("skipped {} entries without task:", "{} regels zonder taak overgeslagen:"),
+	("not deleting {} entries because of --add-only", "{} regels niet verwijderd vanwege --add-only"),
+	("not updating {} entries and not adding {} entries because of --delete-only", "{} regels niet bijgewerkt en {} regels niet toegevoegd vanwege --delete-only"),
+	("{} warnings were logged, failing because of --fail-on-warn", "er zijn {} waarschuwingen gelogd, mislukt vanwege --fail-on-warn"),
+	("Updating description of entry {}: {} -> {}", "Omschrijving van regel {} bijwerken: {} -> {}"),
+	("Adding entry with task id {}: {}", "Regel toevoegen met taak-ID {}: {}"),
+	("Deleting entry {}: {}, {}, {}", "Regel {} verwijderen: {}, {}, {}"),
+	("refusing to delete {} entries, which is more than the maximum of {} set by --max-deletions", "{} regels worden niet verwijderd, want dat is meer dan het maximum van {} uit --max-deletions"),
+	("failed to apply {} of {} changes:", "{} van {} wijzigingen zijn mislukt:"),
+	("the configuration maps tags to task {}, which does not exist on Paymo", "de configuratie koppelt tags aan taak {}, die niet bestaat op Paymo"),
+	("entries are mapped to tasks that do not exist on Paymo: {}, update the task IDs in the configuration", "regels zijn gekoppeld aan taken die niet bestaan op Paymo: {}, werk de taak-ID's in de configuratie bij"),
+	("entries are mapped to {} completed tasks:", "regels zijn gekoppeld aan {} afgeronde taken:"),
+	("task {} ({}) is completed, reopen it on Paymo or map the entries to an active task like {} ({})", "taak {} ({}) is afgerond, heropen de taak op Paymo of koppel de regels aan een actieve taak zoals {} ({})"),
+	("task {} ({}) is completed and its project has no active tasks, reopen it on Paymo", "taak {} ({}) is afgerond en het project heeft geen actieve taken, heropen de taak op Paymo"),
+	("you are not assigned to task {}, adding entries may fail, use --auto-assign to assign yourself", "je bent niet toegewezen aan taak {}, regels toevoegen kan mislukken, gebruik --auto-assign om jezelf toe te wijzen"),
+	("a timer is already running on task {} since {}, stop it first", "er loopt al een timer op taak {} sinds {}, stop die eerst"),
+	("nothing to change, use --task-id, --date, --hours or --description", "niets te wijzigen, gebruik --task-id, --date, --hours of --description"),
+	("not deleting {} entries, use --confirm to delete them", "{} regels niet verwijderd, gebruik --confirm om ze te verwijderen"),
+	("entry overlaps with the entry on line {}", "regel overlapt met de regel op regelnummer {}"),
+	("found {} errors in {} hour logs", "{} fouten gevonden in {} urenlogs"),
+	("entry is mapped to task {}, which does not exist on Paymo", "regel is gekoppeld aan taak {}, die niet bestaat op Paymo"),
+	("failed to contact Paymo, only checking the hour logs offline: {}", "kan Paymo niet bereiken, de urenlogs worden alleen offline gecontroleerd: {}"),
+	("To check staged hour logs before each commit, save this as .git/hooks/pre-commit in the repository:", "Sla dit op als .git/hooks/pre-commit in de repository om gestagede urenlogs voor elke commit te controleren:"),
+	("Then make it executable with: chmod +x .git/hooks/pre-commit", "Maak het daarna uitvoerbaar met: chmod +x .git/hooks/pre-commit"),
+	("Use git commit --no-verify to commit without checking the hour logs.", "Gebruik git commit --no-verify om te committen zonder de urenlogs te controleren."),
+	("client {} does not exist on Paymo", "klant {} bestaat niet op Paymo"),
+	("no unbilled entries on billable tasks for {} in {}", "geen ongefactureerde regels op declarabele taken voor {} in {}"),
+	("no price per hour for task {} ({}), the line is added with a rate of 0", "geen uurtarief voor taak {} ({}), de factuurregel krijgt een tarief van 0"),
+	("Not creating the invoice because of --dry-run", "Factuur niet aangemaakt vanwege --dry-run"),
+	("Created draft invoice {} ({}) for {}", "Conceptfactuur {} ({}) aangemaakt voor {}"),
+	("not deleting invoiced expense {}: {}", "gefactureerde uitgave {} wordt niet verwijderd: {}"),
+	("Deleting expense {}: {}", "Uitgave {} verwijderen: {}"),
+	("Adding expense for project {}: {}", "Uitgave toevoegen voor project {}: {}"),
+	("skipped {} expenses without project:", "{} uitgaven zonder project overgeslagen:"),
+	("task {} does not exist on Paymo", "taak {} bestaat niet op Paymo"),
+	("project {} does not exist on Paymo", "project {} bestaat niet op Paymo"),
+	("{} entries on tasks without a price per hour are not included in the estimate", "{} regels voor taken zonder uurtarief zijn niet meegeteld in de schatting"),
+	("Replace REDACTED with your API token, or use --token with a token file instead.", "Vervang REDACTED door je API-token, of gebruik --token met een tokenbestand."),
+	("{}, unless the client of the project has a default task, which is only checked with Paymo", "{}, tenzij de klant van het project een standaardtaak heeft, wat alleen met Paymo gecontroleerd wordt"),
+	("refusing to delete {} entries, which is more than the default maximum of {}, use --max-deletions to raise it", "{} regels worden niet verwijderd, want dat is meer dan het standaardmaximum van {}, gebruik --max-deletions om het te verhogen"),
+	("found {} entries older than {} days, use --allow-old to delete them anyway", "{} regels gevonden ouder dan {} dagen, gebruik --allow-old om ze toch te verwijderen"),
+	("invoice {} for {} in {} already exists, void or delete it to create a new one", "factuur {} voor {} in {} bestaat al, annuleer of verwijder die om een nieuwe te maken"),
+	("found {} changes to entries older than {} days, use --allow-old to undo them anyway", "{} wijzigingen aan regels ouder dan {} dagen gevonden, gebruik --allow-old om ze toch ongedaan te maken"),
+	("not undoing {} changes, use --confirm to undo them", "{} wijzigingen niet ongedaan gemaakt, gebruik --confirm om ze ongedaan te maken"),
+	("--task-ids is deprecated, move the mappings from {} to [[Task]] sections in the configuration file", "--task-ids is verouderd, verplaats de koppelingen uit {} naar [[Task]]-secties in het configuratiebestand"),
+	("failed to read task IDs from {}: {}", "kan de taak-ID's niet lezen uit {}: {}"),
+	("the entry is older than {} days, use --allow-old to add it anyway", "de regel is ouder dan {} dagen, gebruik --allow-old om hem toch toe te voegen"),
+	("No changes.", "Geen wijzigingen."),
+	("{} entries to delete, {} descriptions to update, {} entries to add", "{} regels te verwijderen, {} omschrijvingen bij te werken, {} regels toe te voegen"),
+];
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Get the messages passed to `tr!` in the source files in a directory.
+	fn tr_messages(dir: &std::path::Path, messages: &mut Vec<String>) {
+		for entry in std::fs::read_dir(dir).unwrap() {
+			let path = entry.unwrap().path();
+			if path.is_dir() {
+				tr_messages(&path, messages);
+				continue;
+			}
+			if path.extension() != Some(std::ffi::OsStr::new("rs")) {
+				continue;
+			}
+			let source = std::fs::read_to_string(&path).unwrap();
+			for (i, _) in source.match_indices("tr!(") {
+				if let Some(literal) = source[i + 4..].trim_start().strip_prefix('"') {
+					messages.push(parse_literal(literal));
+				}
+			}
+		}
+	}
+
+	/// Parse the rest of a string literal after the opening quote.
+	fn parse_literal(literal: &str) -> String {
+		let mut result = String::new();
+		let mut chars = literal.chars();
+		while let Some(c) = chars.next() {
+			match c {
+				'"' => break,
+				'\\' => match chars.next() {
+					Some('n') => result.push('\n'),
+					Some('t') => result.push('\t'),
+					Some(c) => result.push(c),
+					None => break,
+				},
+				c => result.push(c),
+			}
+		}
+		result
+	}
+
+	#[test]
+	fn dutch_translations_have_the_same_placeholders() {
+		for (english, dutch) in DUTCH {
+			assert_eq!(english.matches("{}").count(), dutch.matches("{}").count(), "placeholders differ for {:?}: {:?}", english, dutch);
+		}
+	}
+
+	#[test]
+	fn dutch_translations_are_used() {
+		let mut messages = Vec::new();
+		tr_messages(std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/src")), &mut messages);
+		for (english, _) in DUTCH {
+			assert!(messages.iter().any(|x| x == english), "translated message is not used by tr!: {:?}", english);
+		}
+	}
+
+	#[test]
+	fn dutch_translations_are_unique() {
+		for (i, (english, _)) in DUTCH.iter().enumerate() {
+			assert!(DUTCH[..i].iter().all(|(x, _)| x != english), "duplicate translation for {:?}", english);
+		}
+	}
+}
@@ -0,0 +1,233 @@
+use std::path::Path;
+
+use crate::config::CsvConfig;
+use crate::entry::{ClockTimes, Entry};
+
+mod clockify;
+mod csv;
+pub mod json;
+mod org;
+mod timeclock;
+mod timew;
+mod toggl;
+
+/// The file format of an hour log.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InputFormat {
+	/// A uurlog hour log.
+	Uurlog,
+
+	/// A CSV file, read with the column mapping from the configuration.
+	Csv,
+
+	/// A JSON array of entries, as written by `--convert`.
+	Json,
+
+	/// A detailed report exported from Toggl Track as CSV.
+	Toggl,
+
+	/// A detailed report exported from Clockify as CSV.
+	Clockify,
+
+	/// The JSON output of `timew export`.
+	Timew,
+
+	/// The `CLOCK:` lines of an Emacs org-mode file.
+	Org,
+
+	/// A ledger-style timeclock file.
+	Timeclock,
+}
+
+impl std::fmt::Display for InputFormat {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::Uurlog => f.write_str("uurlog"),
+			Self::Csv => f.write_str("csv"),
+			Self::Json => f.write_str("json"),
+			Self::Toggl => f.write_str("toggl"),
+			Self::Clockify => f.write_str("clockify"),
+			Self::Timew => f.write_str("timew"),
+			Self::Org => f.write_str("org"),
+			Self::Timeclock => f.write_str("timeclock"),
+		}
+	}
+}
+
+impl std::str::FromStr for InputFormat {
+	type Err = String;
+
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		match data {
+			"uurlog" => Ok(Self::Uurlog),
+			"csv" => Ok(Self::Csv),
+			"json" => Ok(Self::Json),
+			"toggl" => Ok(Self::Toggl),
+			"clockify" => Ok(Self::Clockify),
+			"timew" => Ok(Self::Timew),
+			"org" => Ok(Self::Org),
+			"timeclock" => Ok(Self::Timeclock),
+			_ => Err(format!("unknown input format: {}, expected uurlog, csv, json, toggl, clockify, timew, org or timeclock", data)),
+		}
+	}
+}
+
+/// What to do with entries whose clock times cross midnight.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MidnightPolicy {
+	/// Split the entry in a part before and after midnight, each on their own date.
+	#[default]
+	Split,
+
+	/// Keep the whole entry on the date it started.
+	Start,
+}
+
+/// Reader for hour logs in any of the supported formats.
+#[derive(Debug, Clone)]
+pub struct Reader {
+	/// The format of the hour logs.
+	pub format: InputFormat,
+
+	/// Read start and end times of entries.
+	///
+	/// For uurlog hour logs, these are parsed from the start of the description.
+	/// Formats that always record start and end times use them to split entries at midnight,
+	/// even if they are not kept.
+	pub clock_times: bool,
+
+	/// The column mapping for CSV files.
+	pub csv: CsvConfig,
+
+	/// What to do with entries that cross midnight.
+	pub midnight: MidnightPolicy,
+
+	/// The timezone for the local dates and times of formats that store UTC timestamps.
+	///
+	/// If not set, the timezone of the system is used.
+	pub timezone: Option<chrono_tz::Tz>,
+}
+
+impl Reader {
+	/// Check if the format stores UTC timestamps, and no timezone is set to convert them to local times.
+	pub fn needs_timezone(&self) -> bool {
+		self.format == InputFormat::Timew && self.timezone.is_none()
+	}
+
+	/// Read all entries from an hour log.
+	pub fn read(&self, path: &Path) -> Result<Vec<Entry>, String> {
+		let entries = match self.format {
+			InputFormat::Uurlog => {
+				let entries = uurlog::parse_file(path).map_err(|e| e.to_string())?;
+				entries.into_iter().map(|x| Entry::from_uurlog(x, self.clock_times)).collect()
+			},
+			InputFormat::Csv => csv::read(path, &self.csv)?,
+			InputFormat::Json => json::read(path)?,
+			InputFormat::Toggl => toggl::read(path)?,
+			InputFormat::Clockify => clockify::read(path)?,
+			InputFormat::Timew => timew::read(path, self.timezone)?,
+			InputFormat::Org => org::read(path)?,
+			InputFormat::Timeclock => timeclock::read(path)?,
+		};
+
+		// CSV files keep their clock times, since the column mapping asks for them explicitly.
+		let keep_clock_times = self.clock_times || self.format == InputFormat::Csv;
+
+		// Entries with clock times are uploaded with those, so use them for the duration too.
+		// Otherwise the entries on Paymo would never match the hour log.
+		let mut entries = entries;
+		if keep_clock_times {
+			for entry in &mut entries {
+				if let Some(clock_times) = entry.clock_times.filter(|x| x.total_seconds() != entry.seconds) {
+					log::warn!("clock times of entry do not match the logged hours, using the clock times: {}", entry);
+					entry.seconds = clock_times.total_seconds();
+				}
+			}
+		}
+
+		let mut entries: Vec<Entry> = match self.midnight {
+			MidnightPolicy::Split => entries.into_iter().flat_map(split_at_midnight).collect(),
+			MidnightPolicy::Start => entries,
+		};
+		if !keep_clock_times {
+			for entry in &mut entries {
+				entry.clock_times = None;
+			}
+		}
+		Ok(entries)
+	}
+}
+
+/// Split an entry whose clock times cross midnight in a part for each date.
+///
+/// The part after midnight gets the remainder of the logged duration.
+fn split_at_midnight(entry: Entry) -> Vec<Entry> {
+	let midnight = chrono::NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+	let clock_times = match entry.clock_times {
+		Some(x) if x.end < x.start && x.end != midnight => x,
+		_ => return vec![entry],
+	};
+
+	let before = ClockTimes { start: clock_times.start, end: midnight };
+	let after = ClockTimes { start: midnight, end: clock_times.end };
+	let before_seconds = before.total_seconds().min(entry.seconds);
+	vec![
+		Entry {
+			seconds: before_seconds,
+			clock_times: Some(before),
+			..entry.clone()
+		},
+		Entry {
+			date: entry.date.next(),
+			seconds: entry.seconds - before_seconds,
+			clock_times: Some(after),
+			..entry
+		},
+	]
+}
+
+/// Find the index of a column by the name in the header row.
+fn find_column(headers: &::csv::StringRecord, name: &str) -> Result<usize, String> {
+	headers.iter()
+		.position(|x| x.trim() == name)
+		.ok_or_else(|| format!("missing column in CSV header: {}", name))
+}
+
+/// Parse a duration formatted as `H:MM:SS` and return the number of seconds.
+fn parse_hms(data: &str) -> Result<u32, String> {
+	let invalid = || format!("invalid duration {:?}, expected H:MM:SS", data);
+	let mut fields = data.splitn(3, ':');
+	let mut next = || -> Result<u32, String> {
+		fields.next().ok_or_else(invalid)?.parse().map_err(|_| invalid())
+	};
+	let hours = next()?;
+	let minutes = next()?;
+	let seconds = next()?;
+	if minutes >= 60 || seconds >= 60 {
+		return Err(invalid());
+	}
+	Ok(hours * 3600 + minutes * 60 + seconds)
+}
+
+/// Parse a duration as decimal hours or as `H:MM`, and return the number of minutes.
+pub fn parse_duration(data: &str) -> Result<u32, String> {
+	let invalid = || format!("invalid duration {:?}, expected decimal hours or H:MM", data);
+	match data.split_once(':') {
+		Some((hours, minutes)) => {
+			let hours: u32 = hours.parse().map_err(|_| invalid())?;
+			let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+			if minutes >= 60 {
+				return Err(invalid());
+			}
+			Ok(hours * 60 + minutes)
+		},
+		None => {
+			let hours: f64 = data.parse().map_err(|_| invalid())?;
+			if !hours.is_finite() || hours < 0.0 {
+				return Err(invalid());
+			}
+			Ok((hours * 60.0).round() as u32)
+		},
+	}
+}
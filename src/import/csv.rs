@@ -0,0 +1,74 @@
+use chrono::{NaiveDate, NaiveTime};
+use std::convert::TryFrom;
+use std::path::Path;
+
+use crate::config::CsvConfig;
+use crate::entry::{ClockTimes, Entry};
+
+/// Read entries from a CSV file using a column mapping.
+pub fn read(path: &Path, mapping: &CsvConfig) -> Result<Vec<Entry>, String> {
+	let delimiter = u8::try_from(mapping.delimiter)
+		.map_err(|_| format!("invalid CSV delimiter: {:?}, expected an ASCII character", mapping.delimiter))?;
+	let mut reader = ::csv::ReaderBuilder::new()
+		.delimiter(delimiter)
+		.from_path(path)
+		.map_err(|e| e.to_string())?;
+
+	let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+	let column = |name: &str| super::find_column(&headers, name);
+	let date_column = column(&mapping.date)?;
+	let duration_column = mapping.duration.as_deref().map(column).transpose()?;
+	let start_column = mapping.start.as_deref().map(column).transpose()?;
+	let end_column = mapping.end.as_deref().map(column).transpose()?;
+	let description_column = column(&mapping.description)?;
+	let tags_column = column(&mapping.tags)?;
+
+	let mut entries = Vec::new();
+	for record in reader.records() {
+		let record = record.map_err(|e| e.to_string())?;
+		let line = record.position().map(|x| x.line()).unwrap_or(0);
+		let field = |index: usize| record.get(index).unwrap_or("").trim();
+		let error = |message: String| format!("line {}: {}", line, message);
+
+		let date = NaiveDate::parse_from_str(field(date_column), &mapping.date_format)
+			.map_err(|e| error(format!("invalid date {:?}: {}", field(date_column), e)))?;
+
+		let clock_times = match (start_column, end_column) {
+			(Some(start), Some(end)) => Some(ClockTimes {
+				start: parse_time(field(start)).map_err(error)?,
+				end: parse_time(field(end)).map_err(error)?,
+			}),
+			_ => None,
+		};
+
+		let seconds = match (duration_column, &clock_times) {
+			(Some(duration), _) => super::parse_duration(field(duration)).map_err(error)? * 60,
+			(None, Some(clock_times)) => clock_times.total_seconds(),
+			(None, None) => return Err(String::from("CSV mapping needs either a duration column or start and end columns")),
+		};
+
+		let tags = field(tags_column)
+			.split(mapping.tag_separator.as_str())
+			.map(str::trim)
+			.filter(|x| !x.is_empty())
+			.map(String::from)
+			.collect();
+
+		entries.push(Entry {
+			date: crate::timezone::from_naive_date(date).map_err(error)?,
+			seconds,
+			clock_times,
+			tags,
+			description: field(description_column).to_string(),
+			line: Some(line as usize),
+		});
+	}
+
+	Ok(entries)
+}
+
+/// Parse a time of day formatted as `HH:MM`.
+fn parse_time(data: &str) -> Result<NaiveTime, String> {
+	NaiveTime::parse_from_str(data, "%H:%M")
+		.map_err(|e| format!("invalid time {:?}: {}", data, e))
+}
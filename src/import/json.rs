@@ -0,0 +1,65 @@
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::entry::{ClockTimes, Entry};
+
+/// The JSON representation of an entry.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsonEntry {
+	pub date: NaiveDate,
+	pub minutes: u32,
+
+	/// The exact duration in seconds, only present if it is not a whole number of minutes.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub seconds: Option<u32>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub start: Option<NaiveTime>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub end: Option<NaiveTime>,
+	#[serde(default)]
+	pub tags: Vec<String>,
+	#[serde(default)]
+	pub description: String,
+}
+
+impl From<&Entry> for JsonEntry {
+	fn from(entry: &Entry) -> Self {
+		Self {
+			date: crate::timezone::to_naive_date(entry.date),
+			minutes: entry.hours().total_minutes(),
+			seconds: Some(entry.seconds).filter(|x| x % 60 != 0),
+			start: entry.clock_times.map(|x| x.start),
+			end: entry.clock_times.map(|x| x.end),
+			tags: entry.tags.clone(),
+			description: entry.description.clone(),
+		}
+	}
+}
+
+impl std::convert::TryFrom<JsonEntry> for Entry {
+	type Error = String;
+
+	fn try_from(entry: JsonEntry) -> Result<Self, String> {
+		let clock_times = match (entry.start, entry.end) {
+			(Some(start), Some(end)) => Some(ClockTimes { start, end }),
+			_ => None,
+		};
+		Ok(Self {
+			date: crate::timezone::from_naive_date(entry.date)?,
+			seconds: entry.seconds.unwrap_or(entry.minutes * 60),
+			clock_times,
+			tags: entry.tags,
+			description: entry.description,
+			line: None,
+		})
+	}
+}
+
+/// Read entries from a JSON array of entries.
+pub fn read(path: &Path) -> Result<Vec<Entry>, String> {
+	let data = std::fs::read(path).map_err(|e| e.to_string())?;
+	let entries: Vec<JsonEntry> = serde_json::from_slice(&data).map_err(|e| e.to_string())?;
+	entries.into_iter().map(std::convert::TryFrom::try_from).collect()
+}
@@ -0,0 +1,93 @@
+use chrono::NaiveDateTime;
+use std::path::Path;
+
+use crate::entry::{ClockTimes, Entry};
+
+/// Read entries from the `CLOCK:` lines in an Emacs org-mode file.
+///
+/// Each clock line becomes an entry with the title of the heading as description.
+/// The tags of the heading and its parent headings are used as tags of the entry.
+/// Running clocks are skipped.
+pub fn read(path: &Path) -> Result<Vec<Entry>, String> {
+	let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+	// The title and tags of the current heading and its parents.
+	let mut headings: Vec<(usize, String, Vec<String>)> = Vec::new();
+	let mut entries = Vec::new();
+
+	for (i, line) in data.lines().enumerate() {
+		let error = |message: String| format!("line {}: {}", i + 1, message);
+
+		if let Some((level, title, tags)) = parse_heading(line) {
+			headings.retain(|(x, _, _)| *x < level);
+			headings.push((level, title, tags));
+			continue;
+		}
+
+		let clock = match line.trim().strip_prefix("CLOCK:") {
+			Some(clock) => clock.trim(),
+			None => continue,
+		};
+		let (start, end) = match clock.split_once("--") {
+			Some((start, end)) => (start, end.split("=>").next().unwrap_or("").trim()),
+			None => {
+				log::warn!("skipping running clock on line {} of {}", i + 1, path.display());
+				continue;
+			},
+		};
+		let start = parse_timestamp(start).map_err(error)?;
+		let end = parse_timestamp(end).map_err(error)?;
+		let minutes = (end - start).num_minutes().max(0) as u32;
+
+		let (description, tags) = match headings.last() {
+			Some((_, title, _)) => (title.clone(), headings.iter().flat_map(|(_, _, tags)| tags.iter().cloned()).collect()),
+			None => (String::new(), Vec::new()),
+		};
+
+		entries.push(Entry {
+			date: crate::timezone::from_naive_date(start.date()).map_err(error)?,
+			seconds: minutes * 60,
+			clock_times: Some(ClockTimes { start: start.time(), end: end.time() }),
+			tags,
+			description,
+			line: Some(i + 1),
+		});
+	}
+
+	Ok(entries)
+}
+
+/// Parse an org-mode heading into its level, title and tags.
+///
+/// A leading TODO or DONE keyword is removed from the title.
+fn parse_heading(line: &str) -> Option<(usize, String, Vec<String>)> {
+	let level = line.chars().take_while(|&c| c == '*').count();
+	if level == 0 || !line[level..].starts_with(' ') {
+		return None;
+	}
+
+	let mut title = line[level..].trim();
+	let mut tags = Vec::new();
+	if let Some(start) = title.strip_suffix(':').and_then(|x| x.rfind(char::is_whitespace)) {
+		let candidate = title[start..].trim();
+		if candidate.starts_with(':') && !candidate.contains(' ') {
+			tags = candidate.split(':').filter(|x| !x.is_empty()).map(String::from).collect();
+			title = title[..start].trim_end();
+		}
+	}
+	for keyword in ["TODO ", "DONE "] {
+		title = title.strip_prefix(keyword).unwrap_or(title);
+	}
+
+	Some((level, title.to_string(), tags))
+}
+
+/// Parse an org-mode timestamp like `[2024-01-02 Tue 09:00]`.
+fn parse_timestamp(data: &str) -> Result<NaiveDateTime, String> {
+	let inner = data.trim().trim_start_matches('[').trim_end_matches(']');
+	let mut fields = inner.split_whitespace();
+	let date = fields.next().unwrap_or("");
+	let time = fields.last().unwrap_or("");
+	NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M")
+		.map_err(|e| format!("invalid timestamp {:?}: {}", data, e))
+}
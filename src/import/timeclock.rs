@@ -0,0 +1,72 @@
+use chrono::NaiveDateTime;
+use std::path::Path;
+
+use crate::entry::{ClockTimes, Entry};
+
+/// Read entries from a ledger-style timeclock file.
+///
+/// Each pair of `i` and `o` lines becomes an entry.
+/// The components of the account name are used as tags, and the payee as description.
+pub fn read(path: &Path) -> Result<Vec<Entry>, String> {
+	let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+	// The check-in time, account, payee and line of the running interval.
+	let mut running: Option<(NaiveDateTime, String, String, usize)> = None;
+	let mut entries = Vec::new();
+
+	for (i, line) in data.lines().enumerate() {
+		let error = |message: String| format!("line {}: {}", i + 1, message);
+		let line = line.trim_end();
+		let (code, rest) = match line.split_once(' ') {
+			Some(x) => x,
+			None => continue,
+		};
+
+		match code {
+			"i" | "I" => {
+				if running.is_some() {
+					return Err(error(String::from("check-in while already checked in")));
+				}
+				let (timestamp, rest) = split_timestamp(rest).map_err(error)?;
+				let (account, payee) = match rest.find("  ").or_else(|| rest.find('\t')) {
+					Some(i) => (rest[..i].trim(), rest[i..].trim()),
+					None => (rest.trim(), ""),
+				};
+				running = Some((timestamp, account.to_string(), payee.to_string(), i + 1));
+			},
+			"o" | "O" => {
+				let (start, account, payee, line) = running.take()
+					.ok_or_else(|| error(String::from("check-out without check-in")))?;
+				let (end, _) = split_timestamp(rest).map_err(error)?;
+				let seconds = (end - start).num_seconds().max(0) as u32;
+				entries.push(Entry {
+					date: crate::timezone::from_naive_date(start.date()).map_err(error)?,
+					seconds,
+					clock_times: Some(ClockTimes { start: start.time(), end: end.time() }),
+					tags: account.split(':').filter(|x| !x.is_empty()).map(String::from).collect(),
+					description: payee,
+					line: Some(line),
+				});
+			},
+			_ => continue,
+		}
+	}
+
+	if let Some((start, account, _, _)) = running {
+		log::warn!("skipping running interval for {} started at {} in {}", account, start, path.display());
+	}
+
+	Ok(entries)
+}
+
+/// Split a timeclock timestamp like `2024/01/02 09:00:00` from the rest of a line.
+fn split_timestamp(data: &str) -> Result<(NaiveDateTime, &str), String> {
+	let data = data.trim_start();
+	let mut fields = data.splitn(3, ' ');
+	let date = fields.next().unwrap_or("").replace('-', "/");
+	let time = fields.next().unwrap_or("");
+	let rest = fields.next().unwrap_or("");
+	let timestamp = NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y/%m/%d %H:%M:%S")
+		.map_err(|e| format!("invalid timestamp {:?}: {}", data, e))?;
+	Ok((timestamp, rest))
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::entry::{ClockTimes, Entry};
+
+/// An interval from `timew export`.
+#[derive(Debug, Deserialize)]
+struct Interval {
+	start: String,
+	end: Option<String>,
+	#[serde(default)]
+	tags: Vec<String>,
+	#[serde(default)]
+	annotation: String,
+}
+
+/// Read entries from the JSON output of `timew export`.
+///
+/// Intervals are converted to entries on the date of their start time in the given timezone,
+/// or in the timezone of the system if no timezone is given.
+/// Intervals that are still running are skipped.
+pub fn read(path: &Path, timezone: Option<Tz>) -> Result<Vec<Entry>, String> {
+	let data = std::fs::read(path).map_err(|e| e.to_string())?;
+	let intervals: Vec<Interval> = serde_json::from_slice(&data).map_err(|e| e.to_string())?;
+
+	let mut entries = Vec::with_capacity(intervals.len());
+	for interval in intervals {
+		let end = match &interval.end {
+			Some(end) => parse_timestamp(end)?,
+			None => {
+				log::warn!("skipping running timewarrior interval started at {}", interval.start);
+				continue;
+			},
+		};
+		let start = parse_timestamp(&interval.start)?;
+		let seconds = (end - start).num_seconds().max(0) as u32;
+
+		let start = to_local(start, timezone);
+		let end = to_local(end, timezone);
+		let clock_times = Some(ClockTimes {
+			start: start.time(),
+			end: end.time(),
+		});
+
+		entries.push(Entry {
+			date: crate::timezone::from_naive_date(start.date())?,
+			seconds,
+			clock_times,
+			tags: interval.tags,
+			description: interval.annotation,
+			line: None,
+		});
+	}
+
+	Ok(entries)
+}
+
+/// Convert a timestamp to the local time in a timezone, or in the timezone of the system.
+fn to_local(timestamp: DateTime<Utc>, timezone: Option<Tz>) -> NaiveDateTime {
+	match timezone {
+		Some(timezone) => timestamp.with_timezone(&timezone).naive_local(),
+		None => timestamp.with_timezone(&Local).naive_local(),
+	}
+}
+
+/// Parse a timestamp in the timewarrior format: `YYYYMMDDTHHMMSSZ`.
+fn parse_timestamp(data: &str) -> Result<DateTime<Utc>, String> {
+	let timestamp = NaiveDateTime::parse_from_str(data, "%Y%m%dT%H%M%SZ")
+		.map_err(|e| format!("invalid timestamp {:?}: {}", data, e))?;
+	Ok(Utc.from_utc_datetime(&timestamp))
+}
@@ -0,0 +1,62 @@
+use chrono::{NaiveDate, NaiveTime};
+use std::path::Path;
+
+use crate::entry::{ClockTimes, Entry};
+
+/// Read entries from a Toggl Track detailed report in CSV format.
+///
+/// The Toggl project and task are added as tags before the Toggl tags,
+/// so they can be mapped to Paymo tasks with the normal configuration.
+pub fn read(path: &Path) -> Result<Vec<Entry>, String> {
+	let mut reader = ::csv::Reader::from_path(path).map_err(|e| e.to_string())?;
+
+	let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+	let column = |name: &str| super::find_column(&headers, name);
+	let project_column = column("Project")?;
+	let task_column = column("Task").ok();
+	let description_column = column("Description")?;
+	let start_date_column = column("Start date")?;
+	let start_time_column = column("Start time")?;
+	let end_time_column = column("End time")?;
+	let duration_column = column("Duration")?;
+	let tags_column = column("Tags")?;
+
+	let mut entries = Vec::new();
+	for record in reader.records() {
+		let record = record.map_err(|e| e.to_string())?;
+		let line = record.position().map(|x| x.line()).unwrap_or(0);
+		let field = |index: usize| record.get(index).unwrap_or("").trim();
+		let error = |message: String| format!("line {}: {}", line, message);
+
+		let date = NaiveDate::parse_from_str(field(start_date_column), "%Y-%m-%d")
+			.map_err(|e| error(format!("invalid start date {:?}: {}", field(start_date_column), e)))?;
+		let seconds = super::parse_hms(field(duration_column)).map_err(error)?;
+
+		let clock_times = Some(ClockTimes {
+			start: parse_time(field(start_time_column)).map_err(error)?,
+			end: parse_time(field(end_time_column)).map_err(error)?,
+		});
+
+		let mut tags = Vec::new();
+		tags.extend(Some(field(project_column)).filter(|x| !x.is_empty()).map(String::from));
+		tags.extend(task_column.map(field).filter(|x| !x.is_empty()).map(String::from));
+		tags.extend(field(tags_column).split(',').map(str::trim).filter(|x| !x.is_empty()).map(String::from));
+
+		entries.push(Entry {
+			date: crate::timezone::from_naive_date(date).map_err(error)?,
+			seconds,
+			clock_times,
+			tags,
+			description: field(description_column).to_string(),
+			line: Some(line as usize),
+		});
+	}
+
+	Ok(entries)
+}
+
+/// Parse a time of day formatted as `HH:MM:SS`.
+fn parse_time(data: &str) -> Result<NaiveTime, String> {
+	NaiveTime::parse_from_str(data, "%H:%M:%S")
+		.map_err(|e| format!("invalid time {:?}: {}", data, e))
+}
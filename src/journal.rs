@@ -0,0 +1,193 @@
+//! A journal of the changes made to entries on Paymo, so that a run can be undone.
+//!
+//! The journal is a file with one JSON record per line.
+//! Every run that changes entries appends its changes to it.
+
+use crate::types::TimeEntry;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A journal file to record changes in.
+#[derive(Debug)]
+pub struct Journal {
+	path: PathBuf,
+
+	/// The ID of the current run.
+	run: String,
+
+	/// The profile used for the changes, if any.
+	profile: Option<String>,
+
+	/// The API root the changes are made on.
+	api_root: Option<String>,
+
+	/// Lock to prevent concurrent changes from writing interleaved records.
+	lock: Mutex<()>,
+}
+
+/// A change recorded in the journal.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Record {
+	/// The ID of the run that made the change.
+	pub run: String,
+
+	/// The moment the change was made.
+	pub time: chrono::DateTime<chrono::Utc>,
+
+	/// The profile used for the change, if any.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub profile: Option<String>,
+
+	/// The API root the change was made on.
+	///
+	/// Missing for records written by older versions.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub api_root: Option<String>,
+
+	#[serde(flatten)]
+	pub change: Change,
+}
+
+/// A change to an entry on Paymo.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Change {
+	/// An entry was added.
+	Add {
+		entry_id: u64,
+
+		/// The added entry, missing for records written by older versions.
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		entry: Option<TimeEntry>,
+	},
+
+	/// An entry was deleted.
+	Delete {
+		entry: TimeEntry,
+	},
+
+	/// An entry was changed, with the entry as it was before the change.
+	Update {
+		entry: TimeEntry,
+	},
+}
+
+impl Journal {
+	/// Create a journal for the current run.
+	pub fn new(path: impl Into<PathBuf>) -> Self {
+		let now = chrono::Utc::now();
+		Self {
+			path: path.into(),
+			run: format!("{}-{}", now.format("%Y%m%dT%H%M%S%.3fZ"), std::process::id()),
+			profile: None,
+			api_root: None,
+			lock: Mutex::new(()),
+		}
+	}
+
+	/// Get a journal for the changes made with a profile, in the same file and run.
+	pub fn for_profile(&self, profile: &str) -> Self {
+		Self {
+			path: self.path.clone(),
+			run: self.run.clone(),
+			profile: Some(profile.to_owned()),
+			api_root: self.api_root.clone(),
+			lock: Mutex::new(()),
+		}
+	}
+
+	/// Get a journal for the changes made on an API root, in the same file and run.
+	pub fn for_api_root(&self, api_root: &str) -> Self {
+		Self {
+			path: self.path.clone(),
+			run: self.run.clone(),
+			profile: self.profile.clone(),
+			api_root: Some(api_root.to_owned()),
+			lock: Mutex::new(()),
+		}
+	}
+
+	/// Get a journal for a part of the period of the current run, recorded as a separate run.
+	pub fn for_period(&self, label: &str) -> Self {
+		Self {
+			path: self.path.clone(),
+			run: format!("{}-{}", self.run, label),
+			profile: self.profile.clone(),
+			api_root: self.api_root.clone(),
+			lock: Mutex::new(()),
+		}
+	}
+
+	/// Get the ID of the current run.
+	pub fn run(&self) -> &str {
+		&self.run
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	pub fn profile(&self) -> Option<&str> {
+		self.profile.as_deref()
+	}
+
+	pub fn api_root(&self) -> Option<&str> {
+		self.api_root.as_deref()
+	}
+
+	/// Append a change to the journal.
+	///
+	/// The change has already been made, so failing to record it is only logged.
+	pub fn record(&self, change: Change) {
+		let record = Record {
+			run: self.run.clone(),
+			time: chrono::Utc::now(),
+			profile: self.profile.clone(),
+			api_root: self.api_root.clone(),
+			change,
+		};
+		if let Err(e) = self.append(&record) {
+			log::warn!("failed to write change to journal {}: {}", self.path.display(), e);
+		}
+	}
+
+	fn append(&self, record: &Record) -> std::io::Result<()> {
+		let mut line = serde_json::to_vec(record)?;
+		line.push(b'\n');
+
+		let _lock = self.lock.lock().unwrap();
+		if let Some(parent) = self.path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		std::fs::OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&self.path)?
+			.write_all(&line)
+	}
+}
+
+/// Get the default path of the journal.
+///
+/// Uses `$XDG_STATE_HOME/uurlog-paymo/journal.jsonl`, falling back to `$HOME/.local/state/uurlog-paymo/journal.jsonl`.
+pub fn default_path() -> Option<PathBuf> {
+	Some(crate::timer::state_dir()?.join("journal.jsonl"))
+}
+
+/// Read all records from a journal file.
+///
+/// A missing file is treated as an empty journal.
+pub fn read(path: &Path) -> Result<Vec<Record>, String> {
+	let data = match std::fs::read_to_string(path) {
+		Ok(data) => data,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e.to_string()),
+	};
+	data.lines()
+		.enumerate()
+		.filter(|(_, line)| !line.trim().is_empty())
+		.map(|(i, line)| serde_json::from_str(line).map_err(|e| format!("line {}: {}", i + 1, e)))
+		.collect()
+}
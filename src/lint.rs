@@ -0,0 +1,198 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::entry::Entry;
+use crate::i18n::tr;
+use crate::import::Reader;
+use crate::task_ids::{TaskIds, UnmappedPolicy};
+
+/// The output format for lint results.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LintFormat {
+	/// Problems grouped per file, for reading in a terminal.
+	Text,
+
+	/// One `FILE:LINE:COLUMN: SEVERITY: MESSAGE` record per line.
+	///
+	/// This is the format of compiler diagnostics, which editors can parse with their standard problem matchers.
+	Diagnostics,
+}
+
+impl std::str::FromStr for LintFormat {
+	type Err = String;
+
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		match data {
+			"text" => Ok(Self::Text),
+			"diagnostics" => Ok(Self::Diagnostics),
+			_ => Err(format!("unknown format for lint results: {}, expected text or diagnostics", data)),
+		}
+	}
+}
+
+/// Which problems are errors, and which are only warnings.
+///
+/// These mirror the options of a synchronization, so that errors are exactly the problems that would stop it.
+#[derive(Debug, Clone)]
+pub struct LintOptions {
+	pub unmapped: UnmappedPolicy,
+	pub allow_overlap: bool,
+
+	/// The IDs of the tasks on Paymo, to check that entries are mapped to existing tasks.
+	pub existing_tasks: Option<BTreeSet<u64>>,
+
+	/// Clients have default tasks that were not looked up, because Paymo was not contacted.
+	///
+	/// Entries that are only mapped to a project may then get the default task of its client,
+	/// so they are reported as warnings instead of errors.
+	pub unresolved_default_tasks: bool,
+}
+
+/// The severity of a problem.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+	Error,
+	Warning,
+}
+
+impl std::fmt::Display for Severity {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::Error => f.write_str("error"),
+			Self::Warning => f.write_str("warning"),
+		}
+	}
+}
+
+/// A problem in an hour log.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+	pub file: PathBuf,
+
+	/// The line number, starting at 1.
+	pub line: usize,
+
+	/// The column number in characters, starting at 1.
+	pub column: usize,
+
+	pub severity: Severity,
+	pub message: String,
+}
+
+/// Check an hour log for syntax errors, unmapped tags and overlapping entries.
+///
+/// Duplicate entries are not checked, since only identical entries in different hour logs block a synchronization.
+///
+/// Without a tag mapping, tags are not checked.
+/// Entries of formats that do not record their line, like uurlog hour logs, are found by searching for their tags and description.
+pub fn lint_file(file: &Path, reader: &Reader, task_ids: Option<&TaskIds>, options: &LintOptions) -> Vec<Diagnostic> {
+	let diagnostic = |(line, column): (usize, usize), severity, message| Diagnostic {
+		file: file.to_owned(),
+		line,
+		column,
+		severity,
+		message,
+	};
+
+	let entries = match reader.read(file) {
+		Ok(entries) => entries,
+		Err(e) => return vec![diagnostic((error_line(&e).unwrap_or(1), 1), Severity::Error, e)],
+	};
+	let source = std::fs::read_to_string(file).unwrap_or_default();
+	let positions = locate_entries(&source, &entries);
+	let index_of = |entry: &Entry| entries.iter().position(|x| std::ptr::eq(x, entry)).unwrap();
+
+	let mut diagnostics = Vec::new();
+	if let Some(task_ids) = task_ids {
+		let severity = match options.unmapped {
+			UnmappedPolicy::Skip => None,
+			UnmappedPolicy::Warn => Some(Severity::Warning),
+			UnmappedPolicy::Error => Some(Severity::Error),
+		};
+		if let Some(severity) = severity {
+			for (entry, position) in entries.iter().zip(&positions) {
+				match task_ids.resolve(&entry.tags) {
+					Err(reason) if options.unresolved_default_tasks && task_ids.resolve_project(&entry.tags).is_some() => {
+						diagnostics.push(diagnostic(*position, Severity::Warning, tr!("{}, unless the client of the project has a default task, which is only checked with Paymo", reason)));
+					},
+					Err(reason) => diagnostics.push(diagnostic(*position, severity, reason)),
+					Ok(task_id) if options.existing_tasks.as_ref().is_some_and(|x| !x.contains(&task_id)) => {
+						diagnostics.push(diagnostic(*position, Severity::Error, tr!("entry is mapped to task {}, which does not exist on Paymo", task_id)));
+					},
+					Ok(_) => (),
+				}
+			}
+		}
+	}
+
+	let severity = if options.allow_overlap { Severity::Warning } else { Severity::Error };
+	for (a, b) in crate::checks::find_overlaps(&entries) {
+		let (a, b) = (positions[index_of(a)], positions[index_of(b)]);
+		diagnostics.push(diagnostic(b, severity, tr!("entry overlaps with the entry on line {}", a.0)));
+	}
+
+	diagnostics.sort_by_key(|x| (x.line, x.column, x.severity));
+	diagnostics
+}
+
+/// Print diagnostics in the given format.
+pub fn print_diagnostics(diagnostics: &[Diagnostic], format: LintFormat) {
+	match format {
+		LintFormat::Diagnostics => {
+			for x in diagnostics {
+				println!("{}:{}:{}: {}: {}", x.file.display(), x.line, x.column, x.severity, x.message);
+			}
+		},
+		LintFormat::Text => {
+			let mut file = None;
+			for x in diagnostics {
+				if file != Some(&x.file) {
+					println!("{}", x.file.display());
+					file = Some(&x.file);
+				}
+				println!("  line {}: {}: {}", x.line, x.severity, x.message);
+			}
+		},
+	}
+}
+
+/// Get the line number from an error message of the importers, like `line 12: invalid date`.
+fn error_line(message: &str) -> Option<usize> {
+	let (_, rest) = message.split_once("line ")?;
+	let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+	rest[..digits].parse().ok().filter(|&x| x > 0)
+}
+
+/// Find the line and column of each entry in the source of an hour log.
+///
+/// Entries without a known line are searched for in order, starting after the line of the previous entry.
+/// The column is the position of the first tag, and entries that can not be found are put on the first line.
+fn locate_entries(source: &str, entries: &[Entry]) -> Vec<(usize, usize)> {
+	let lines: Vec<&str> = source.lines().collect();
+	let mut next = 0;
+	entries.iter()
+		.map(|entry| {
+			let found = match entry.line {
+				// The CSV reader counts blank lines before a record as part of it, so skip those.
+				Some(line) => (line.saturating_sub(1)..lines.len()).find(|&i| !lines[i].trim().is_empty()),
+				None => (next..lines.len()).chain(0..next).find(|&i| matches_entry(lines[i], entry)),
+			};
+			match found {
+				Some(i) => {
+					next = i + 1;
+					let column = entry.tags.first()
+						.and_then(|tag| lines[i].find(tag.as_str()))
+						.map(|x| lines[i][..x].chars().count() + 1)
+						.unwrap_or(1);
+					(i + 1, column)
+				},
+				None => (1, 1),
+			}
+		})
+		.collect()
+}
+
+/// Check if a line of an hour log contains the tags and description of an entry.
+fn matches_entry(line: &str, entry: &Entry) -> bool {
+	line.contains(entry.description.as_str()) && entry.tags.iter().all(|tag| line.contains(tag.as_str()))
+}
@@ -0,0 +1,103 @@
+//! Formatting of dates and numbers according to the settings of a Paymo user.
+
+use crate::types;
+
+/// The date and number format of a Paymo user.
+///
+/// Only used for output meant for humans.
+/// Machine readable output, like JSON and exported files, always uses ISO 8601 dates and plain numbers.
+#[derive(Debug, Clone)]
+pub struct Locale {
+	/// The date format, as `chrono` format string.
+	date_format: &'static str,
+
+	/// The separator between the integer and fractional part of numbers.
+	decimal_sep: String,
+
+	/// The separator between groups of thousands, possibly empty.
+	thousands_sep: String,
+}
+
+impl Default for Locale {
+	fn default() -> Self {
+		Self {
+			date_format: "%Y-%m-%d",
+			decimal_sep: String::from("."),
+			thousands_sep: String::new(),
+		}
+	}
+}
+
+impl Locale {
+	/// Get the locale from the settings of a user.
+	///
+	/// Settings that are missing or unknown fall back to ISO 8601 dates and a decimal point.
+	pub fn from_user(user: &types::User) -> Self {
+		let default = Self::default();
+		Self {
+			date_format: user.date_format.strftime().unwrap_or(default.date_format),
+			decimal_sep: Some(user.decimal_sep.clone()).filter(|x| !x.is_empty()).unwrap_or(default.decimal_sep),
+			thousands_sep: user.thousands_sep.clone(),
+		}
+	}
+
+	/// Format a date.
+	pub fn date(&self, date: chrono::NaiveDate) -> String {
+		date.format(self.date_format).to_string()
+	}
+
+	/// Format a number with a fixed number of decimals.
+	pub fn number(&self, value: f64, decimals: usize) -> String {
+		let formatted = format!("{:.*}", decimals, value.abs());
+		let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+
+		let mut result = String::new();
+		if value < 0.0 && formatted.chars().any(|x| x.is_ascii_digit() && x != '0') {
+			result.push('-');
+		}
+		for (i, digit) in integer.chars().enumerate() {
+			if i > 0 && (integer.len() - i) % 3 == 0 {
+				result.push_str(&self.thousands_sep);
+			}
+			result.push(digit);
+		}
+		if !fraction.is_empty() {
+			result.push_str(&self.decimal_sep);
+			result.push_str(fraction);
+		}
+		result
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn locale(decimal_sep: &str, thousands_sep: &str) -> Locale {
+		Locale {
+			date_format: "%Y-%m-%d",
+			decimal_sep: decimal_sep.into(),
+			thousands_sep: thousands_sep.into(),
+		}
+	}
+
+	#[test]
+	fn number_with_separators() {
+		assert_eq!(locale(".", ",").number(1234567.891, 2), "1,234,567.89");
+		assert_eq!(locale(",", ".").number(1234567.891, 2), "1.234.567,89");
+		assert_eq!(locale(".", "").number(1234567.891, 2), "1234567.89");
+		assert_eq!(locale(",", ".").number(123.0, 0), "123");
+	}
+
+	#[test]
+	fn negative_number() {
+		assert_eq!(locale(",", ".").number(-1234.5, 2), "-1.234,50");
+		assert_eq!(locale(".", ",").number(-0.5, 1), "-0.5");
+	}
+
+	#[test]
+	fn negative_number_rounded_to_zero() {
+		assert_eq!(locale(".", ",").number(-0.001, 2), "0.00");
+		assert_eq!(locale(".", ",").number(-0.4, 0), "0");
+	}
+}
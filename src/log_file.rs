@@ -0,0 +1,150 @@
+//! Logging to the console and optionally to a rotated log file.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Rotate the log file when it grows beyond this size in bytes.
+const MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// The number of rotated log files to keep.
+const KEEP_ROTATED: usize = 3;
+
+/// The number of warnings logged by this crate so far.
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/// Get the number of warnings logged by this crate so far, including warnings hidden from the console.
+pub fn warning_count() -> usize {
+	WARNINGS.load(Ordering::Relaxed)
+}
+
+/// A logger that writes to the console and to a log file.
+///
+/// It also counts the warnings logged by this crate.
+pub struct Logger {
+	/// The console logger.
+	pub console: env_logger::Logger,
+
+	/// The log file, if any.
+	pub file: Option<LogFile>,
+}
+
+impl log::Log for Logger {
+	fn enabled(&self, metadata: &log::Metadata) -> bool {
+		self.console.enabled(metadata) || self.file.as_ref().map(|x| x.enabled(metadata)).unwrap_or(false)
+	}
+
+	fn log(&self, record: &log::Record) {
+		if record.level() == log::Level::Warn && record.target().starts_with("uurlog_paymo") {
+			WARNINGS.fetch_add(1, Ordering::Relaxed);
+		}
+		self.console.log(record);
+		if let Some(file) = &self.file {
+			file.log(record);
+		}
+	}
+
+	fn flush(&self) {
+		self.console.flush();
+		if let Some(file) = &self.file {
+			file.flush();
+		}
+	}
+}
+
+/// A log file that is rotated when it grows too large.
+pub struct LogFile {
+	/// The path of the log file.
+	path: PathBuf,
+
+	/// The maximum level of messages to write.
+	level: log::LevelFilter,
+
+	/// The open file and its current size.
+	file: Mutex<(File, u64)>,
+}
+
+impl LogFile {
+	/// Open a log file for appending, rotating it first if it is too large.
+	pub fn open(path: impl Into<PathBuf>, level: log::LevelFilter) -> std::io::Result<Self> {
+		let path = path.into();
+		let (file, size) = open_append(&path)?;
+		let log_file = Self {
+			path,
+			level,
+			file: Mutex::new((file, size)),
+		};
+		if size > MAX_SIZE {
+			let mut file = log_file.file.lock().unwrap();
+			log_file.rotate(&mut file)?;
+		}
+		Ok(log_file)
+	}
+
+	/// Get the maximum level of messages written to the file.
+	pub fn level(&self) -> log::LevelFilter {
+		self.level
+	}
+
+	fn enabled(&self, metadata: &log::Metadata) -> bool {
+		metadata.level() <= self.level && metadata.target().starts_with("uurlog_paymo")
+	}
+
+	fn log(&self, record: &log::Record) {
+		if !self.enabled(record.metadata()) {
+			return;
+		}
+		let line = format!(
+			"{} {:<5} {}: {}\n",
+			chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+			record.level(),
+			record.target(),
+			record.args(),
+		);
+
+		let mut file = self.file.lock().unwrap();
+		if file.1 + line.len() as u64 > MAX_SIZE {
+			if let Err(e) = self.rotate(&mut file) {
+				eprintln!("failed to rotate log file {}: {}", self.path.display(), e);
+			}
+		}
+		// There is nowhere to report write errors to, except the console.
+		match file.0.write_all(line.as_bytes()) {
+			Ok(()) => file.1 += line.len() as u64,
+			Err(e) => eprintln!("failed to write to log file {}: {}", self.path.display(), e),
+		}
+	}
+
+	fn flush(&self) {
+		let _ = self.file.lock().unwrap().0.flush();
+	}
+
+	/// Move the current log file to `<path>.1`, shifting older files, and start a new file.
+	fn rotate(&self, file: &mut (File, u64)) -> std::io::Result<()> {
+		for i in (1..KEEP_ROTATED).rev() {
+			let from = rotated_path(&self.path, i);
+			if from.exists() {
+				std::fs::rename(&from, rotated_path(&self.path, i + 1))?;
+			}
+		}
+		std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+		*file = open_append(&self.path)?;
+		Ok(())
+	}
+}
+
+/// Open a file for appending, and get its current size.
+fn open_append(path: &Path) -> std::io::Result<(File, u64)> {
+	let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+	let size = file.metadata()?.len();
+	Ok((file, size))
+}
+
+/// Get the path of a rotated log file.
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+	let mut path = path.as_os_str().to_owned();
+	path.push(format!(".{}", index));
+	path.into()
+}
@@ -1,255 +1,3789 @@
-use std::collections::BTreeMap;
+use futures::StreamExt;
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 use structopt::clap;
 
 mod api_client;
+mod budget;
+mod calendar;
+mod cassette;
+mod checks;
+mod config;
+mod convert;
+mod entry;
+mod expenses;
+mod export;
+mod fingerprint;
+mod git;
+mod http_log;
+mod i18n;
+mod import;
+mod journal;
+mod lint;
+mod locale;
+mod log_file;
+mod mock_server;
 mod parse_tasks;
 mod partial_date;
+mod rate_limiter;
+mod redact;
+mod report;
+mod serve;
+mod summary;
+mod task_ids;
+mod timer;
+mod timezone;
 mod types;
+mod workspace;
 
 use api_client::ApiClient;
+use config::Config;
+use entry::Entry;
+use i18n::tr;
+use locale::Locale;
 use partial_date::PartialDate;
+use rate_limiter::RateLimiter;
 
 #[derive(StructOpt)]
 #[structopt(setting = clap::AppSettings::DeriveDisplayOrder)]
 #[structopt(setting = clap::AppSettings::UnifiedHelpMessage)]
 #[structopt(setting = clap::AppSettings::ColoredHelp)]
 #[structopt(group = clap::ArgGroup::with_name("action").required(true))]
+#[structopt(group = clap::ArgGroup::with_name("task-mapping").multiple(true))]
 struct Options {
 	#[structopt(long, short)]
 	#[structopt(parse(from_occurrences))]
 	verbose: i8,
 
-	/// Synchronize logged hours to Paymo.
+	/// Do not show entry descriptions in log messages.
+	#[structopt(long)]
+	redact_descriptions: bool,
+
+	/// Also write log messages to this file.
+	///
+	/// The file is rotated when it grows beyond 10 MiB, keeping the last 3 rotated files.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	log_file: Option<PathBuf>,
+
+	/// The maximum level of messages to write to the log file: error, warn, info, debug or trace.
+	#[structopt(long)]
+	#[structopt(value_name = "LEVEL")]
+	#[structopt(default_value = "debug")]
+	log_file_level: log::LevelFilter,
+
+	/// Exit with an error if any warnings were logged, even if everything else succeeded.
+	#[structopt(long)]
+	fail_on_warn: bool,
+
+	/// Synchronize logged hours from one or more hour logs to Paymo.
+	///
+	/// Without files, the hour logs matching `hours` in the general section of the configuration file are synchronized.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	#[structopt(min_values = 0)]
+	#[structopt(requires = "task-mapping")]
+	#[structopt(group = "action")]
+	sync: Option<Vec<PathBuf>>,
+
+	/// Synchronize the hour logs of all users in the `[[user]]` sections of the configuration file.
+	///
+	/// The API token must have permission to add and delete entries for these users.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	#[structopt(requires = "config")]
+	sync_team: bool,
+
+	/// Synchronize expenses like receipts and travel costs from one or more expense files to Paymo.
+	///
+	/// Expense files are CSV files with the columns date, amount, tags and description, with tags separated by spaces.
+	/// Expenses are mapped to a project with the project tags of the configuration file, or with the project of the task their tags map to.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	#[structopt(min_values = 1)]
+	#[structopt(requires = "config")]
+	#[structopt(group = "action")]
+	sync_expenses: Option<Vec<PathBuf>>,
+
+	/// The period to synchronize or report on.
+	///
+	/// Either a year (YYYY), a month (YYYY-MM), a day (YYYY-MM-DD), an ISO week (YYYY-Www),
+	/// or one of today, yesterday, this-week, last-week, this-month, last-month, this-year or last-year.
+	/// Defaults to `default_period` in the general section of the configuration file.
+	#[structopt(value_name = "PERIOD")]
+	#[structopt(long)]
+	#[structopt(parse(try_from_str = validate_period))]
+	period: Option<String>,
+
+	/// Print what would be done, without changing any entries on Paymo.
+	///
+	/// With --timer-start and --timer-stop, the timer state file is not changed either.
+	#[structopt(long)]
+	dry_run: bool,
+
+	/// Continue with the remaining changes when a change fails.
+	#[structopt(long)]
+	keep_going: bool,
+
+	/// Synchronize a year one month at a time, stopping at the first month that fails.
+	///
+	/// Each month is recorded as a separate run in the journal.
+	#[structopt(long)]
+	#[structopt(requires = "sync")]
+	split_months: bool,
+
+	/// Only add new entries and update descriptions, without deleting entries from Paymo.
+	#[structopt(long)]
+	#[structopt(conflicts_with = "delete-only")]
+	add_only: bool,
+
+	/// Only delete entries from Paymo that are not in the hour logs, without adding or updating entries.
+	#[structopt(long)]
+	delete_only: bool,
+
+	/// Refuse to delete more than this many entries from Paymo in a single run.
+	///
+	/// With --split-months, the limit applies to all months together.
+	#[structopt(long)]
+	#[structopt(value_name = "N")]
+	max_deletions: Option<usize>,
+
+	/// Only warn about entries with overlapping clock times, instead of refusing to synchronize.
+	#[structopt(long)]
+	allow_overlap: bool,
+
+	/// Only warn about identical entries in different hour logs, instead of refusing to synchronize.
+	#[structopt(long)]
+	allow_duplicates: bool,
+
+	/// Merge entries on the same day with the same tags and description into a single entry.
+	#[structopt(long)]
+	merge_entries: bool,
+
+	/// What to do with entries that can not be mapped to a task: skip, warn or error.
+	///
+	/// Skipped entries are listed after the synchronization.
+	#[structopt(long)]
+	#[structopt(value_name = "MODE")]
+	#[structopt(default_value = "error")]
+	unmapped: task_ids::UnmappedPolicy,
+
+	/// Only synchronize entries with one of these tags, can be given multiple times.
+	///
+	/// Entries on Paymo are only deleted or updated in the tasks of the selected entries.
+	#[structopt(long)]
+	#[structopt(value_name = "TAG")]
+	#[structopt(number_of_values = 1)]
+	only_tags: Vec<String>,
+
+	/// Do not synchronize entries with any of these tags, can be given multiple times.
+	///
+	/// Entries on Paymo are only deleted or updated in the tasks of the selected entries.
+	#[structopt(long)]
+	#[structopt(value_name = "TAG")]
+	#[structopt(number_of_values = 1)]
+	exclude_tags: Vec<String>,
+
+	/// Synchronize or add entries dated in the future, instead of refusing to.
+	#[structopt(long)]
+	allow_future: bool,
+
+	/// Change or add entries older than `general.max_entry_age_days`, instead of refusing to.
+	#[structopt(long)]
+	allow_old: bool,
+
+	/// Assign yourself to tasks you are not assigned to before adding entries to them.
+	#[structopt(long)]
+	auto_assign: bool,
+
+	/// Allow up to this many changes to be in flight at the same time.
+	///
+	/// New changes are still started at most once per second.
+	#[structopt(long)]
+	#[structopt(value_name = "N")]
+	#[structopt(default_value = "1")]
+	#[structopt(parse(try_from_str = parse_concurrency))]
+	concurrency: usize,
+
+	/// Upload entries with a start and end time if the description starts with a time range.
+	///
+	/// The time range must be formatted as HH:MM-HH:MM and is removed from the description.
+	/// Entries without a time range are uploaded with only a date and duration.
+	/// For other input formats, the start and end times are taken from the imported intervals instead.
+	/// If the start and end time do not match the logged hours, the duration is taken from the start and end time.
+	#[structopt(long)]
+	clock_times: bool,
+
+	/// The format of the hour logs: uurlog, csv, toggl, clockify, timew, org or timeclock.
+	///
+	/// CSV files are read with the column mapping in the `[csv]` section of the configuration file.
+	#[structopt(long)]
+	#[structopt(value_name = "FORMAT")]
+	#[structopt(default_value = "uurlog")]
+	input_format: import::InputFormat,
+
+	/// Consider durations equal if they differ by at most this many seconds.
+	///
+	/// Useful for entries created with the Paymo timer, which have durations with second precision.
+	/// Entries on Paymo with a whole number of minutes always match local durations within 59 seconds.
+	#[structopt(long)]
+	#[structopt(value_name = "SECONDS")]
+	#[structopt(default_value = "0")]
+	duration_tolerance: u32,
+
+	/// Interpret dates in this timezone instead of the timezone of the Paymo user.
+	///
+	/// Also used to convert the UTC timestamps of timewarrior exports to local times.
+	/// Without it, they are converted to the timezone of the Paymo user when synchronizing,
+	/// and to the timezone of the system otherwise.
+	/// Relative periods like `today` are also resolved in this timezone, or in the timezone of the system without it.
+	#[structopt(long)]
+	#[structopt(value_name = "TIMEZONE")]
+	timezone: Option<chrono_tz::Tz>,
+
+	/// Read the configuration, including the tag to task ID mapping, from this TOML file.
+	#[structopt(long, short)]
+	#[structopt(value_name = "FILE")]
+	#[structopt(group = "task-mapping")]
+	config: Option<PathBuf>,
+
+	/// Read tag to task ID mappings from a file with one "tag = ID" line per task.
+	///
+	/// Deprecated: use [[Task]] sections in the configuration file instead.
+	/// The mappings are added to the task mappings of the configuration file.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	#[structopt(group = "task-mapping")]
+	task_ids: Option<PathBuf>,
+
+	/// Compare tags case insensitively when looking up task IDs.
+	#[structopt(long)]
+	ignore_tag_case: bool,
+
+	/// Apply unicode normalization to tags when looking up task IDs.
+	#[structopt(long)]
+	normalize_tag_unicode: bool,
+
+	/// List all non-completed tasks for active projects.
+	///
+	/// Use --all or --completed to list completed tasks too.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	list_tasks: bool,
+
+	/// List both completed and non-completed tasks.
+	#[structopt(long)]
+	#[structopt(conflicts_with = "completed")]
+	all: bool,
+
+	/// List only completed tasks.
+	#[structopt(long)]
+	completed: bool,
+
+	/// Show the budget and logged hours of projects and tasks in --list-tasks.
+	#[structopt(long)]
+	budgets: bool,
+
+	/// Show budgeted and logged hours for all tasks in the configuration.
+	///
+	/// Exits with a non-zero status if any task or project exceeds the failure threshold.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	#[structopt(requires = "config")]
+	check_budget: bool,
+
+	/// Report tasks and projects as close to their budget at this percentage of the budget.
+	#[structopt(long)]
+	#[structopt(value_name = "PERCENT")]
+	#[structopt(default_value = "80")]
+	budget_warn_threshold: f64,
+
+	/// Report tasks and projects as over budget above this percentage of the budget.
+	#[structopt(long)]
+	#[structopt(value_name = "PERCENT")]
+	#[structopt(default_value = "100")]
+	budget_fail_threshold: f64,
+
+	/// Show billable and non-billable hours per client for a period.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	billable_report: bool,
+
+	/// Estimate the earnings per client for a period from the hours on Paymo, before invoicing.
+	///
+	/// The hours on billable tasks are multiplied by the price per hour of the task, its project, your user or the company, in that order.
+	/// Entries on tasks without a price per hour are listed, but not counted.
+	/// Use --client-id to only show one client.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	earnings: bool,
+
+	/// Compare the totals per day and per task of an hour log with the entries on Paymo.
+	///
+	/// Does not change any entries on Paymo.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	#[structopt(group = "action")]
+	#[structopt(requires = "config")]
+	reconcile: Option<PathBuf>,
+
+	/// List working days in a period without any hours on Paymo.
+	///
+	/// If a file is given, also list working days without any hours in the hour log.
+	/// Holidays are read from the configuration file.
+	/// Weekend days are read from the configuration file or from the Paymo company settings.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	#[structopt(group = "action")]
+	missing_days: Option<Option<PathBuf>>,
+
+	/// Show a heatmap of the logged hours per day in a period.
+	///
+	/// If a file is given, the hours are read from the hour log instead of from Paymo.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	#[structopt(group = "action")]
+	heatmap: Option<Option<PathBuf>>,
+
+	/// Export the time entries on Paymo for a period to a file.
+	///
+	/// Use `-` to write to standard output.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	#[structopt(group = "action")]
+	export: Option<PathBuf>,
+
+	/// Render a template with the hours on Paymo for a period, aggregated per client, project, task and day.
+	///
+	/// Templates use the Tera syntax, see https://keats.github.io/tera/docs/.
+	/// The result is written to --output.
+	#[structopt(long)]
+	#[structopt(value_name = "TEMPLATE")]
+	#[structopt(group = "action")]
+	render: Option<PathBuf>,
+
+	/// Show the hours on Paymo for a period per client, project, task and day.
+	///
+	/// The report is written to --output, as text, as a standalone HTML page with --format html,
+	/// or as Markdown tables with --format markdown.
+	/// If hour logs are configured, entries in the period with unmapped tags are listed too.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	report: bool,
+
+	/// Create a draft invoice on Paymo for your unbilled entries on billable tasks of a client in a period, with one line per task.
+	///
+	/// The rate of a line is the price per hour of the task, its project, your user or the company, in that order.
+	/// The invoice is never sent, and the entries are not marked as billed, so review and finish the invoice on Paymo.
+	/// The invoice is titled with the period, and a second invoice for the same client and period is refused unless the first one is void.
+	/// Use --dry-run to only show the lines.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	#[structopt(requires = "client-id")]
+	create_invoice: bool,
+
+	/// The output format: ics, csv or json for --export, text or config for --list-tasks, text or diagnostics for --lint, text, html or markdown for --report.
+	///
+	/// The config format prints a `[[Task]]` block for each task, ready to paste in the configuration file.
+	#[structopt(long)]
+	#[structopt(value_name = "FORMAT")]
+	format: Option<String>,
+
+	/// List the time entries on Paymo for a period.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	list_entries: bool,
+
+	/// Check if the Paymo API is reachable and accepts the API token.
+	///
+	/// Prints the latency and rate limit headers, and exits with a non-zero status if the request fails.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	ping: bool,
+
+	/// Search for tasks with a task, project or client name similar to a query.
+	#[structopt(long)]
+	#[structopt(value_name = "QUERY")]
+	#[structopt(group = "action")]
+	find_task: Option<String>,
+
+	/// Show the Paymo user of the API token.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	whoami: bool,
+
+	/// List clients with their number of projects.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	list_clients: bool,
+
+	/// List projects with their client and budget.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	list_projects: bool,
+
+	/// List archived projects instead of active projects, or include archived clients.
+	#[structopt(long)]
+	archived: bool,
+
+	/// Only list projects with a name containing this text.
+	#[structopt(long)]
+	#[structopt(value_name = "TEXT")]
+	name: Option<String>,
+
+	/// Only list entries or projects for this client, or the client to invoice with --create-invoice.
+	#[structopt(long)]
+	#[structopt(value_name = "ID")]
+	client_id: Option<u64>,
+
+	/// Only list or synchronize entries for this project.
+	///
+	/// With --sync, entries on Paymo in other projects are left alone.
+	#[structopt(long)]
+	#[structopt(value_name = "ID")]
+	project_id: Option<u64>,
+
+	/// Only list or synchronize entries for this task, or move the entry to this task with --edit-entry.
+	///
+	/// With --sync, entries on Paymo in other tasks are left alone.
+	#[structopt(long)]
+	#[structopt(value_name = "ID")]
+	task_id: Option<u64>,
+
+	/// Print listings as JSON instead of human readable text.
+	#[structopt(long)]
+	json: bool,
+
+	/// Show statistics about the logged hours per tag in one or more hour logs.
+	///
+	/// If a configuration file is given, also show statistics per task.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	#[structopt(min_values = 1)]
+	#[structopt(group = "action")]
+	stats: Option<Vec<PathBuf>>,
+
+	/// Show a timesheet with the hours per task and per day on Paymo for a period.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	timesheet: bool,
+
+	/// Check the configuration file for errors, without contacting Paymo.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	#[structopt(requires = "config")]
+	check_config: bool,
+
+	/// Check one or more hour logs for syntax errors, unmapped tags and overlapping entries, without contacting Paymo.
+	///
+	/// Problems that would stop a synchronization are errors, and make the command fail.
+	/// Tags are only checked if a configuration file is given.
+	/// Use `--format diagnostics` for output that editors can parse.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	#[structopt(min_values = 1)]
+	#[structopt(group = "action")]
+	lint: Option<Vec<PathBuf>>,
+
+	/// Check the staged hour logs in a git repository, for use as a pre-commit hook.
+	///
+	/// The staged contents of files matching `hours` in the general section of the configuration file are checked like --lint.
+	/// Use --pre-commit-instructions to see how to install the hook.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	#[structopt(requires = "config")]
+	pre_commit: bool,
+
+	/// Also check the hour logs against Paymo with --pre-commit.
+	///
+	/// This looks up the default tasks of clients, and checks that entries are mapped to existing tasks.
+	/// The API token is read from the --profile or from --token.
+	/// If Paymo can not be reached, the hour logs are only checked offline.
+	#[structopt(long)]
+	check_server: bool,
+
+	/// Print instructions to install --pre-commit as a git pre-commit hook, with the current options.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	#[structopt(requires = "config")]
+	pre_commit_instructions: bool,
+
+	/// Convert one or more hour logs to another format, without contacting Paymo.
+	///
+	/// The input format is set with --input-format, and the output format with --output-format.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	#[structopt(min_values = 1)]
+	#[structopt(group = "action")]
+	convert: Option<Vec<PathBuf>>,
+
+	/// Run a mock Paymo server with in-memory data on this address, for development and testing.
+	///
+	/// Other commands can use it with `--api-root http://ADDRESS/api`, and any API token is accepted.
+	#[structopt(long)]
+	#[structopt(value_name = "ADDRESS")]
+	#[structopt(group = "action")]
+	mock_server: Option<std::net::SocketAddr>,
+
+	/// Serve a local HTTP API on this address to plan and apply synchronizations of the configured profiles.
+	///
+	/// The endpoints are `GET /status`, `GET /plan?profile=NAME&period=PERIOD` and `POST /apply?profile=NAME&period=PERIOD`.
+	/// The hour logs are taken from `hours` in the configuration file.
+	/// Without --profile, all profiles in the configuration file are served.
+	/// A new token is printed at startup, which requests must send as `Authorization: Bearer TOKEN` header.
+	#[structopt(long)]
+	#[structopt(value_name = "ADDRESS")]
+	#[structopt(group = "action")]
+	#[structopt(requires = "config")]
+	serve: Option<std::net::SocketAddr>,
+
+	/// Allow --serve to listen on a non-local address, and accept requests for non-local hosts and origins.
+	///
+	/// Requests still need the token, but anyone who knows it can change entries on Paymo.
+	#[structopt(long)]
+	#[structopt(requires = "serve")]
+	serve_allow_remote: bool,
+
+	/// Serve the API of --serve as JSON-RPC on standard input and output, for editor integrations.
+	///
+	/// Each request and response is a single line of JSON.
+	/// Besides `status`, `plan` and `apply`, the methods `resolve-tag` and `validate-entries` check tags and hour logs.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	#[structopt(requires = "config")]
+	stdio: bool,
+
+	/// Load the data for --mock-server from a JSON file, instead of using a small example workspace.
+	///
+	/// The file has the same format as the API responses, with `users`, `company`, `clients`, `projects`, `tasks` and `entries`.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	#[structopt(requires = "mock-server")]
+	mock_data: Option<PathBuf>,
+
+	/// Write converted entries, the rendered template or the report to this file, or to standard output for `-`.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	#[structopt(default_value = "-")]
+	output: PathBuf,
+
+	/// The format to convert entries to: uurlog, csv or json.
+	///
+	/// CSV files are written with the column mapping in the `[csv]` section of the configuration file.
+	#[structopt(long)]
+	#[structopt(value_name = "FORMAT")]
+	#[structopt(default_value = "json")]
+	output_format: convert::OutputFormat,
+
+	/// Add a single entry with this description to Paymo.
+	///
+	/// The task is looked up from the tags given with --tag, using the configuration file.
+	#[structopt(long)]
+	#[structopt(value_name = "DESCRIPTION")]
+	#[structopt(group = "action")]
+	#[structopt(requires_all = &["config", "date", "hours"])]
+	add: Option<String>,
+
+	/// The date of the entry for --add or --edit-entry.
+	#[structopt(long)]
+	#[structopt(value_name = "YYYY-MM-DD")]
+	date: Option<chrono::NaiveDate>,
+
+	/// The duration of the entry for --add or --edit-entry, as decimal hours or as H:MM.
+	#[structopt(long)]
+	#[structopt(value_name = "HOURS")]
+	#[structopt(parse(try_from_str = import::parse_duration))]
+	hours: Option<u32>,
+
+	/// A tag of the entry for --add, can be given multiple times.
+	#[structopt(long)]
+	#[structopt(value_name = "TAG")]
+	#[structopt(number_of_values = 1)]
+	tag: Vec<String>,
+
+	/// Start a timer on the task for the given tags.
+	///
+	/// By default, a running timer entry is created on Paymo.
+	#[structopt(long)]
+	#[structopt(value_name = "TAG")]
+	#[structopt(min_values = 1)]
+	#[structopt(group = "action")]
+	#[structopt(requires = "config")]
+	timer_start: Option<Vec<String>>,
+
+	/// Stop the running timer.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	timer_stop: bool,
+
+	/// The description of the entry for --timer-start or --edit-entry.
+	#[structopt(long)]
+	#[structopt(value_name = "TEXT")]
+	description: Option<String>,
+
+	/// Only track the timer locally, and create the entry on Paymo when the timer is stopped.
+	#[structopt(long)]
+	local_timer: bool,
+
+	/// Keep track of the running timer in this file.
+	///
+	/// Defaults to `$XDG_STATE_HOME/uurlog-paymo/timer.json`.
 	#[structopt(long)]
 	#[structopt(value_name = "FILE")]
-	#[structopt(requires = "task-ids")]
-	#[structopt(requires = "period")]
+	timer_file: Option<PathBuf>,
+
+	/// Change the date, duration, task or description of a single entry on Paymo.
+	#[structopt(long)]
+	#[structopt(value_name = "ID")]
+	#[structopt(group = "action")]
+	edit_entry: Option<u64>,
+
+	/// Delete all your time entries on Paymo in a period.
+	///
+	/// Only lists the entries that would be deleted, unless --confirm is given.
+	/// Refuses to delete more than 100 entries unless --max-deletions is given,
+	/// and refuses to delete entries older than `general.max_entry_age_days` unless --allow-old is given.
+	#[structopt(long)]
+	#[structopt(group = "action")]
+	delete_period: bool,
+
+	/// Really delete the entries listed by --delete-period, or undo the changes listed by --undo-last-run.
+	#[structopt(long)]
+	confirm: bool,
+
+	/// Undo the changes of the last run that changed entries on the same Paymo API root, using the journal.
+	///
+	/// Added entries are deleted, and deleted entries are added again with a new ID.
+	/// Changed entries get back their task, date, duration and description.
+	/// Only lists the changes that would be undone, unless --confirm is given.
+	/// Like --delete-period, refuses to delete more than 100 entries unless --max-deletions is given,
+	/// and refuses to change entries older than `general.max_entry_age_days` unless --allow-old is given.
+	#[structopt(long)]
 	#[structopt(group = "action")]
-	sync: Option<PathBuf>,
+	undo_last_run: bool,
+
+	/// Record all changes to entries on Paymo in this journal file, for --undo-last-run.
+	///
+	/// Defaults to `$XDG_STATE_HOME/uurlog-paymo/journal.jsonl`.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	journal: Option<PathBuf>,
+
+	/// Read the Paymo API token from this file.
+	///
+	/// If no token or profile is given, the token is read from the terminal.
+	#[structopt(short, long)]
+	#[structopt(conflicts_with = "profile")]
+	token: Option<PathBuf>,
+
+	/// Use this Paymo API token.
+	///
+	/// Note that other users on the system may be able to see the command line of running processes.
+	#[structopt(long)]
+	#[structopt(value_name = "TOKEN")]
+	#[structopt(conflicts_with_all = &["token", "profile"])]
+	auth_token: Option<String>,
+
+	/// Use the API token and task mappings of a profile from the configuration file.
+	///
+	/// Can be given multiple times with --sync or --add to apply the same entries to several Paymo accounts.
+	#[structopt(long)]
+	#[structopt(number_of_values = 1)]
+	#[structopt(requires = "config")]
+	profile: Vec<String>,
+
+	/// Use this URL as the root for the Paymo API.
+	///
+	/// Overrides the `api_root` of a profile.
+	/// Defaults to https://app.paymoapp.com/api.
+	#[structopt(long)]
+	api_root: Option<String>,
+
+	/// Record all API responses to files in this directory.
+	///
+	/// With --profile, the responses for each profile are recorded in a subdirectory.
+	/// The recordings contain the full responses, but not the API token.
+	#[structopt(long)]
+	#[structopt(value_name = "DIR")]
+	#[structopt(conflicts_with = "replay")]
+	record: Option<PathBuf>,
+
+	/// Write all API requests and responses with their full bodies to this file, one JSON object per line.
+	///
+	/// The API token is not written to the file.
+	#[structopt(long)]
+	#[structopt(value_name = "FILE")]
+	http_log: Option<PathBuf>,
+
+	/// Replay API responses recorded with --record from this directory, instead of contacting Paymo.
+	///
+	/// No API token is needed, and relative periods and checks for future entries use the time of the recording.
+	/// Combine with --dry-run to reproduce the changes of a recorded --sync --dry-run.
+	#[structopt(long)]
+	#[structopt(value_name = "DIR")]
+	replay: Option<PathBuf>,
+
+	/// The time of the recording when replaying API responses.
+	#[structopt(skip)]
+	recorded_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+}
+
+/// The root URL of the Paymo API, if not overridden by a profile or on the command line.
+const DEFAULT_API_ROOT: &str = "https://app.paymoapp.com/api";
+
+/// The maximum number of entries to delete with --delete-period, if not overridden with --max-deletions.
+const DEFAULT_MAX_PERIOD_DELETIONS: usize = 100;
+
+impl Options {
+	/// Get the period to synchronize or report on.
+	fn period(&self) -> Result<PartialDate, Error> {
+		let period = self.period.as_deref()
+			.ok_or_else(|| Error::Failed(tr!("no period given, use --period or set default_period in the configuration file")))?;
+		PartialDate::parse_relative_to(period, self.today())
+			.map_err(|e| Error::Failed(tr!("invalid period {}: {}", period, e)))
+	}
+
+	/// Get the current date in the timezone given with --timezone, or in the local timezone.
+	fn today(&self) -> chrono::NaiveDate {
+		match self.timezone {
+			Some(timezone) => self.now().with_timezone(&timezone).date_naive(),
+			None => self.now().date_naive(),
+		}
+	}
+
+	/// Get the current time, or the time of the recording when replaying API responses.
+	fn now(&self) -> chrono::DateTime<chrono::FixedOffset> {
+		self.recorded_at.unwrap_or_else(|| chrono::Local::now().into())
+	}
+
+	/// Get the tag normalization options.
+	fn tag_normalization(&self) -> task_ids::TagNormalization {
+		task_ids::TagNormalization {
+			ignore_case: self.ignore_tag_case,
+			unicode: self.normalize_tag_unicode,
+		}
+	}
+
+	/// Get the options for checking hour logs.
+	fn lint_options(&self) -> lint::LintOptions {
+		lint::LintOptions {
+			unmapped: self.unmapped,
+			allow_overlap: self.allow_overlap,
+			existing_tasks: None,
+			unresolved_default_tasks: false,
+		}
+	}
+
+	/// Get the options for synchronizing or deleting entries.
+	fn sync_options(&self) -> SyncOptions {
+		SyncOptions {
+			dry_run: self.dry_run,
+			keep_going: self.keep_going,
+			add_only: self.add_only,
+			delete_only: self.delete_only,
+			max_deletions: self.max_deletions,
+			concurrency: self.concurrency,
+			timezone: self.timezone,
+			duration_tolerance: self.duration_tolerance,
+			tag_normalization: self.tag_normalization(),
+			auto_assign: self.auto_assign,
+			allow_overlap: self.allow_overlap,
+			allow_duplicates: self.allow_duplicates,
+			merge_entries: self.merge_entries,
+			unmapped: self.unmapped,
+			task_id: self.task_id,
+			project_id: self.project_id,
+			tag_filter: task_ids::TagFilter {
+				only: self.only_tags.clone(),
+				exclude: self.exclude_tags.clone(),
+			},
+			allow_future: self.allow_future,
+			allow_old: self.allow_old,
+			now: self.now().into(),
+		}
+	}
+}
+
+/// Options for synchronizing logged hours to Paymo.
+#[derive(Clone)]
+struct SyncOptions {
+	/// Print what would be done, without changing any entries on Paymo.
+	dry_run: bool,
+
+	/// Log and skip failed changes instead of aborting.
+	keep_going: bool,
+
+	/// Do not delete entries.
+	add_only: bool,
+
+	/// Do not add or update entries.
+	delete_only: bool,
+
+	/// The maximum number of entries to delete.
+	max_deletions: Option<usize>,
+
+	/// The maximum number of changes in flight at the same time.
+	concurrency: usize,
+
+	/// The timezone to interpret dates in, overriding the timezone of the Paymo user.
+	timezone: Option<chrono_tz::Tz>,
+
+	/// The maximum difference in seconds between durations of matching entries.
+	duration_tolerance: u32,
+
+	/// How to normalize tags before looking up task IDs.
+	tag_normalization: task_ids::TagNormalization,
+
+	/// Assign the user to tasks before adding entries to them.
+	auto_assign: bool,
+
+	/// Only warn about overlapping entries.
+	allow_overlap: bool,
+
+	/// Only warn about duplicate entries.
+	allow_duplicates: bool,
+
+	/// Merge entries on the same day with the same tags and description.
+	merge_entries: bool,
+
+	/// What to do with entries that can not be mapped to a task.
+	unmapped: task_ids::UnmappedPolicy,
+
+	/// Only synchronize entries for this task.
+	task_id: Option<u64>,
+
+	/// Only synchronize entries for this project.
+	project_id: Option<u64>,
+
+	/// Which entries to synchronize, based on their tags.
+	tag_filter: task_ids::TagFilter,
+
+	/// Allow entries dated in the future.
+	allow_future: bool,
+
+	/// Allow changes to entries older than the maximum entry age.
+	allow_old: bool,
+
+	/// The current time, or the time of the recording when replaying API responses.
+	now: chrono::DateTime<chrono::Utc>,
+}
+
+/// Error returned when a run did not fully succeed.
+///
+/// The error message is printed once, by `main`.
+#[derive(Debug)]
+enum Error {
+	/// The run was aborted.
+	Failed(String),
+
+	/// The run completed, but some changes could not be applied.
+	PartialFailure(String),
+}
+
+impl Error {
+	/// Get the process exit code for the error.
+	fn exit_code(&self) -> i32 {
+		match self {
+			Self::Failed(_) => 1,
+			Self::PartialFailure(_) => 2,
+		}
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::Failed(message) => f.write_str(message),
+			Self::PartialFailure(message) => f.write_str(message),
+		}
+	}
+}
+
+impl From<String> for Error {
+	fn from(message: String) -> Self {
+		Self::Failed(message)
+	}
+}
+
+#[tokio::main]
+async fn main() {
+	let options = Options::from_args();
+	let fail_on_warn = options.fail_on_warn;
+	let result = do_main(options).await
+		.and_then(|()| check_warnings(fail_on_warn));
+	if let Err(e) = result {
+		log::error!("{}", e);
+		std::process::exit(e.exit_code());
+	}
+}
+
+/// Fail if any warnings were logged and `--fail-on-warn` is given.
+fn check_warnings(fail_on_warn: bool) -> Result<(), Error> {
+	let warnings = log_file::warning_count();
+	if fail_on_warn && warnings > 0 {
+		return Err(Error::Failed(tr!("{} warnings were logged, failing because of --fail-on-warn", warnings)));
+	}
+	Ok(())
+}
+
+/// Parse the value of the `--concurrency` option.
+fn parse_concurrency(input: &str) -> Result<usize, String> {
+	match input.parse() {
+		Ok(0) => Err("concurrency must be at least 1".into()),
+		Ok(x) => Ok(x),
+		Err(e) => Err(format!("{}", e)),
+	}
+}
+
+/// Check the value of the `--period` option.
+///
+/// The period is kept as text, so that relative periods can be resolved from the time of a recording.
+fn validate_period(input: &str) -> Result<String, partial_date::ParsePartialDateError> {
+	PartialDate::validate(input)?;
+	Ok(input.to_owned())
+}
+
+/// Read a file to a string, with a potential final newline removed.
+fn read_file(path: impl AsRef<Path>) -> std::io::Result<String> {
+	let mut data = std::fs::read_to_string(path)?;
+	if data.ends_with('\n') {
+		data.pop();
+	}
+	Ok(data)
+}
+
+/// Initialize logging to the console, and optionally to a log file.
+///
+/// If the log file can not be opened, logging to the console is still initialized.
+///
+/// With `fail_on_warn`, warnings are always passed to the logger so they can be counted, even if they are hidden from the console.
+fn init_logging(verbosity: i8, log_file: Option<&Path>, log_file_level: log::LevelFilter, fail_on_warn: bool) -> Result<(), Error> {
+	let level = if verbosity <= -2 {
+		log::LevelFilter::Error
+	} else if verbosity == -1 {
+		log::LevelFilter::Warn
+	} else if verbosity == 0 {
+		log::LevelFilter::Info
+	} else if verbosity == 1 {
+		log::LevelFilter::Debug
+	} else {
+		log::LevelFilter::Trace
+	};
+
+	let console = env_logger::Builder::from_env("RUST_LOG").filter_module("uurlog_paymo", level).build();
+	let (file, result) = match log_file.map(|path| (path, log_file::LogFile::open(path, log_file_level))) {
+		None => (None, Ok(())),
+		Some((_, Ok(file))) => (Some(file), Ok(())),
+		Some((path, Err(e))) => (None, Err(Error::Failed(format!("failed to open log file {}: {}", path.display(), e)))),
+	};
+
+	let mut max_level = console.filter().max(file.as_ref().map(|x| x.level()).unwrap_or(log::LevelFilter::Off));
+	if fail_on_warn {
+		max_level = max_level.max(log::LevelFilter::Warn);
+	}
+	log::set_boxed_logger(Box::new(log_file::Logger { console, file }))
+		.expect("logger is only initialized once");
+	log::set_max_level(max_level);
+	result
+}
+
+async fn do_main(mut options: Options) -> Result<(), Error> {
+	init_logging(options.verbose, options.log_file.as_deref(), options.log_file_level, options.fail_on_warn)?;
+	redact::set_redact_descriptions(options.redact_descriptions);
+	i18n::set_language(i18n::Language::from_env());
+
+	let mut config = match &options.config {
+		Some(path) => Config::from_file(path)
+			.map_err(|e| tr!("failed to read configuration from {}: {}", path.display(), e))?,
+		None => Config::default(),
+	};
+	if let Some(path) = &options.task_ids {
+		log::warn!("{}", tr!("--task-ids is deprecated, move the mappings from {} to [[Task]] sections in the configuration file", path.display()));
+		let tasks = parse_tasks::read_task_ids(path)
+			.map_err(|e| tr!("failed to read task IDs from {}: {}", path.display(), e))?;
+		config.tasks.extend(tasks);
+	}
+	if let Some(language) = config.general.language {
+		i18n::set_language(language);
+	}
+
+	if options.period.is_none() {
+		// The period was already validated when reading the configuration.
+		options.period = config.general.default_period.clone();
+	}
+
+	let reader = import::Reader {
+		format: options.input_format,
+		clock_times: options.clock_times,
+		csv: config.csv.clone(),
+		midnight: config.general.midnight,
+		timezone: options.timezone,
+	};
+
+	// Converting files, checking hour logs or the configuration and running a mock server does not need the API.
+	if let Some(files) = &options.convert {
+		let period = match options.period {
+			Some(_) => Some(options.period()?),
+			None => None,
+		};
+		return convert(files, &reader, period, &options.output, options.output_format, &config);
+	} else if let Some(files) = &options.lint {
+		let format = parse_format(options.format.as_deref(), "text")?;
+		let config = match (&options.config, options.profile.as_slice()) {
+			(None, _) => None,
+			(Some(_), []) => Some(config),
+			(Some(_), [name]) => Some(config.for_profile(name)?.0),
+			(Some(_), _) => return Err(Error::Failed("--lint can only use a single profile".into())),
+		};
+		return lint(files, &reader, config.as_ref(), &options, format);
+	} else if options.pre_commit {
+		return pre_commit(&reader, &config, &options).await;
+	} else if options.pre_commit_instructions {
+		return pre_commit_instructions(&options);
+	} else if options.check_config {
+		return check_config(&config, options.tag_normalization());
+	} else if let Some(address) = options.mock_server {
+		let data = match &options.mock_data {
+			Some(path) => mock_server::Data::from_file(path)?,
+			None => mock_server::Data::example(),
+		};
+		return Ok(mock_server::run(address, data).await?);
+	}
+
+	let cassette = match (&options.record, &options.replay) {
+		(Some(directory), _) => {
+			let cassette = cassette::Cassette::record(directory);
+			cassette.save_info(options.now())?;
+			Some(cassette)
+		},
+		(None, Some(directory)) if !directory.is_dir() => {
+			return Err(Error::Failed(tr!("recording directory {} does not exist", directory.display())));
+		},
+		(None, Some(directory)) => {
+			let cassette = cassette::Cassette::replay(directory);
+			options.recorded_at = cassette.load_info()?;
+			if let Some(recorded_at) = options.recorded_at {
+				log::info!("Replaying API responses recorded at {}", recorded_at.format("%Y-%m-%d %H:%M:%S %:z"));
+			}
+			Some(cassette)
+		},
+		(None, None) => None,
+	};
+	let replay = cassette.as_ref().map(|x| x.is_replay()).unwrap_or(false);
+
+	// Replayed changes did not really happen, so they are not recorded in the journal.
+	let journal = match &options.journal {
+		_ if replay => None,
+		Some(path) => Some(journal::Journal::new(path)),
+		None => journal::default_path().map(journal::Journal::new),
+	};
+
+	let http_log = match &options.http_log {
+		Some(path) => {
+			let http_log = http_log::HttpLog::open(path)
+				.map_err(|e| format!("failed to open HTTP log {}: {}", path.display(), e))?;
+			Some(std::sync::Arc::new(http_log))
+		},
+		None => None,
+	};
+
+	if options.serve.is_some() || options.stdio {
+		let names: Vec<String> = if options.profile.is_empty() {
+			config.profile.keys().cloned().collect()
+		} else {
+			options.profile.clone()
+		};
+		if names.is_empty() {
+			return Err(Error::Failed(tr!("no profiles configured, add [profile.NAME] sections to the configuration file")));
+		}
+		let profiles = profile_clients(&options, &config, &names, cassette.as_ref(), journal.as_ref(), http_log.as_ref(), replay)?
+			.into_iter()
+			.map(|(name, config, api)| serve::Profile { name, config, api })
+			.collect();
+		let server = serve::Server::new(profiles, reader, options.sync_options(), options.period.clone(), options.recorded_at);
+		return match options.serve {
+			Some(address) => Ok(serve::run(address, server, options.serve_allow_remote).await?),
+			None => Ok(serve::run_stdio(server).await?),
+		};
+	}
+
+	if options.profile.is_empty() {
+		let token = match (&options.auth_token, &options.token) {
+			// Replayed responses do not need an API token.
+			_ if replay => String::new(),
+			(Some(token), _) => token.clone(),
+			(None, Some(token_path)) => read_token(token_path)?,
+			(None, None) => rpassword::prompt_password("Paymo API token: ")
+				.map_err(|e| format!("failed to read API token from terminal: {}", e))?,
+		};
+		let api = api_client(options.api_root.as_deref().unwrap_or(DEFAULT_API_ROOT), token, cassette, journal, http_log, &config);
+		return run_action(&options, &api, &config, &reader).await;
+	}
+
+	if options.profile.len() > 1 && options.sync.is_none() && options.add.is_none() && !options.undo_last_run {
+		return Err(Error::Failed("multiple profiles can only be used with --sync, --add or --undo-last-run".into()));
+	}
+
+	// Resolve all profiles first, so that a typo does not leave the accounts half synchronized.
+	let profiles = profile_clients(&options, &config, &options.profile, cassette.as_ref(), journal.as_ref(), http_log.as_ref(), replay)?;
+
+	let mut failed_profiles = Vec::new();
+	for (name, profile_config, api) in &profiles {
+		log::info!("Using profile {}", name);
+		if let Err(e) = run_action(&options, api, profile_config, &reader).await {
+			if profiles.len() == 1 {
+				return Err(e);
+			}
+			log::error!("{}", e);
+			failed_profiles.push(name.as_str());
+		}
+	}
+
+	if !failed_profiles.is_empty() {
+		return Err(Error::PartialFailure(tr!("failed to apply changes for {} of {} profiles: {}", failed_profiles.len(), profiles.len(), failed_profiles.join(", "))));
+	}
+	Ok(())
+}
+
+/// Get the configuration and an API client for each of the named profiles.
+fn profile_clients(
+	options: &Options,
+	config: &Config,
+	names: &[String],
+	cassette: Option<&cassette::Cassette>,
+	journal: Option<&journal::Journal>,
+	http_log: Option<&std::sync::Arc<http_log::HttpLog>>,
+	replay: bool,
+) -> Result<Vec<(String, Config, ApiClient)>, Error> {
+	let config_dir = options.config.as_ref().and_then(|x| x.parent()).unwrap_or_else(|| Path::new(""));
+	let mut profiles = Vec::with_capacity(names.len());
+	for name in names {
+		let (profile_config, profile) = config.for_profile(name)?;
+		let api_root = options.api_root.as_deref()
+			.or(profile.api_root.as_deref())
+			.unwrap_or(DEFAULT_API_ROOT);
+		let cassette = cassette.map(|x| x.subdirectory(name));
+		let token = if replay {
+			String::new()
+		} else {
+			read_token(&config_dir.join(&profile.token))?
+		};
+		let journal = journal.map(|x| x.for_profile(name));
+		let api = api_client(api_root, token, cassette, journal, http_log.cloned(), &profile_config);
+		profiles.push((name.clone(), profile_config, api));
+	}
+	Ok(profiles)
+}
+
+/// Read an API token from a file.
+fn read_token(token_path: &Path) -> Result<String, Error> {
+	read_file(token_path)
+		.map_err(|e| Error::Failed(tr!("failed to read token from {}: {}", token_path.display(), e)))
+}
+
+/// Get the hour logs matching the `hours` patterns in the configuration.
+fn configured_hour_logs(config: &Config) -> Result<Vec<PathBuf>, Error> {
+	if config.general.hours.is_empty() {
+		return Err(Error::Failed(tr!("no hour logs given, add them to --sync or set hours in the configuration file")));
+	}
+	let mut files = Vec::new();
+	for pattern in &config.general.hours {
+		let matches = config::expand_glob(pattern)
+			.map_err(|e| format!("invalid hours pattern {}: {}", pattern, e))?;
+		if matches.is_empty() {
+			log::warn!("{}", tr!("no hour logs found matching {}", pattern));
+		}
+		files.extend(matches);
+	}
+	if files.is_empty() {
+		return Err(Error::Failed(tr!("no hour logs found matching the hours in the configuration file")));
+	}
+	Ok(files)
+}
+
+/// Check the task mappings and other settings of the configuration.
+///
+/// Parse errors and invalid values are already reported when reading the configuration.
+/// This also checks for conflicting task mappings in the configuration and all profiles.
+fn check_config(config: &Config, normalization: task_ids::TagNormalization) -> Result<(), Error> {
+	let check_mappings = |config: &Config| -> Result<usize, String> {
+		let task_ids = config.task_ids(normalization)
+			.map_err(|e| format!("invalid task configuration: {}", e))?;
+		config.client_default_tasks()
+			.map_err(|e| format!("invalid client configuration: {}", e))?;
+		Ok(task_ids.all_task_ids().len())
+	};
+
+	let mapped = check_mappings(config)?;
+	println!("{} tasks mapped", mapped);
+	for name in config.profile.keys() {
+		let (profile_config, _) = config.for_profile(name)?;
+		let mapped = check_mappings(&profile_config)
+			.map_err(|e| format!("in profile {}: {}", name, e))?;
+		println!("profile {}: {} tasks mapped", name, mapped);
+	}
+	for user in &config.users {
+		glob::Pattern::new(&user.hours)
+			.map_err(|e| format!("invalid hours pattern for user {}: {}", user.email, e))?;
+	}
+	println!("configuration is valid");
+	Ok(())
+}
+
+/// Create an API client for a token, with the retry policy from the configuration.
+fn api_client(
+	api_root: &str,
+	auth_token: String,
+	cassette: Option<cassette::Cassette>,
+	journal: Option<journal::Journal>,
+	http_log: Option<std::sync::Arc<http_log::HttpLog>>,
+	config: &Config,
+) -> ApiClient {
+	ApiClient {
+		api_root: api_root.to_owned(),
+		auth_token,
+		user_id: None,
+		cassette: cassette.map(std::sync::Arc::new),
+		journal: journal.map(|x| std::sync::Arc::new(x.for_api_root(api_root))),
+		retry: config.http.retry.clone(),
+		http_log,
+	}
+}
+
+/// Run the selected action against a single Paymo account.
+async fn run_action(options: &Options, api: &ApiClient, config: &Config, reader: &import::Reader) -> Result<(), Error> {
+	if let Some(files) = &options.sync {
+		let files = if files.is_empty() {
+			configured_hour_logs(config)?
+		} else {
+			files.clone()
+		};
+		let period = options.period()?;
+		if options.split_months {
+			sync_months(api, &files, reader, config, period, &options.sync_options()).await
+		} else {
+			sync_to_paymo(api, &files, reader, config, &period, &options.sync_options()).await
+		}
+	} else if let Some(description) = &options.add {
+		let entry = Entry {
+			date: timezone::from_naive_date(options.date.unwrap())?,
+			seconds: options.hours.unwrap() * 60,
+			clock_times: None,
+			tags: options.tag.clone(),
+			description: description.clone(),
+			line: None,
+		};
+		add_single_entry(api, config, &entry, &options.sync_options()).await
+	} else if let Some(tags) = &options.timer_start {
+		let timer_file = timer_file(options)?;
+		timer_start(api, config, tags, &timer_file, options).await
+	} else if options.timer_stop {
+		let timer_file = timer_file(options)?;
+		timer_stop(api, &timer_file, options.dry_run).await
+	} else if let Some(entry_id) = options.edit_entry {
+		let update = api_client::EntryUpdate {
+			task_id: options.task_id,
+			date: options.date.map(timezone::from_naive_date).transpose()?,
+			duration: options.hours.map(|x| x * 60),
+			end_time: None,
+			description: options.description.clone(),
+		};
+		edit_entry(api, entry_id, &update, options.dry_run).await
+	} else if let Some(files) = &options.sync_expenses {
+		sync_expenses(api, files, config, &options.period()?, &options.sync_options()).await
+	} else if options.sync_team {
+		sync_team(api, reader, config, &options.period()?, &options.sync_options()).await
+	} else if options.delete_period {
+		let sync_options = SyncOptions {
+			dry_run: options.dry_run || !options.confirm,
+			..options.sync_options()
+		};
+		delete_period(api, config, &options.period()?, &sync_options).await
+	} else if options.undo_last_run {
+		let sync_options = SyncOptions {
+			dry_run: options.dry_run || !options.confirm,
+			..options.sync_options()
+		};
+		undo_last_run(api, config, &sync_options).await
+	} else if options.list_tasks {
+		let list_options = ListTasksOptions {
+			open: !options.completed,
+			completed: options.all || options.completed,
+			budgets: options.budgets,
+			format: parse_format(options.format.as_deref(), "text")?,
+		};
+		list_tasks(api, &list_options).await
+	} else if options.check_budget {
+		let thresholds = budget::Thresholds {
+			warn: options.budget_warn_threshold,
+			fail: options.budget_fail_threshold,
+		};
+		check_budget(api, config, thresholds).await
+	} else if let Some(file) = &options.reconcile {
+		reconcile(api, file, reader, config, &options.period()?, options.tag_normalization(), options.timezone).await
+	} else if let Some(file) = &options.missing_days {
+		missing_days(api, file.as_deref(), reader, config, &options.period()?, options.timezone).await
+	} else if let Some(file) = &options.heatmap {
+		heatmap(api, file.as_deref(), reader, &options.period()?, options.timezone).await
+	} else if let Some(file) = &options.export {
+		let format = parse_format(options.format.as_deref(), "ics")?;
+		export(api, file, format, &options.period()?, options.timezone).await
+	} else if let Some(template) = &options.render {
+		let period = options.period()?;
+		render(api, config, reader, options, Some(template), &period).await
+	} else if options.report {
+		let period = options.period()?;
+		render(api, config, reader, options, None, &period).await
+	} else if options.create_invoice {
+		let period = options.period()?;
+		create_invoice(api, options.client_id.unwrap(), &period, options.timezone, options.now().date_naive(), options.dry_run).await
+	} else if options.ping {
+		ping(api).await
+	} else if let Some(query) = &options.find_task {
+		find_task(api, query).await
+	} else if options.whoami {
+		whoami(api, options.json).await
+	} else if options.list_clients {
+		list_clients(api, options.archived, options.json).await
+	} else if options.list_projects {
+		let mut filter = api_client::ProjectsFilter::new().active(!options.archived);
+		if let Some(client_id) = options.client_id {
+			filter = filter.client_id(client_id);
+		}
+		if let Some(name) = &options.name {
+			filter = filter.name_like(format!("%{}%", name));
+		}
+		list_projects(api, filter, options.json).await
+	} else if options.list_entries {
+		let mut filter = api_client::TimeEntryFilter::new();
+		if let Some(client_id) = options.client_id {
+			filter = filter.client_id(client_id);
+		}
+		if let Some(project_id) = options.project_id {
+			filter = filter.project_id(project_id);
+		}
+		if let Some(task_id) = options.task_id {
+			filter = filter.task_id(task_id);
+		}
+		list_entries(api, filter, &options.period()?, options.timezone, options.json).await
+	} else if let Some(files) = &options.stats {
+		let config = options.config.as_ref().map(|_| config);
+		stats(api, files, reader, config, &options.period()?, options.tag_normalization()).await
+	} else if options.timesheet {
+		timesheet(api, &options.period()?, options.timezone).await
+	} else if options.billable_report {
+		billable_report(api, options.period()?, options.timezone).await
+	} else if options.earnings {
+		earnings(api, options.client_id, &options.period()?, options.timezone).await
+	} else {
+		unreachable!("no action selected");
+	}
+}
+
+/// Options for listing tasks.
+struct ListTasksOptions {
+	/// List tasks that are not completed.
+	open: bool,
+
+	/// List completed tasks.
+	completed: bool,
+
+	/// Show the budget and logged hours of projects and tasks.
+	budgets: bool,
+
+	/// The output format.
+	format: ListTasksFormat,
+}
+
+/// The output format for listing tasks.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ListTasksFormat {
+	/// A tree of clients, projects and tasks.
+	Text,
+
+	/// Task mappings for the configuration file.
+	Config,
+}
+
+impl std::str::FromStr for ListTasksFormat {
+	type Err = String;
+
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		match data {
+			"text" => Ok(Self::Text),
+			"config" => Ok(Self::Config),
+			_ => Err(format!("unknown format for listing tasks: {}, expected text or config", data)),
+		}
+	}
+}
+
+async fn list_tasks(api: &ApiClient, options: &ListTasksOptions) -> Result<(), Error> {
+	let mut clients = api.get_clients().await?;
+	clients.sort_by(|a, b| a.name.cmp(&b.name));
+
+	// Get all active projects, and index them by client ID.
+	let filter = api_client::ProjectsFilter::new().active(true);
+	let projects = api.get_projects_filtered(&filter).await?;
+	let projects_by_client_id = index_by(projects, |x| x.client_id);
+
+	// Get all tasks, and index them by project ID.
+	let tasks = api.get_tasks().await?;
+	let tasks_by_project_id = index_by(tasks, |x| x.project_id);
+
+	if options.format == ListTasksFormat::Config {
+		print_task_config(&clients, &projects_by_client_id, &tasks_by_project_id, options);
+		return Ok(());
+	}
+
+	let locale = if options.budgets {
+		user_locale(api).await
+	} else {
+		Locale::default()
+	};
+
+	// Print a tree of clients -> projects -> tasks.
+	for client in &clients {
+		let projects = projects_by_client_id.get(&client.id);
+		if let Some(projects) = projects {
+			println!("{} ({})", client.name, client.id);
+			for project in projects {
+				// Get the logged time per task of the project with a single request.
+				let logged = if options.budgets {
+					let filter = api_client::TimeEntryFilter::new().project_id(project.id);
+					let entries = api.get_time_entries(&filter).await?;
+					let mut logged = BTreeMap::<u64, u64>::new();
+					for entry in &entries {
+						*logged.entry(entry.task_id).or_default() += u64::from(entry.total_seconds());
+					}
+					Some(logged)
+				} else {
+					None
+				};
+				let budget = |budget: Option<f64>, seconds: u64| match (&logged, budget) {
+					(None, _) => String::new(),
+					(Some(_), Some(budget)) => format!(", {} of {} hours", locale.number(seconds as f64 / 3600.0, 2), locale.number(budget, 2)),
+					(Some(_), None) => format!(", {} hours", locale.number(seconds as f64 / 3600.0, 2)),
+				};
+
+				let project_seconds = logged.as_ref().map(|x| x.values().sum()).unwrap_or(0);
+				println!("  {} ({}){}", project.name, project.id, budget(project.budget_hours, project_seconds));
+				let tasks = tasks_by_project_id.get(&project.id).map(|x| x.as_slice()).unwrap_or_else(|| &[]);
+				for task in tasks {
+					let task_seconds = logged.as_ref().and_then(|x| x.get(&task.id)).copied().unwrap_or(0);
+					if task.complete && options.completed {
+						println!("    {} ({}, completed){}", task.name, task.id, budget(task.budget_hours, task_seconds));
+					} else if !task.complete && options.open {
+						println!("    {} ({}){}", task.name, task.id, budget(task.budget_hours, task_seconds));
+					}
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Print a `[[Task]]` block for each listed task, with a unique tag derived from the task name.
+fn print_task_config(
+	clients: &[types::Client],
+	projects_by_client_id: &BTreeMap<u64, Vec<types::Project>>,
+	tasks_by_project_id: &BTreeMap<u64, Vec<types::Task>>,
+	options: &ListTasksOptions,
+) {
+	let mut used_tags = std::collections::BTreeSet::new();
+	for client in clients {
+		for project in projects_by_client_id.get(&client.id).into_iter().flatten() {
+			let tasks = tasks_by_project_id.get(&project.id).into_iter().flatten()
+				.filter(|task| if task.complete { options.completed } else { options.open });
+			for task in tasks {
+				let tag = config::suggest_task_tag(&project.name, &task.name, task.id, &mut used_tags);
+				println!("# {} / {} / {}", client.name, project.name, task.name);
+				println!("[[Task]]");
+				println!("name = {}", toml::Value::String(tag));
+				println!("id = {}", task.id);
+				println!();
+			}
+		}
+	}
+}
+
+/// Parse an output format, or use a default if no format was given.
+fn parse_format<T: std::str::FromStr<Err = String>>(format: Option<&str>, default: &str) -> Result<T, Error> {
+	let format = format.unwrap_or(default)
+		.parse()?;
+	Ok(format)
+}
+
+/// Show budgeted and logged hours for all tasks in the configuration.
+async fn check_budget(api: &ApiClient, config: &Config, thresholds: budget::Thresholds) -> Result<(), Error> {
+	let task_ids = config.task_ids(Default::default())
+		.map_err(|e| format!("invalid task configuration: {}", e))?;
+	let client_default_tasks = config.client_default_tasks()
+		.map_err(|e| format!("invalid client configuration: {}", e))?;
+
+	let mut all_task_ids = task_ids.all_task_ids();
+	all_task_ids.extend(client_default_tasks.values());
+
+	let locale = user_locale(api).await;
+	let over_budget = budget::check_budget(api, all_task_ids, thresholds, &locale).await
+		.map_err(|e| format!("failed to check budgets: {}", e))?;
+	if over_budget {
+		return Err(Error::Failed(tr!("one or more tasks or projects are over budget")));
+	}
+	Ok(())
+}
+
+/// Read the entries of an hour log within a period.
+fn read_entries(file: &Path, period: &std::ops::Range<uurlog::Date>, reader: &import::Reader) -> Result<Vec<Entry>, Error> {
+	let entries = reader.read(file)
+		.map_err(|e| format!("failed to read {}: {}", file.display(), e))?;
+
+	Ok(entries
+		.into_iter()
+		.filter(|entry| period.contains(&entry.date))
+		.collect())
+}
+
+/// Convert hour logs to another format, optionally limited to a period.
+fn convert(
+	files: &[PathBuf],
+	reader: &import::Reader,
+	period: Option<PartialDate>,
+	output: &Path,
+	format: convert::OutputFormat,
+	config: &Config,
+) -> Result<(), Error> {
+	let mut entries = Vec::new();
+	for file in files {
+		let file_entries = reader.read(file)
+			.map_err(|e| format!("failed to read {}: {}", file.display(), e))?;
+		match period {
+			Some(period) => entries.extend(file_entries.into_iter().filter(|x| period.as_range().contains(&x.date))),
+			None => entries.extend(file_entries),
+		}
+	}
+
+	let result = if output == Path::new("-") {
+		convert::write_entries(&mut std::io::stdout().lock(), format, &entries, &config.csv)
+	} else {
+		std::fs::File::create(output)
+			.map(std::io::BufWriter::new)
+			.map_err(|e| e.to_string())
+			.and_then(|mut writer| {
+				convert::write_entries(&mut writer, format, &entries, &config.csv)?;
+				writer.flush().map_err(|e| e.to_string())
+			})
+	};
+	result.map_err(|e| format!("failed to write {}: {}", output.display(), e))?;
+	Ok(())
+}
+
+/// Check hour logs for problems and print them.
+///
+/// Without a configuration, tags are not checked.
+/// With a single `--profile`, the task mappings of the profile are used.
+/// Default tasks of clients are not looked up, since that needs the Paymo API,
+/// so entries that could get the default task of a client are only warnings.
+fn lint(files: &[PathBuf], reader: &import::Reader, config: Option<&Config>, options: &Options, format: lint::LintFormat) -> Result<(), Error> {
+	let mut lint_options = options.lint_options();
+	if let Some(config) = config {
+		lint_options.unresolved_default_tasks = has_client_default_tasks(config)?;
+	}
+	let mut diagnostics = Vec::new();
+	for file in files {
+		let task_ids = match config {
+			Some(config) => Some(offline_task_ids(config, file, options.tag_normalization())?),
+			None => None,
+		};
+		diagnostics.extend(lint::lint_file(file, reader, task_ids.as_ref(), &lint_options));
+	}
+	report_diagnostics(&diagnostics, files.len(), format)
+}
+
+/// Print diagnostics, and fail if any of them is an error.
+fn report_diagnostics(diagnostics: &[lint::Diagnostic], files: usize, format: lint::LintFormat) -> Result<(), Error> {
+	lint::print_diagnostics(diagnostics, format);
+	let errors = diagnostics.iter().filter(|x| x.severity == lint::Severity::Error).count();
+	if errors > 0 {
+		return Err(Error::Failed(tr!("found {} errors in {} hour logs", errors, files)));
+	}
+	Ok(())
+}
+
+/// Check if any client in the configuration has a default task.
+fn has_client_default_tasks(config: &Config) -> Result<bool, Error> {
+	let default_tasks = config.client_default_tasks()
+		.map_err(|e| format!("invalid client configuration: {}", e))?;
+	Ok(!default_tasks.is_empty())
+}
+
+/// Build the tag to task ID mapping for an hour log, without contacting Paymo.
+fn offline_task_ids(config: &Config, file: &Path, normalization: task_ids::TagNormalization) -> Result<task_ids::TaskIds, Error> {
+	let task_ids = file_config(config, file)?.task_ids(normalization)
+		.map_err(|e| format!("invalid task configuration: {}", e))?;
+	Ok(task_ids)
+}
+
+/// Check the staged contents of the hour logs in a git repository.
+///
+/// Each staged hour log is written to a temporary file to check it,
+/// so that changes that are not staged do not affect the result.
+async fn pre_commit(reader: &import::Reader, config: &Config, options: &Options) -> Result<(), Error> {
+	let (config, api) = match options.profile.as_slice() {
+		[] if options.check_server => {
+			let token = match (&options.auth_token, &options.token) {
+				(Some(token), _) => token.clone(),
+				(None, Some(token_path)) => read_token(token_path)?,
+				(None, None) => return Err(Error::Failed("--check-server needs --profile, --token or --auth-token".into())),
+			};
+			let api = api_client(options.api_root.as_deref().unwrap_or(DEFAULT_API_ROOT), token, None, None, None, config);
+			(config.clone(), Some(api))
+		},
+		[] => (config.clone(), None),
+		[name] if options.check_server => {
+			let (_, config, api) = profile_clients(options, config, std::slice::from_ref(name), None, None, None, false)?.remove(0);
+			(config, Some(api))
+		},
+		[name] => (config.for_profile(name)?.0, None),
+		_ => return Err(Error::Failed("--pre-commit can only use a single profile".into())),
+	};
+
+	let mut hour_logs = BTreeSet::new();
+	for pattern in &config.general.hours {
+		let matches = config::expand_glob(pattern)
+			.map_err(|e| format!("invalid hours pattern {}: {}", pattern, e))?;
+		hour_logs.extend(matches.iter().filter_map(|x| x.canonicalize().ok()));
+	}
+	if hour_logs.is_empty() {
+		return Err(Error::Failed(tr!("no hour logs found matching the hours in the configuration file")));
+	}
+
+	let staged: Vec<_> = git::staged_files()?
+		.into_iter()
+		.filter(|x| x.path.canonicalize().is_ok_and(|x| hour_logs.contains(&x)))
+		.collect();
+	if staged.is_empty() {
+		log::debug!("no staged hour logs");
+		return Ok(());
+	}
+
+	let mut lint_options = options.lint_options();
+	if let Some(api) = &api {
+		match api.get_tasks().await {
+			Ok(tasks) => lint_options.existing_tasks = Some(tasks.iter().map(|x| x.id).collect()),
+			Err(e) => log::warn!("{}", tr!("failed to contact Paymo, only checking the hour logs offline: {}", e)),
+		}
+	}
+	// Only use the API for the task mapping if Paymo could be reached.
+	let api = api.filter(|_| lint_options.existing_tasks.is_some());
+	if api.is_none() {
+		lint_options.unresolved_default_tasks = has_client_default_tasks(&config)?;
+	}
+
+	let mut diagnostics = Vec::new();
+	for file in &staged {
+		let task_ids = match &api {
+			Some(api) => load_task_ids(api, &config, &file.path, options.tag_normalization()).await?,
+			None => offline_task_ids(&config, &file.path, options.tag_normalization())?,
+		};
+
+		let copy = file.write_temp_copy()?;
+		let file_diagnostics = lint::lint_file(&copy.path, reader, Some(&task_ids), &lint_options);
+		drop(copy);
+
+		diagnostics.extend(file_diagnostics.into_iter().map(|x| lint::Diagnostic { file: file.path.clone(), ..x }));
+	}
+	report_diagnostics(&diagnostics, staged.len(), lint::LintFormat::Text)
+}
+
+/// Print instructions to install the pre-commit hook with the current options.
+fn pre_commit_instructions(options: &Options) -> Result<(), Error> {
+	// The hook runs in the top level of the repository, so the configuration needs an absolute path.
+	let config = options.config.as_ref().unwrap();
+	let config = config.canonicalize()
+		.map_err(|e| format!("failed to resolve {}: {}", config.display(), e))?;
+
+	let mut command = format!("uurlog-paymo --pre-commit --config {}", shell_quote(&config.to_string_lossy()));
+	for name in &options.profile {
+		command += &format!(" --profile {}", shell_quote(name));
+	}
+	if options.input_format != import::InputFormat::Uurlog {
+		command += &format!(" --input-format {}", options.input_format);
+	}
+	if options.clock_times {
+		command += " --clock-times";
+	}
+	if options.ignore_tag_case {
+		command += " --ignore-tag-case";
+	}
+	if options.normalize_tag_unicode {
+		command += " --normalize-tag-unicode";
+	}
+	if options.unmapped != task_ids::UnmappedPolicy::Error {
+		command += &format!(" --unmapped {}", options.unmapped);
+	}
+	if options.allow_overlap {
+		command += " --allow-overlap";
+	}
+	if options.allow_duplicates {
+		command += " --allow-duplicates";
+	}
+	if options.check_server {
+		command += " --check-server";
+	}
+	if let Some(api_root) = &options.api_root {
+		command += &format!(" --api-root {}", shell_quote(api_root));
+	}
+	// Refer to the token file instead of the token, and never print a token given on the command line.
+	if let Some(token) = &options.token {
+		let token = token.canonicalize()
+			.map_err(|e| format!("failed to resolve {}: {}", token.display(), e))?;
+		command += &format!(" --token {}", shell_quote(&token.to_string_lossy()));
+	}
+	if options.auth_token.is_some() {
+		command += " --auth-token REDACTED";
+	}
+
+	println!("{}", tr!("To check staged hour logs before each commit, save this as .git/hooks/pre-commit in the repository:"));
+	println!();
+	println!("#!/bin/sh");
+	println!("exec {}", command);
+	println!();
+	if options.auth_token.is_some() {
+		println!("{}", tr!("Replace REDACTED with your API token, or use --token with a token file instead."));
+	}
+	println!("{}", tr!("Then make it executable with: chmod +x .git/hooks/pre-commit"));
+	println!("{}", tr!("Use git commit --no-verify to commit without checking the hour logs."));
+	Ok(())
+}
+
+/// Quote an argument for a POSIX shell, if needed.
+fn shell_quote(arg: &str) -> String {
+	if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:@".contains(c)) {
+		arg.to_owned()
+	} else {
+		format!("'{}'", arg.replace('\'', "'\\''"))
+	}
+}
+
+/// Get the configuration for an hour log, with the configuration overrides for the file applied if they exist.
+fn file_config<'a>(config: &'a Config, file: &Path) -> Result<std::borrow::Cow<'a, Config>, Error> {
+	let overrides_path = config::overrides_path(file);
+	if !overrides_path.exists() {
+		return Ok(std::borrow::Cow::Borrowed(config));
+	}
+	log::debug!("reading configuration overrides from {}", overrides_path.display());
+	let overrides = Config::from_file(&overrides_path)
+		.map_err(|e| tr!("failed to read configuration from {}: {}", overrides_path.display(), e))?;
+	Ok(std::borrow::Cow::Owned(config.clone().with_overrides(overrides)))
+}
+
+/// Build the tag to task ID mapping for an hour log.
+///
+/// This applies the configuration overrides for the file,
+/// and looks up the clients of mapped projects to fall back to the default task of the client.
+async fn load_task_ids(api: &ApiClient, config: &Config, file: &Path, normalization: task_ids::TagNormalization) -> Result<task_ids::TaskIds, Error> {
+	let config = file_config(config, file)?;
+	build_task_ids(api, &config, normalization).await
+}
+
+/// Build the tag to task ID mapping from the configuration.
+///
+/// This looks up the clients of mapped projects to fall back to the default task of the client.
+async fn build_task_ids(api: &ApiClient, config: &Config, normalization: task_ids::TagNormalization) -> Result<task_ids::TaskIds, Error> {
+	let mut task_ids = config.task_ids(normalization)
+		.map_err(|e| format!("invalid task configuration: {}", e))?;
+
+	// Look up the clients of mapped projects to fall back to the default task of the client.
+	let client_default_tasks = config.client_default_tasks()
+		.map_err(|e| format!("invalid client configuration: {}", e))?;
+	if !client_default_tasks.is_empty() {
+		let projects = api.get_projects().await
+			.map_err(|e| format!("failed to get projects: {}", e))?;
+		for project_id in task_ids.project_ids().collect::<Vec<_>>() {
+			let client_id = projects.iter().find(|x| x.id == project_id).map(|x| x.client_id);
+			if let Some(&default_task) = client_id.and_then(|x| client_default_tasks.get(&x)) {
+				task_ids.insert_default_task(project_id, default_task);
+			}
+		}
+	}
+
+	Ok(task_ids)
+}
+
+/// Compare the totals per day and per task of an hour log with the entries on Paymo.
+async fn reconcile(
+	api: &ApiClient,
+	file: &Path,
+	reader: &import::Reader,
+	config: &Config,
+	period: &PartialDate,
+	normalization: task_ids::TagNormalization,
+	timezone: Option<chrono_tz::Tz>,
+) -> Result<(), Error> {
+	let period = period.as_range();
+	let entries = read_entries(file, &period, reader)?;
+	let task_ids = load_task_ids(api, config, file, normalization).await?;
+	let (user, timezone, server_entries) = get_user_entries(api, &period, timezone).await?;
+	let workspace = workspace::Workspace::fetch(api).await
+		.map_err(|e| format!("failed to get workspace data: {}", e))?;
+
+	let mut local_totals = report::Totals::default();
+	for entry in &entries {
+		let task_id = task_ids.resolve(&entry.tags).ok();
+		local_totals.add(timezone::to_naive_date(entry.date), task_id, u64::from(entry.seconds));
+	}
+
+	let mut server_totals = report::Totals::default();
+	for entry in &server_entries {
+		if let Some(date) = entry.local_date(timezone) {
+			server_totals.add(date, Some(entry.task_id), u64::from(entry.total_seconds()));
+		}
+	}
+
+	let differences = report::print_reconciliation(&local_totals, &server_totals, &workspace, &Locale::from_user(&user));
+	if differences > 0 {
+		log::warn!("found differences for {} days and tasks", differences);
+	}
+	Ok(())
+}
+
+/// Get the date and number format of the acting user.
+///
+/// Falls back to ISO 8601 dates and a decimal point if the user can not be retrieved.
+async fn user_locale(api: &ApiClient) -> Locale {
+	match api.acting_user().await {
+		Ok(user) => Locale::from_user(&user),
+		Err(e) => {
+			log::warn!("failed to get the date and number format of the user: {}", e);
+			Locale::default()
+		},
+	}
+}
+
+/// Get the calendar of working days.
+///
+/// If the configuration does not specify the weekend, the working days of the Paymo company are used.
+async fn working_days(api: &ApiClient, config: &Config) -> calendar::WorkingDays {
+	if config.general.weekend.is_some() {
+		return config.working_days(Vec::new());
+	}
+
+	let default_weekend = vec![chrono::Weekday::Sat, chrono::Weekday::Sun];
+	let company = match api.get_company().await {
+		Ok(company) => company,
+		Err(e) => {
+			log::warn!("failed to get company working days, assuming a weekend on Saturday and Sunday: {}", e);
+			return config.working_days(default_weekend);
+		},
+	};
+
+	let weekend = match company.working_days.as_deref().map(calendar::weekend_from_working_days) {
+		Some(Ok(weekend)) => weekend,
+		Some(Err(e)) => {
+			log::warn!("failed to parse company working days, assuming a weekend on Saturday and Sunday: {}", e);
+			default_weekend
+		},
+		None => default_weekend,
+	};
+	config.working_days(weekend)
+}
+
+/// List working days in a period without any hours on Paymo, and optionally in an hour log.
+async fn missing_days(
+	api: &ApiClient,
+	file: Option<&Path>,
+	reader: &import::Reader,
+	config: &Config,
+	period: &PartialDate,
+	timezone: Option<chrono_tz::Tz>,
+) -> Result<(), Error> {
+	let period = period.as_range();
+	let working_days = working_days(api, config).await;
+
+	let local_dates = match file {
+		Some(file) => {
+			let entries = read_entries(file, &period, reader)?;
+			Some(entries.iter().map(|x| timezone::to_naive_date(x.date)).collect::<BTreeSet<_>>())
+		},
+		None => None,
+	};
+
+	let (user, timezone, server_entries) = get_user_entries(api, &period, timezone).await?;
+	let locale = Locale::from_user(&user);
+	let server_dates: BTreeSet<_> = server_entries.iter()
+		.filter_map(|x| x.local_date(timezone))
+		.collect();
+
+	for date in calendar::dates(&period).filter(|x| working_days.is_working_day(*x)) {
+		let missing_locally = local_dates.as_ref().map(|x| !x.contains(&date)).unwrap_or(false);
+		let missing_on_server = !server_dates.contains(&date);
+		match (missing_locally, missing_on_server) {
+			(false, false) => (),
+			(true, false) => println!("{} ({}): no hours in hour log", locale.date(date), date.format("%a")),
+			(false, true) => println!("{} ({}): no hours on Paymo", locale.date(date), date.format("%a")),
+			(true, true) => println!("{} ({}): no hours in hour log and on Paymo", locale.date(date), date.format("%a")),
+		}
+	}
+
+	Ok(())
+}
+
+/// Show a heatmap of the logged hours per day, from an hour log or from Paymo.
+async fn heatmap(api: &ApiClient, file: Option<&Path>, reader: &import::Reader, period: &PartialDate, timezone: Option<chrono_tz::Tz>) -> Result<(), Error> {
+	let period = period.as_range();
+	let mut per_day = BTreeMap::<chrono::NaiveDate, u64>::new();
+	match file {
+		Some(file) => {
+			for entry in read_entries(file, &period, reader)? {
+				*per_day.entry(timezone::to_naive_date(entry.date)).or_default() += u64::from(entry.seconds);
+			}
+		},
+		None => {
+			let (_user, timezone, entries) = get_user_entries(api, &period, timezone).await?;
+			for entry in &entries {
+				if let Some(date) = entry.local_date(timezone) {
+					*per_day.entry(date).or_default() += u64::from(entry.total_seconds());
+				}
+			}
+		},
+	}
+
+	let week_start = match api.acting_user().await {
+		Ok(user) => user.week_start.weekday().unwrap_or(chrono::Weekday::Mon),
+		Err(e) => {
+			log::warn!("failed to get the first day of the week, using Monday: {}", e);
+			chrono::Weekday::Mon
+		},
+	};
+	report::print_heatmap(&period, &per_day, week_start);
+	Ok(())
+}
+
+/// Export the time entries on Paymo for a period to a file, or to standard output for `-`.
+async fn export(
+	api: &ApiClient,
+	file: &Path,
+	format: export::ExportFormat,
+	period: &PartialDate,
+	timezone: Option<chrono_tz::Tz>,
+) -> Result<(), Error> {
+	let (_user, timezone, entries) = get_user_entries(api, &period.as_range(), timezone).await?;
+	let workspace = workspace::Workspace::fetch(api).await
+		.map_err(|e| format!("failed to get workspace data: {}", e))?;
+
+	let result = if file == Path::new("-") {
+		export::write_entries(&mut std::io::stdout().lock(), format, &entries, &workspace, timezone)
+	} else {
+		std::fs::File::create(file)
+			.map(std::io::BufWriter::new)
+			.and_then(|mut output| {
+				export::write_entries(&mut output, format, &entries, &workspace, timezone)?;
+				output.flush()
+			})
+	};
+	result.map_err(|e| format!("failed to write {}: {}", file.display(), e))?;
+	Ok(())
+}
+
+/// Render a template or a report with a summary of the hours on Paymo for a period.
+///
+/// Without a template, a report is written in the format given by --format.
+async fn render(
+	api: &ApiClient,
+	config: &Config,
+	reader: &import::Reader,
+	options: &Options,
+	template: Option<&Path>,
+	period: &PartialDate,
+) -> Result<(), Error> {
+	let period = period.as_range();
+	let (user, timezone, entries) = get_user_entries(api, &period, options.timezone).await?;
+	let workspace = workspace::Workspace::fetch(api).await
+		.map_err(|e| format!("failed to get workspace data: {}", e))?;
+
+	let period_name = options.period.as_deref().unwrap_or_default();
+	let mut summary = summary::Summary::new(period_name, &period, &user, &entries, &workspace, timezone);
+	let unmapped = unmapped_entries(api, config, reader, &period, options.tag_normalization()).await?;
+	summary.unmapped = summary::UnmappedSummary::group(&unmapped);
+
+	let rendered = match template {
+		Some(template) => summary::render(template, &summary)?,
+		None => summary::report(&summary, parse_format(options.format.as_deref(), "text")?)?,
+	};
+	let output = options.output.as_path();
+	let result = if output == Path::new("-") {
+		std::io::stdout().lock().write_all(rendered.as_bytes())
+	} else {
+		std::fs::write(output, rendered)
+	};
+	result.map_err(|e| format!("failed to write {}: {}", output.display(), e))?;
+	Ok(())
+}
+
+/// Create a draft invoice for the unbilled entries of a client in a period, with one line per task.
+///
+/// Entries on non-billable tasks are not invoiced.
+async fn create_invoice(
+	api: &ApiClient,
+	client_id: u64,
+	period: &PartialDate,
+	timezone: Option<chrono_tz::Tz>,
+	today: chrono::NaiveDate,
+	dry_run: bool,
+) -> Result<(), Error> {
+	let period_name = period.to_string();
+	let filter = api_client::TimeEntryFilter::new()
+		.client_id(client_id)
+		.billed(false);
+	let (user, _timezone, entries) = get_user_entries_filtered(api, filter, &period.as_range(), timezone).await?;
+	let workspace = workspace::Workspace::fetch(api).await
+		.map_err(|e| format!("failed to get workspace data: {}", e))?;
+	let company = api.get_company().await
+		.map_err(|e| format!("failed to get company: {}", e))?;
+	let client = workspace.clients.get(&client_id)
+		.ok_or_else(|| tr!("client {} does not exist on Paymo", client_id))?;
+
+	// The entries are not linked to the invoice, so refuse to invoice the same period twice.
+	let invoices = api.get_client_invoices(client_id).await
+		.map_err(|e| format!("failed to get invoices: {}", e))?;
+	if let Some(invoice) = invoices.iter().find(|x| x.title == period_name && x.status != types::InvoiceStatus::Void) {
+		return Err(Error::Failed(tr!("invoice {} for {} in {} already exists, void or delete it to create a new one", invoice.number, client.name, period_name)));
+	}
+
+	let entries: Vec<_> = entries.into_iter()
+		.filter(|x| workspace.tasks.get(&x.task_id).map(|x| x.billable).unwrap_or(false))
+		.collect();
+	if entries.is_empty() {
+		return Err(Error::Failed(tr!("no unbilled entries on billable tasks for {} in {}", client.name, period_name)));
+	}
+
+	let mut per_task = BTreeMap::<u64, u64>::new();
+	for entry in &entries {
+		*per_task.entry(entry.task_id).or_default() += u64::from(entry.total_seconds());
+	}
+
+	let locale = Locale::from_user(&user);
+	let mut items = Vec::with_capacity(per_task.len());
+	let mut total = 0.0;
+	for (&task_id, &seconds) in &per_task {
+		let task = workspace.tasks.get(&task_id);
+		let project = workspace.project_of_task(task_id);
+		let task_name = task.map(|x| x.name.clone()).unwrap_or_else(|| format!("unknown task ({})", task_id));
+		let project_name = project.map(|x| x.name.clone()).unwrap_or_default();
+		let rate = match workspace.hourly_rate(task_id, &user, &company) {
+			Some(rate) => rate,
+			None => {
+				log::warn!("{}", tr!("no price per hour for task {} ({}), the line is added with a rate of 0", task_name, task_id));
+				0.0
+			},
+		};
+
+		// Paymo shows quantities with two decimals, so round the hours to avoid surprising totals.
+		let quantity = (seconds as f64 / 3600.0 * 100.0).round() / 100.0;
+		total += quantity * rate;
+		println!("{} / {}: {} × {} = {}", project_name, task_name, locale.number(quantity, 2), locale.number(rate, 2), locale.number(quantity * rate, 2));
+		items.push(api_client::NewInvoiceItem {
+			item: task_name,
+			description: project_name,
+			price_unit: rate,
+			quantity,
+		});
+	}
+	match &company.default_currency {
+		Some(currency) => println!("Total: {} {}", locale.number(total, 2), currency),
+		None => println!("Total: {}", locale.number(total, 2)),
+	}
+
+	if dry_run {
+		log::info!("{}", tr!("Not creating the invoice because of --dry-run"));
+		return Ok(());
+	}
+	let invoice = api.create_invoice(&api_client::NewInvoice {
+		client_id,
+		title: &period_name,
+		currency: company.default_currency.as_deref(),
+		date: today,
+		items,
+	}).await?;
+	log::info!("{}", tr!("Created draft invoice {} ({}) for {}", invoice.number, invoice.id, client.name));
+	Ok(())
+}
+
+/// Get the entries of a period in the configured hour logs that can not be mapped to a task.
+///
+/// Returns no entries if no hour logs are configured.
+async fn unmapped_entries(
+	api: &ApiClient,
+	config: &Config,
+	reader: &import::Reader,
+	period: &std::ops::Range<uurlog::Date>,
+	normalization: task_ids::TagNormalization,
+) -> Result<Vec<(Entry, String)>, Error> {
+	if config.general.hours.is_empty() {
+		return Ok(Vec::new());
+	}
+	let mut unmapped = Vec::new();
+	for file in configured_hour_logs(config)? {
+		let task_ids = load_task_ids(api, config, &file, normalization).await?;
+		for entry in read_entries(&file, period, reader)? {
+			if let Err(reason) = task_ids.resolve(&entry.tags) {
+				unmapped.push((entry, reason));
+			}
+		}
+	}
+	Ok(unmapped)
+}
+
+/// Check the health of the Paymo API.
+async fn ping(api: &ApiClient) -> Result<(), Error> {
+	let ping = api.ping().await?;
+
+	println!("Status: {}", ping.status);
+	println!("Latency: {} ms", ping.latency.as_millis());
+	for (name, value) in &ping.rate_limit {
+		println!("{}: {}", name, value);
+	}
+
+	if ping.status == reqwest::StatusCode::UNAUTHORIZED || ping.status == reqwest::StatusCode::FORBIDDEN {
+		Err(Error::Failed(tr!("the API token was not accepted")))
+	} else if !ping.status.is_success() {
+		Err(Error::Failed(tr!("the API responded with an error")))
+	} else {
+		Ok(())
+	}
+}
+
+/// Search for tasks by task, project or client name.
+async fn find_task(api: &ApiClient, query: &str) -> Result<(), Error> {
+	let workspace = workspace::Workspace::fetch(api).await
+		.map_err(|e| format!("failed to get workspace data: {}", e))?;
+
+	let matches = workspace.find_tasks(query, 0.8);
+	if matches.is_empty() {
+		log::warn!("no tasks found matching {:?}", query);
+	}
+	for (_score, task) in matches.iter().take(20) {
+		let project = workspace.project_of_task(task.id).map(|x| x.name.as_str()).unwrap_or("unknown project");
+		let client = workspace.client_of_task(task.id).map(|x| x.name.as_str()).unwrap_or("unknown client");
+		let complete = if task.complete { ", complete" } else { "" };
+		println!("{} / {} / {} ({}{})", client, project, task.name, task.id, complete);
+	}
+	Ok(())
+}
+
+/// Show the Paymo user of the API token, as text or JSON.
+async fn whoami(api: &ApiClient, json: bool) -> Result<(), Error> {
+	let user = api.my_user().await
+		.map_err(|e| format!("failed to get user: {}", e))?;
+
+	if json {
+		serde_json::to_writer_pretty(std::io::stdout().lock(), &user)
+			.map_err(|e| format!("failed to write user: {}", e))?;
+		println!();
+		return Ok(());
+	}
+
+	println!("ID: {}", user.id);
+	println!("Name: {}", user.name);
+	println!("Email: {}", user.email);
+	println!("Timezone: {}", user.timezone);
+	match user.workday_hours {
+		Some(hours) => println!("Workday hours: {}", hours),
+		None => println!("Workday hours: not set"),
+	}
+
+	// Not every user may see the company settings, so treat this as optional.
+	let company = match api.get_company().await {
+		Ok(company) => company,
+		Err(e) => {
+			log::warn!("failed to get company: {}", e);
+			return Ok(());
+		},
+	};
+	println!();
+	println!("Workspace: {} ({})", company.name, company.id);
+	println!("Account type: {}", company.account_type);
+	println!("Users: {}", format_limit(company.current_users, company.max_users));
+	println!("Projects: {}", format_limit(company.current_projects, company.max_projects));
+	println!("Invoices: {}", format_limit(company.current_invoices, company.max_invoices));
+	match company.working_days.as_deref().map(calendar::weekend_from_working_days) {
+		Some(Ok(weekend)) => {
+			let working_days: Vec<_> = calendar::WEEKDAYS.iter()
+				.filter(|x| !weekend.contains(x))
+				.map(|x| x.to_string())
+				.collect();
+			println!("Working days: {}", working_days.join(", "));
+		},
+		Some(Err(_)) | None => println!("Working days: not set"),
+	}
+	Ok(())
+}
+
+/// Format the usage of a limited resource of a Paymo plan.
+fn format_limit(current: Option<u64>, max: Option<u64>) -> String {
+	match (current, max) {
+		(Some(current), Some(max)) => format!("{} of {}", current, max),
+		(Some(current), None) => format!("{} (unlimited)", current),
+		(None, Some(max)) => format!("at most {}", max),
+		(None, None) => "unknown".into(),
+	}
+}
+
+/// List clients with their number of projects, as text or JSON.
+async fn list_clients(api: &ApiClient, include_archived: bool, json: bool) -> Result<(), Error> {
+	let mut clients = api.get_clients().await
+		.map_err(|e| format!("failed to get clients: {}", e))?;
+	clients.retain(|x| include_archived || x.active);
+	clients.sort_by(|a, b| a.name.cmp(&b.name));
+
+	let projects = api.get_projects().await
+		.map_err(|e| format!("failed to get projects: {}", e))?;
+	let project_count = |client_id| projects.iter().filter(|x| x.client_id == client_id).count();
+
+	if json {
+		#[derive(serde::Serialize)]
+		struct Client<'a> {
+			#[serde(flatten)]
+			client: &'a types::Client,
+			project_count: usize,
+		}
+		let clients: Vec<_> = clients.iter().map(|client| Client { client, project_count: project_count(client.id) }).collect();
+		serde_json::to_writer_pretty(std::io::stdout().lock(), &clients)
+			.map_err(|e| format!("failed to write clients: {}", e))?;
+		println!();
+		return Ok(());
+	}
+
+	for client in &clients {
+		let status = if client.active { "active" } else { "archived" };
+		println!("{} ({}), {}, {} projects", client.name, client.id, status, project_count(client.id));
+	}
+	Ok(())
+}
+
+/// List projects matching a filter, as text or JSON.
+async fn list_projects(api: &ApiClient, filter: api_client::ProjectsFilter, json: bool) -> Result<(), Error> {
+	let mut projects = api.get_projects_filtered(&filter).await
+		.map_err(|e| format!("failed to get projects: {}", e))?;
+	projects.sort_by(|a, b| a.name.cmp(&b.name));
+
+	if json {
+		serde_json::to_writer_pretty(std::io::stdout().lock(), &projects)
+			.map_err(|e| format!("failed to write projects: {}", e))?;
+		println!();
+		return Ok(());
+	}
+
+	let clients = api.get_clients().await
+		.map_err(|e| format!("failed to get clients: {}", e))?;
+	for project in &projects {
+		let client = clients.iter().find(|x| x.id == project.client_id).map(|x| x.name.as_str()).unwrap_or("unknown client");
+		let budget = match project.budget_hours {
+			Some(budget) => format!("{} hours", budget),
+			None => String::from("no budget"),
+		};
+		println!("{} ({}), client: {} ({}), {}", project.name, project.id, client, project.client_id, budget);
+	}
+	Ok(())
+}
+
+/// List the time entries of the current user on Paymo, as text or JSON.
+async fn list_entries(
+	api: &ApiClient,
+	filter: api_client::TimeEntryFilter,
+	period: &PartialDate,
+	timezone: Option<chrono_tz::Tz>,
+	json: bool,
+) -> Result<(), Error> {
+	let (user, timezone, mut entries) = get_user_entries_filtered(api, filter, &period.as_range(), timezone).await?;
+	let workspace = workspace::Workspace::fetch(api).await
+		.map_err(|e| format!("failed to get workspace data: {}", e))?;
+	entries.sort_by(|a, b| (a.local_date(timezone), &a.start_time, a.id).cmp(&(b.local_date(timezone), &b.start_time, b.id)));
+
+	if json {
+		export::write_entries(&mut std::io::stdout().lock(), export::ExportFormat::Json, &entries, &workspace, timezone)
+			.map_err(|e| format!("failed to write entries: {}", e))?;
+	} else {
+		report::print_entries(&entries, &workspace, timezone, &Locale::from_user(&user));
+	}
+	Ok(())
+}
+
+/// Show statistics about the logged hours per tag, and per task if a configuration is given.
+async fn stats(
+	api: &ApiClient,
+	files: &[PathBuf],
+	reader: &import::Reader,
+	config: Option<&Config>,
+	period: &PartialDate,
+	normalization: task_ids::TagNormalization,
+) -> Result<(), Error> {
+	let period = period.as_range();
+	let mut per_tag = BTreeMap::<String, report::Stats>::new();
+	let mut per_task = BTreeMap::<String, report::Stats>::new();
+	let mut total = report::Stats::default();
+	let mut dates = BTreeSet::new();
+
+	for file in files {
+		let entries = read_entries(file, &period, reader)?;
+		let task_ids = match config {
+			Some(config) => Some(load_task_ids(api, config, file, normalization).await?),
+			None => None,
+		};
+		for entry in &entries {
+			let seconds = u64::from(entry.seconds);
+			dates.insert(entry.date);
+			total.add(seconds);
+			for tag in &entry.tags {
+				per_tag.entry(tag.clone()).or_default().add(seconds);
+			}
+			if let Some(task_ids) = &task_ids {
+				let task = match task_ids.resolve(&entry.tags) {
+					Ok(task_id) => format!("task {}", task_id),
+					Err(_) => String::from("unmapped"),
+				};
+				per_task.entry(task).or_default().add(seconds);
+			}
+		}
+	}
+
+	let locale = user_locale(api).await;
+	report::print_stats("Tag", &per_tag, &total, dates.len(), &locale);
+	if config.is_some() {
+		println!();
+		report::print_stats("Task", &per_task, &total, dates.len(), &locale);
+	}
+	Ok(())
+}
+
+/// Show a timesheet with the hours per task and per day on Paymo.
+async fn timesheet(api: &ApiClient, period: &PartialDate, timezone: Option<chrono_tz::Tz>) -> Result<(), Error> {
+	let period = period.as_range();
+	let (_user, timezone, entries) = get_user_entries(api, &period, timezone).await?;
+	let workspace = workspace::Workspace::fetch(api).await
+		.map_err(|e| format!("failed to get workspace data: {}", e))?;
+
+	let mut per_task_day = BTreeMap::<(u64, chrono::NaiveDate), u64>::new();
+	for entry in &entries {
+		if let Some(date) = entry.local_date(timezone) {
+			*per_task_day.entry((entry.task_id, date)).or_default() += u64::from(entry.total_seconds());
+		}
+	}
+	report::print_timesheet(&period, &per_task_day, &workspace);
+	Ok(())
+}
+
+/// Show billable and non-billable hours per client for a period.
+async fn billable_report(api: &ApiClient, period: PartialDate, timezone: Option<chrono_tz::Tz>) -> Result<(), Error> {
+	let (_user, _timezone, entries) = get_user_entries(api, &period.as_range(), timezone).await?;
+	let workspace = workspace::Workspace::fetch(api).await
+		.map_err(|e| format!("failed to get workspace data: {}", e))?;
+	report::print_billable_report(&workspace, &entries);
+	Ok(())
+}
+
+/// Estimate the earnings per client for a period.
+async fn earnings(api: &ApiClient, client_id: Option<u64>, period: &PartialDate, timezone: Option<chrono_tz::Tz>) -> Result<(), Error> {
+	let mut filter = api_client::TimeEntryFilter::new();
+	if let Some(client_id) = client_id {
+		filter = filter.client_id(client_id);
+	}
+	let (user, _timezone, entries) = get_user_entries_filtered(api, filter, &period.as_range(), timezone).await?;
+	let workspace = workspace::Workspace::fetch(api).await
+		.map_err(|e| format!("failed to get workspace data: {}", e))?;
+	let company = api.get_company().await
+		.map_err(|e| format!("failed to get company: {}", e))?;
+	let unrated = report::print_earnings(&workspace, &entries, &user, &company, &Locale::from_user(&user));
+	if unrated > 0 {
+		log::warn!("{}", tr!("{} entries on tasks without a price per hour are not included in the estimate", unrated));
+	}
+	Ok(())
+}
+
+/// Get the time entries of the current user for a period.
+///
+/// The period is interpreted in the given timezone, or in the timezone of the user if no timezone is given.
+/// Returns the user, the timezone that was used and the time entries.
+async fn get_user_entries(
+	api: &ApiClient,
+	period: &std::ops::Range<uurlog::Date>,
+	timezone: Option<chrono_tz::Tz>,
+) -> Result<(types::User, chrono_tz::Tz, Vec<types::TimeEntry>), Error> {
+	get_user_entries_filtered(api, api_client::TimeEntryFilter::new(), period, timezone).await
+}
+
+/// Get the time entries of the acting user for a period that also match an additional filter.
+async fn get_user_entries_filtered(
+	api: &ApiClient,
+	filter: api_client::TimeEntryFilter,
+	period: &std::ops::Range<uurlog::Date>,
+	timezone: Option<chrono_tz::Tz>,
+) -> Result<(types::User, chrono_tz::Tz, Vec<types::TimeEntry>), Error> {
+	let user = api.acting_user().await
+		.map_err(|e| format!("failed to determine user ID: {}", e))?;
+	let timezone = timezone.unwrap_or_else(|| timezone::parse_user_timezone(&user.timezone));
+
+	let filter = filter
+		.user_id(user.id)
+		.period(period.clone())
+		.timezone(timezone);
+	let entries = api.get_time_entries(&filter)
+		.await
+		.map_err(|e| format!("failed to get time entries between {} and {}: {}", period.start, period.end, e))?;
+	Ok((user, timezone, entries))
+}
+
+/// Synchronize the hour logs of all users in the configuration.
+///
+/// All users are synchronized, even if synchronizing one of them fails.
+async fn sync_team(api: &ApiClient, reader: &import::Reader, config: &Config, period: &PartialDate, options: &SyncOptions) -> Result<(), Error> {
+	if config.users.is_empty() {
+		return Err(Error::Failed(tr!("no users configured, add [[user]] sections to the configuration file")));
+	}
+
+	let users = api.get_users().await
+		.map_err(|e| format!("failed to get users: {}", e))?;
+
+	let mut failed_users = Vec::new();
+	for user_config in &config.users {
+		if let Err(e) = sync_team_member(api, &users, user_config, reader, config, period, options).await {
+			log::error!("{}", e);
+			failed_users.push(user_config.email.as_str());
+		}
+	}
+
+	if !failed_users.is_empty() {
+		return Err(Error::PartialFailure(tr!("failed to synchronize hours for {} of {} users: {}", failed_users.len(), config.users.len(), failed_users.join(", "))));
+	}
+	Ok(())
+}
+
+/// Synchronize a period one month at a time.
+///
+/// Each month is recorded as a separate run in the journal, so that `--undo-last-run` only undoes the last month.
+async fn sync_months(api: &ApiClient, files: &[PathBuf], reader: &import::Reader, config: &Config, period: PartialDate, options: &SyncOptions) -> Result<(), Error> {
+	// Plan all months before changing anything, so --max-deletions applies to the whole period.
+	let mut plans = Vec::new();
+	for month in period.months() {
+		let label = timezone::to_naive_date(month.as_range().start).format("%Y-%m").to_string();
+		let month_api = ApiClient {
+			journal: api.journal.as_ref().map(|x| std::sync::Arc::new(x.for_period(&label))),
+			..api.clone()
+		};
+		match plan_sync(&month_api, files, reader, config, &month, options).await {
+			Ok(plan) => plans.push((label, month_api, plan)),
+			Err(e) => {
+				log::error!("failed to plan changes for {}, no months were synchronized", label);
+				return Err(e);
+			},
+		}
+	}
+	check_max_deletions(plans.iter().map(|(_, _, plan)| plan.deletions.len()).sum(), options)?;
+
+	for (label, month_api, plan) in plans {
+		log::info!("Synchronizing {}", label);
+		if let Err(e) = execute_sync(&month_api, plan, config, options).await {
+			log::error!("failed to synchronize {}, the following months were not synchronized", label);
+			return Err(e);
+		}
+	}
+	Ok(())
+}
+
+/// Synchronize the hour logs of a single user in the configuration.
+async fn sync_team_member(
+	api: &ApiClient,
+	users: &[types::User],
+	user_config: &config::UserConfig,
+	reader: &import::Reader,
+	config: &Config,
+	period: &PartialDate,
+	options: &SyncOptions,
+) -> Result<(), Error> {
+	let user = users.iter()
+		.find(|x| x.email.eq_ignore_ascii_case(&user_config.email))
+		.ok_or_else(|| format!("no Paymo user found with email address {}", user_config.email))?;
+
+	let files = user_config.hour_logs()
+		.map_err(|e| format!("invalid hour logs for {}: {}", user_config.email, e))?;
+	if files.is_empty() {
+		log::warn!("no hour logs found for {} matching {}", user_config.email, user_config.hours);
+		return Ok(());
+	}
+
+	log::info!("{}", tr!("Synchronizing hours of {} ({})", user.name, user.email));
+	let user_api = ApiClient {
+		user_id: Some(user.id),
+		..api.clone()
+	};
+	sync_to_paymo(&user_api, &files, reader, config, period, options).await
+}
+
+/// Synchronize logged hours to Paymo.
+async fn sync_to_paymo(api: &ApiClient, files: &[PathBuf], reader: &import::Reader, config: &Config, period: &PartialDate, options: &SyncOptions) -> Result<(), Error> {
+	let plan = plan_sync(api, files, reader, config, period, options).await?;
+	execute_sync(api, plan, config, options).await
+}
+
+/// Apply a planned synchronization, or only show it for a dry run.
+async fn execute_sync(api: &ApiClient, plan: SyncPlan, config: &Config, options: &SyncOptions) -> Result<(), Error> {
+	// For a dry run, show all changes grouped by date instead of applying them.
+	let result = if options.dry_run {
+		print_sync_diff(&plan, &task_names(api).await, &Locale::from_user(&plan.user), config);
+		Ok(())
+	} else {
+		apply_sync(api, &plan, config, options).await
+	};
+
+	report_unmapped(&plan.unmapped, options.unmapped);
+	result
+}
+
+/// The changes needed to synchronize hour logs to Paymo.
+struct SyncPlan {
+	/// The Paymo user the entries are synchronized for.
+	user: types::User,
+
+	/// The timezone to interpret dates in.
+	timezone: chrono_tz::Tz,
+
+	/// Entries on Paymo to delete.
+	deletions: Vec<types::TimeEntry>,
+
+	/// Entries on Paymo to give the description of a local entry.
+	updates: Vec<(types::TimeEntry, Entry)>,
+
+	/// Local entries to add, with their task ID.
+	additions: Vec<(Entry, u64)>,
+
+	/// Local entries that were skipped because they could not be mapped to a task, with the reason.
+	unmapped: Vec<(Entry, String)>,
+}
+
+/// Determine the changes needed to synchronize hour logs to Paymo, without changing anything.
+///
+/// This also performs all checks that should prevent a synchronization.
+async fn plan_sync(api: &ApiClient, files: &[PathBuf], reader: &import::Reader, config: &Config, period: &PartialDate, options: &SyncOptions) -> Result<SyncPlan, Error> {
+	let period = period.as_range();
+
+	// Convert UTC timestamps in the hour logs to the timezone of the user, unless --timezone is given.
+	let user_reader;
+	let reader = if reader.needs_timezone() {
+		let user = api.acting_user().await
+			.map_err(|e| format!("failed to determine user ID: {}", e))?;
+		user_reader = import::Reader {
+			timezone: Some(timezone::parse_user_timezone(&user.timezone)),
+			..reader.clone()
+		};
+		&user_reader
+	} else {
+		reader
+	};
+
+	// Read all entries from the hour logs within the period,
+	// and build the tag to task ID mapping for each hour log.
+	let mut files_entries = Vec::with_capacity(files.len());
+	for file in files {
+		let entries = read_entries(file, &period, reader)?;
+		let task_ids = load_task_ids(api, config, file, options.tag_normalization).await?;
+		files_entries.push((entries, task_ids));
+	}
+
+	// Only synchronize the entries selected by tag.
+	// The other entries are kept aside to recognize their counterparts on Paymo.
+	let mut filtered_entries = Vec::new();
+	if !options.tag_filter.is_empty() {
+		for (entries, _) in &mut files_entries {
+			let (selected, filtered) = std::mem::take(entries).into_iter()
+				.partition(|x| options.tag_filter.matches(&x.tags, options.tag_normalization));
+			*entries = selected;
+			filtered_entries.push(filtered);
+		}
+		log::debug!("selected {} entries by tag, ignoring {} entries",
+			files_entries.iter().map(|(entries, _)| entries.len()).sum::<usize>(),
+			filtered_entries.iter().map(Vec::len).sum::<usize>(),
+		);
+	}
+
+	// Refuse to upload entries that overlap in time.
+	let overlaps = checks::find_overlaps(files_entries.iter().flat_map(|(entries, _)| entries));
+	let level = if options.allow_overlap { log::Level::Warn } else { log::Level::Error };
+	for (a, b) in &overlaps {
+		log::log!(level, "entries overlap:");
+		log::log!(level, "  {}", a);
+		log::log!(level, "  {}", b);
+	}
+	if !overlaps.is_empty() && !options.allow_overlap {
+		return Err(Error::Failed(tr!("found {} overlapping entries, use --allow-overlap to synchronize them anyway", overlaps.len())));
+	}
+
+	// Merge entries before checking for duplicates, so that merged entries are compared.
+	if options.merge_entries {
+		for (entries, _) in &mut files_entries {
+			*entries = entry::merge_entries(std::mem::take(entries));
+		}
+		for entries in &mut filtered_entries {
+			*entries = entry::merge_entries(std::mem::take(entries));
+		}
+	}
+
+	// Refuse to upload identical entries from different files, they are most likely from a file that was included twice.
+	let file_entries = files.iter()
+		.zip(&files_entries)
+		.flat_map(|(file, (entries, _))| entries.iter().map(move |x| (file.as_path(), x)));
+	let duplicates = checks::find_duplicates(file_entries);
+	let level = if options.allow_duplicates { log::Level::Warn } else { log::Level::Error };
+	for duplicate in &duplicates {
+		let (_, entry) = duplicate[0];
+		let files: BTreeSet<_> = duplicate.iter().map(|(file, _)| file.display().to_string()).collect();
+		log::log!(level, "entry appears {} times in {}:", duplicate.len(), files.into_iter().collect::<Vec<_>>().join(", "));
+		log::log!(level, "  {}", entry);
+	}
+	if !duplicates.is_empty() && !options.allow_duplicates {
+		return Err(Error::Failed(tr!("found {} duplicate entries, use --allow-duplicates to synchronize them anyway", duplicates.len())));
+	}
+
+	// Get our Paymo user and the existing entries for the period.
+	// Dates are interpreted in the timezone of the user, unless overridden.
+	// When scoped to a task or project, only get the existing entries for it.
+	let mut filter = api_client::TimeEntryFilter::new();
+	if let Some(task_id) = options.task_id {
+		filter = filter.task_id(task_id);
+	}
+	if let Some(project_id) = options.project_id {
+		filter = filter.project_id(project_id);
+	}
+	let (user, timezone, mut old_entries) = get_user_entries_filtered(api, filter, &period, options.timezone).await?;
+	log::debug!("using timezone {}", timezone.name());
+	log::debug!("found {} existing entries on server between {} and {}", old_entries.len(), period.start, period.end);
+
+	let today = options.now.with_timezone(&timezone).date_naive();
+	check_future_entries(files_entries.iter().flat_map(|(entries, _)| entries), today, options)?;
+
+	// Warn about days with suspiciously many hours.
+	let workday_hours = config.general.workday_hours.or(user.workday_hours);
+	if let Some(workday_hours) = workday_hours.filter(|x| *x > 0.0) {
+		let max_seconds = (workday_hours * config.general.max_workday_factor * 3600.0) as u32;
+		for (date, seconds) in checks::find_long_days(files_entries.iter().flat_map(|(entries, _)| entries), max_seconds) {
+			log::warn!("{} has {} hours logged, which is more than {} times the {} hour workday",
+				date,
+				entry::format_duration(seconds),
+				config.general.max_workday_factor,
+				workday_hours,
+			);
+		}
+	}
+
+	// Warn about hours logged on weekends and holidays.
+	let working_days = working_days(api, config).await;
+	let logged_dates: BTreeSet<_> = files_entries.iter()
+		.flat_map(|(entries, _)| entries)
+		.map(|x| timezone::to_naive_date(x.date))
+		.collect();
+	for date in logged_dates {
+		if working_days.is_holiday(date) {
+			log::warn!("{} ({}) is a holiday, but has hours logged", date, date.format("%a"));
+		} else if working_days.is_weekend(date) {
+			log::warn!("{} ({}) is in the weekend, but has hours logged", date, date.format("%a"));
+		}
+	}
+
+	// Find the right task ID with each hour log entry.
+	let mut entries_with_tasks = Vec::new();
+	let mut unmapped = Vec::new();
+	for (entries, task_ids) in &files_entries {
+		entries_with_tasks.extend(get_tasks_with_entries(entries, task_ids, options.unmapped, &mut unmapped)?);
+	}
+
+	// When scoped to a task or project, ignore entries for other tasks.
+	if let Some(scope) = sync_scope(api, options).await? {
+		let total = entries_with_tasks.len();
+		entries_with_tasks.retain(|(_, task_id)| scope.contains(task_id));
+		log::debug!("{} of {} entries are in the selected task or project", entries_with_tasks.len(), total);
+	}
+
+	// When filtering by tag, leave entries on Paymo alone unless they are in a task of the selected entries.
+	// Entries on Paymo that match a filtered entry are also kept, even if they share a task with the selected entries.
+	if !options.tag_filter.is_empty() {
+		let mut selected_task_ids: BTreeSet<u64> = entries_with_tasks.iter().map(|&(_, task_id)| task_id).collect();
+		for (_, task_ids) in &files_entries {
+			selected_task_ids.extend(options.tag_filter.only.iter().filter_map(|tag| task_ids.get(tag)));
+		}
+		let filtered_with_tasks: Vec<_> = filtered_entries.iter()
+			.zip(&files_entries)
+			.flat_map(|(entries, (_, task_ids))| entries.iter().filter_map(move |x| Some((x, task_ids.resolve(&x.tags).ok()?))))
+			.collect();
+		old_entries.retain(|old_entry| {
+			selected_task_ids.contains(&old_entry.task_id) && !filtered_with_tasks.iter()
+				.any(|&(entry, task_id)| entries_match(entry, task_id, old_entry, timezone, options.duration_tolerance, config.general.fingerprints))
+		});
+	}
+
+	// Collect old entries to delete and new entries to add.
+	let mut delete_entries = Vec::new();
+
+	for old_entry in &old_entries {
+		// See if there is a matching entry in our own hour log.
+		let matching_index = entries_with_tasks
+			.iter()
+			.position(|&(new_entry, task_id)| entries_match(new_entry, task_id, old_entry, timezone, options.duration_tolerance, config.general.fingerprints));
+
+		// If there is, don't upload that entry.
+		if let Some(matching_index) = matching_index {
+			entries_with_tasks.remove(matching_index);
+		// If there isn't, delete the old entry.
+		} else {
+			delete_entries.push(old_entry);
+		}
+	}
+
+	// Update the description of old entries that only differ in description from a new entry,
+	// instead of deleting and re-adding them.
+	// This keeps the identity of the entry, including any link to an invoice.
+	let mut update_entries = Vec::new();
+	delete_entries.retain(|&old_entry| {
+		let matching_index = entries_with_tasks
+			.iter()
+			.position(|&(new_entry, task_id)| entries_match_except_description(new_entry, task_id, old_entry, timezone, options.duration_tolerance));
+		match matching_index {
+			Some(matching_index) => {
+				update_entries.push((old_entry, entries_with_tasks.remove(matching_index).0));
+				false
+			},
+			None => true,
+		}
+	});
+
+	// Explain once why entries that only differ by seconds are replaced, and how to keep them.
+	let mut rounding_mismatches = 0;
+	let mut max_difference = 0;
+	for old_entry in &delete_entries {
+		let new_entry = entries_with_tasks.iter()
+			.find(|&&(new_entry, task_id)| entries_match(new_entry, task_id, old_entry, timezone, 59, config.general.fingerprints));
+		if let Some((new_entry, _)) = new_entry {
+			let server_duration = old_entry.total_seconds();
+			log::info!("entry {} lasts {} on Paymo, but {} in the hour log", old_entry.id, entry::format_duration(server_duration), entry::format_duration(new_entry.seconds));
+			max_difference = max_difference.max(server_duration.max(new_entry.seconds) - server_duration.min(new_entry.seconds));
+			rounding_mismatches += 1;
+		}
+	}
+	if rounding_mismatches > 0 {
+		log::warn!("{}", tr!(
+			"{} entries on Paymo have a duration that differs from the hour log by at most {} seconds and will be replaced, use --duration-tolerance {} to keep them",
+			rounding_mismatches,
+			max_difference,
+			max_difference,
+		));
+	}
+
+	// Only apply one half of the changes when asked.
+	if options.add_only && !delete_entries.is_empty() {
+		log::info!("{}", tr!("not deleting {} entries because of --add-only", delete_entries.len()));
+		delete_entries.clear();
+	}
+	if options.delete_only && !(update_entries.is_empty() && entries_with_tasks.is_empty()) {
+		log::info!("{}", tr!("not updating {} entries and not adding {} entries because of --delete-only", update_entries.len(), entries_with_tasks.len()));
+		update_entries.clear();
+		entries_with_tasks.clear();
+	}
+
+	// Apply changes in a deterministic order, so the output is stable across runs.
+	delete_entries.sort_by(|a, b| {
+		(a.local_date(timezone), a.task_id, &a.description).cmp(&(b.local_date(timezone), b.task_id, &b.description))
+	});
+	update_entries.sort_by(|(a, _), (b, _)| {
+		(a.local_date(timezone), a.task_id, &a.description).cmp(&(b.local_date(timezone), b.task_id, &b.description))
+	});
+	entries_with_tasks.sort_by(|(a, a_task_id), (b, b_task_id)| {
+		(a.date, a_task_id, &a.description).cmp(&(b.date, b_task_id, &b.description))
+	});
+
+	check_max_deletions(delete_entries.len(), options)?;
+
+	// Refuse to change entries older than the maximum entry age, they may already be invoiced.
+	if let Some(max_age) = config.general.max_entry_age_days {
+		let oldest = today - chrono::Duration::days(max_age.into());
+		let level = if options.allow_old { log::Level::Warn } else { log::Level::Error };
+		let mut old_changes = 0;
+		for entry in &delete_entries {
+			if let Some(date) = entry.local_date(timezone).filter(|x| *x < oldest) {
+				log::log!(level, "would delete entry {} dated before {}: {}, {}", entry.id, oldest, date, redact::description(&entry.description));
+				old_changes += 1;
+			}
+		}
+		for (entry, _) in &entries_with_tasks {
+			if timezone::to_naive_date(entry.date) < oldest {
+				log::log!(level, "would add entry dated before {}: {}", oldest, entry);
+				old_changes += 1;
+			}
+		}
+		for (old_entry, new_entry) in &update_entries {
+			if timezone::to_naive_date(new_entry.date) < oldest {
+				log::log!(level, "would update entry {} dated before {}: {}", old_entry.id, oldest, new_entry);
+				old_changes += 1;
+			}
+		}
+		if old_changes > 0 && !options.allow_old {
+			return Err(Error::Failed(tr!("found {} changes to entries older than {} days, use --allow-old to synchronize them anyway", old_changes, max_age)));
+		}
+	}
+
+	// Check that all configured tasks still exist before changing anything.
+	// Otherwise a stale mapping would delete matched entries and then fail to add them again.
+	let configured_task_ids: BTreeSet<u64> = files_entries.iter().flat_map(|(_, task_ids)| task_ids.all_task_ids()).collect();
+	let mapped_task_ids: BTreeSet<u64> = entries_with_tasks.iter().map(|&(_, task_id)| task_id).collect();
+	check_configured_tasks(api, &configured_task_ids, &mapped_task_ids).await?;
+
+	// Get the tasks we add entries to.
+	let mut add_task_ids: Vec<_> = entries_with_tasks.iter().map(|&(_, task_id)| task_id).collect();
+	add_task_ids.sort();
+	add_task_ids.dedup();
+	let mut tasks = BTreeMap::new();
+	for task_id in add_task_ids {
+		let task = api.get_task(task_id).await
+			.map_err(|e| format!("failed to get task {}: {}", task_id, e))?;
+		tasks.insert(task_id, task);
+	}
+
+	// Paymo refuses new entries on completed tasks, so check that before changing anything.
+	check_completed_tasks(api, tasks.values()).await?;
+
+	// Make sure we are assigned to all tasks we add entries to, before changing anything.
+	check_task_assignments(api, user.id, &tasks, options).await?;
+
+	// Warn about tasks and projects that will go over budget.
+	let mut budget_changes = BTreeMap::<u64, budget::TimeChange>::new();
+	for &(entry, task_id) in &entries_with_tasks {
+		budget_changes.entry(task_id).or_default().added += u64::from(entry.seconds);
+	}
+	for delete_entry in &delete_entries {
+		if let Some(change) = budget_changes.get_mut(&delete_entry.task_id) {
+			change.deleted += u64::from(delete_entry.total_seconds());
+		}
+	}
+	budget::warn_budget_exceedance(api, &tasks, &budget_changes, &Locale::from_user(&user)).await
+		.map_err(|e| format!("failed to check budgets: {}", e))?;
+
+	Ok(SyncPlan {
+		user,
+		timezone,
+		deletions: delete_entries.into_iter().cloned().collect(),
+		updates: update_entries.into_iter().map(|(old_entry, new_entry)| (old_entry.clone(), new_entry.clone())).collect(),
+		additions: entries_with_tasks.into_iter().map(|(entry, task_id)| (entry.clone(), task_id)).collect(),
+		unmapped: unmapped.into_iter().map(|(entry, reason)| (entry.clone(), reason)).collect(),
+	})
+}
+
+/// Get the names of all tasks by ID, for showing changes.
+///
+/// Returns an empty map if the tasks can not be retrieved.
+async fn task_names(api: &ApiClient) -> BTreeMap<u64, String> {
+	match api.get_tasks().await {
+		Ok(tasks) => tasks.into_iter().map(|x| (x.id, x.name)).collect(),
+		Err(e) => {
+			log::warn!("failed to get task names: {}", e);
+			BTreeMap::new()
+		},
+	}
+}
+
+/// Apply the changes of a synchronization plan to Paymo.
+async fn apply_sync(api: &ApiClient, plan: &SyncPlan, config: &Config, options: &SyncOptions) -> Result<(), Error> {
+	let timezone = plan.timezone;
+
+	// Keep track of failed changes when running with `keep_going`.
+	let mut failures = Vec::new();
+
+	// Limit the rate of changes, regardless of the concurrency.
+	let limiter = &RateLimiter::new(std::time::Duration::from_secs(1));
+
+	// Delete all old entries without match in the log.
+	let deletions: Vec<_> = plan.deletions.iter().collect();
+	delete_server_entries(api, &deletions, timezone, options, limiter, &mut failures).await?;
+
+	// Update the description of entries that only differ in description.
+	let updates: Vec<_> = plan.updates.iter().map(|(old_entry, new_entry)| async move {
+		log::info!("{}", tr!("Updating description of entry {}: {} -> {}", old_entry.id, redact::description(&old_entry.description), new_entry));
+		limiter.wait().await;
+		let update = api_client::EntryUpdate {
+			description: Some(upload_description(new_entry, config)),
+			..Default::default()
+		};
+		if let Err(e) = api.update_entry(old_entry, &update).await {
+			log::error!("{}", e);
+			return Err(format!("update description of entry {}: {}", old_entry.id, new_entry));
+		}
+		Ok(())
+	}).collect();
+	apply_changes(updates, options, &mut failures).await?;
+
+	// Upload all new entries without existing entry on Paymo.
+	let additions: Vec<_> = plan.additions.iter().map(|&(ref entry, task_id)| async move {
+		log::info!("{}", tr!("Adding entry with task id {}: {}", task_id, entry));
+		limiter.wait().await;
+		if let Err(e) = add_entry(api, task_id, entry, &upload_description(entry, config), timezone).await {
+			log::error!("{}", e);
+			return Err(format!("add entry with task id {}: {}", task_id, entry));
+		}
+		Ok(())
+	}).collect();
+	apply_changes(additions, options, &mut failures).await?;
 
-	/// The period to synchronize.
-	#[structopt(value_name = "YYYY[-MM[-DD]]")]
-	#[structopt(long)]
-	period: Option<PartialDate>,
+	report_failures(&failures, plan.deletions.len() + plan.updates.len() + plan.additions.len())
+}
 
-	/// Print what would be done, without changing any entries on Paymo.
-	#[structopt(long)]
-	dry_run: bool,
+/// A line in the diff of a synchronization.
+struct DiffLine {
+	sign: char,
+	seconds: u32,
+	task_id: u64,
+	description: String,
+}
 
-	/// Read tag to task ID mapping from this file.
-	#[structopt(long)]
-	#[structopt(value_name = "FILE")]
-	task_ids: Option<PathBuf>,
+/// Print the changes of a synchronization as a diff, grouped by date.
+///
+/// Removed entries are prefixed with `-` and added entries with `+`.
+/// A description change shows the old and new entry as a pair of lines.
+fn print_sync_diff(plan: &SyncPlan, task_names: &BTreeMap<u64, String>, locale: &Locale, config: &Config) {
+	let timezone = plan.timezone;
+	let removed = |entry: &types::TimeEntry| DiffLine {
+		sign: '-',
+		seconds: entry.total_seconds(),
+		task_id: entry.task_id,
+		description: redact::description(&entry.description).to_owned(),
+	};
+	let added = |entry: &Entry, task_id: u64| DiffLine {
+		sign: '+',
+		seconds: entry.seconds,
+		task_id,
+		description: redact::description(&upload_description(entry, config)).to_owned(),
+	};
 
-	/// List all non-completed tasks for active projects.
-	#[structopt(long)]
-	#[structopt(group = "action")]
-	list_tasks: bool,
+	let mut days = BTreeMap::<Option<chrono::NaiveDate>, Vec<DiffLine>>::new();
+	for entry in &plan.deletions {
+		days.entry(entry.local_date(timezone)).or_default().push(removed(entry));
+	}
+	for (old_entry, new_entry) in &plan.updates {
+		let lines = days.entry(old_entry.local_date(timezone)).or_default();
+		lines.push(removed(old_entry));
+		lines.push(added(new_entry, old_entry.task_id));
+	}
+	for &(ref entry, task_id) in &plan.additions {
+		days.entry(Some(timezone::to_naive_date(entry.date))).or_default().push(added(entry, task_id));
+	}
 
-	/// Read the Paymo API token from this file.
-	#[structopt(short, long)]
-	token: PathBuf,
+	if days.is_empty() {
+		println!("{}", tr!("No changes."));
+		return;
+	}
 
-	/// Use this URL as the root for the Paymo API.
-	#[structopt(long)]
-	#[structopt(default_value = "https://app.paymoapp.com/api")]
-	api_root: String,
+	let task_name = |task_id: u64| match task_names.get(&task_id) {
+		Some(name) => format!("{} ({})", name, task_id),
+		None => format!("task {}", task_id),
+	};
+	let task_width = days.values().flatten().map(|x| task_name(x.task_id).chars().count()).max().unwrap_or(0);
+
+	for (date, lines) in &days {
+		let added: u32 = lines.iter().filter(|x| x.sign == '+').map(|x| x.seconds).sum();
+		let removed: u32 = lines.iter().filter(|x| x.sign == '-').map(|x| x.seconds).sum();
+		let change = if added >= removed {
+			format!("+{}", entry::format_duration(added - removed))
+		} else {
+			format!("-{}", entry::format_duration(removed - added))
+		};
+		let date = date.map(|x| format!("{} {}", locale.date(x), x.format("%a"))).unwrap_or_else(|| String::from("????"));
+		println!("@@ {} ({}) @@", date, change);
+		for line in lines {
+			println!("{} {:>7}  {:<task_width$}  {}",
+				line.sign,
+				entry::format_duration(line.seconds),
+				task_name(line.task_id),
+				line.description,
+				task_width = task_width,
+			);
+		}
+	}
+
+	println!();
+	println!("{}", tr!(
+		"{} entries to delete, {} descriptions to update, {} entries to add",
+		plan.deletions.len(),
+		plan.updates.len(),
+		plan.additions.len(),
+	));
 }
 
-#[tokio::main]
-async fn main() {
-	if do_main(Options::from_args()).await.is_err() {
-		std::process::exit(1);
+/// Synchronize expense files to Paymo.
+///
+/// Expenses on Paymo match an expense in the files if they have the same date, project, amount and notes.
+/// Invoiced expenses on Paymo are never deleted.
+async fn sync_expenses(api: &ApiClient, files: &[PathBuf], config: &Config, period: &PartialDate, options: &SyncOptions) -> Result<(), Error> {
+	let period = period.as_range();
+	let first_day = timezone::to_naive_date(period.start);
+	let last_day = timezone::to_naive_date(period.end).pred_opt().unwrap_or(first_day);
+
+	let user = api.acting_user().await
+		.map_err(|e| format!("failed to determine user ID: {}", e))?;
+	let workspace = workspace::Workspace::fetch(api).await
+		.map_err(|e| format!("failed to get workspace data: {}", e))?;
+
+	// Read the expenses within the period, and map them to a project.
+	let mut additions = Vec::new();
+	let mut unmapped = Vec::new();
+	for file in files {
+		let task_ids = load_task_ids(api, config, file, options.tag_normalization).await?;
+		let expenses = expenses::read(file)
+			.map_err(|e| format!("failed to read {}: {}", file.display(), e))?;
+		for expense in expenses.into_iter().filter(|x| x.date >= first_day && x.date <= last_day) {
+			match expense_project(&expense, &task_ids, &workspace) {
+				Ok(project) if options.project_id.is_some_and(|x| x != project.id) => (),
+				Ok(project) => additions.push((expense, project)),
+				Err(e) if options.unmapped == task_ids::UnmappedPolicy::Error => return Err(Error::Failed(format!("{}\n  {}", e, expense))),
+				Err(e) => unmapped.push((expense, e)),
+			}
+		}
+	}
+
+	let filter = api_client::ExpenseFilter::new()
+		.user_id(user.id)
+		.from(first_day)
+		.until(last_day);
+	let server_expenses = api.get_expenses(&filter).await
+		.map_err(|e| format!("failed to get expenses between {} and {}: {}", first_day, last_day, e))?;
+	let server_expenses = server_expenses.iter()
+		.filter(|x| options.project_id.is_none() || x.project_id == options.project_id);
+
+	// Keep the expenses on Paymo that match an expense in the files, and delete the others.
+	let mut server_expenses = index_by(server_expenses, |x| (x.date, x.project_id, expenses::to_cents(x.amount), x.notes.clone()));
+	additions.retain(|(expense, project)| {
+		let key = (Some(expense.date), Some(project.id), expense.cents(), expense.description.clone());
+		match server_expenses.get_mut(&key) {
+			Some(matches) if !matches.is_empty() => {
+				matches.pop();
+				false
+			},
+			_ => true,
+		}
+	});
+	let (invoiced, deletions): (Vec<_>, Vec<_>) = server_expenses.into_values()
+		.flatten()
+		.partition(|x| x.invoiced);
+	let deletions = if options.add_only { Vec::new() } else { deletions };
+	if options.delete_only {
+		additions.clear();
+	}
+	for expense in &invoiced {
+		log::warn!("{}", tr!("not deleting invoiced expense {}: {}", expense.id, describe_server_expense(expense)));
+	}
+	check_max_deletions(deletions.len(), options)?;
+
+	let limiter = &RateLimiter::new(std::time::Duration::from_secs(1));
+	let mut failures = Vec::new();
+
+	let changes: Vec<_> = deletions.iter().map(|&expense| async move {
+		log::warn!("{}", tr!("Deleting expense {}: {}", expense.id, describe_server_expense(expense)));
+		if !options.dry_run {
+			limiter.wait().await;
+			if let Err(e) = api.delete_expense(expense.id).await {
+				log::error!("{}", e);
+				return Err(format!("delete expense {}: {}", expense.id, describe_server_expense(expense)));
+			}
+		}
+		Ok(())
+	}).collect();
+	apply_changes(changes, options, &mut failures).await?;
+
+	let changes: Vec<_> = additions.iter().map(|(expense, project)| async move {
+		log::info!("{}", tr!("Adding expense for project {}: {}", project.name, expense));
+		if !options.dry_run {
+			limiter.wait().await;
+			let new_expense = api_client::NewExpense {
+				client_id: project.client_id,
+				project_id: project.id,
+				user_id: api.user_id,
+				amount: expense.amount,
+				date: expense.date,
+				notes: &expense.description,
+			};
+			if let Err(e) = api.add_expense(&new_expense).await {
+				log::error!("{}", e);
+				return Err(format!("add expense for project {}: {}", project.name, expense));
+			}
+		}
+		Ok(())
+	}).collect();
+	apply_changes(changes, options, &mut failures).await?;
+
+	if deletions.is_empty() && additions.is_empty() {
+		log::info!("{}", tr!("No changes."));
 	}
+	if !unmapped.is_empty() {
+		let level = match options.unmapped {
+			task_ids::UnmappedPolicy::Skip => log::Level::Info,
+			_ => log::Level::Warn,
+		};
+		log::log!(level, "{}", tr!("skipped {} expenses without project:", unmapped.len()));
+		for (expense, reason) in &unmapped {
+			log::log!(level, "  {} ({})", expense, reason);
+		}
+	}
+	report_failures(&failures, deletions.len() + additions.len())
 }
 
-/// Read a file to a string, with a potential final newline removed.
-fn read_file(path: impl AsRef<Path>) -> std::io::Result<String> {
-	let mut data = std::fs::read_to_string(path)?;
-	if data.ends_with('\n') {
-		data.pop();
+/// Determine the project of an expense from its tags.
+///
+/// A project tag takes precedence, otherwise the tags must map to a task.
+fn expense_project<'a>(expense: &expenses::Expense, task_ids: &task_ids::TaskIds, workspace: &'a workspace::Workspace) -> Result<&'a types::Project, String> {
+	let project_id = match task_ids.resolve_project(&expense.tags) {
+		Some(project_id) => project_id,
+		None => {
+			let task_id = task_ids.resolve(&expense.tags)?;
+			workspace.tasks.get(&task_id)
+				.map(|x| x.project_id)
+				.ok_or_else(|| tr!("task {} does not exist on Paymo", task_id))?
+		},
+	};
+	workspace.projects.get(&project_id)
+		.ok_or_else(|| tr!("project {} does not exist on Paymo", project_id))
+}
+
+/// Describe an expense on Paymo for log messages.
+fn describe_server_expense(expense: &types::Expense) -> String {
+	let date = expense.date.map(|x| x.to_string()).unwrap_or_else(|| String::from("????"));
+	let project = expense.project_id.map(|x| x.to_string()).unwrap_or_else(|| String::from("none"));
+	format!("{}, {:.2}, project {}, {}", date, expense.amount, project, redact::description(&expense.notes))
+}
+
+/// Add a single entry to Paymo, using the configuration to find the task.
+///
+/// The entry is checked like synchronized entries: for its date, and the completion and assignment of its task.
+async fn add_single_entry(api: &ApiClient, config: &Config, entry: &Entry, options: &SyncOptions) -> Result<(), Error> {
+	let task_ids = build_task_ids(api, config, options.tag_normalization).await?;
+	let task_id = task_ids.resolve(&entry.tags)
+		.map_err(|e| format!("{}: {}", e, entry))?;
+
+	let user = api.acting_user().await
+		.map_err(|e| format!("failed to determine user ID: {}", e))?;
+	let timezone = options.timezone.unwrap_or_else(|| timezone::parse_user_timezone(&user.timezone));
+	let today = options.now.with_timezone(&timezone).date_naive();
+	check_future_entries(std::iter::once(entry), today, options)?;
+
+	// Refuse to add entries older than the maximum entry age, the period may already be invoiced.
+	if let Some(max_age) = config.general.max_entry_age_days {
+		let oldest = today - chrono::Duration::days(max_age.into());
+		if timezone::to_naive_date(entry.date) < oldest {
+			let level = if options.allow_old { log::Level::Warn } else { log::Level::Error };
+			log::log!(level, "would add entry dated before {}: {}", oldest, entry);
+			if !options.allow_old {
+				return Err(Error::Failed(tr!("the entry is older than {} days, use --allow-old to add it anyway", max_age)));
+			}
+		}
 	}
-	Ok(data)
+
+	let task = api.get_task(task_id).await
+		.map_err(|e| format!("failed to get task {}: {}", task_id, e))?;
+	check_completed_tasks(api, std::iter::once(&task)).await?;
+	check_task_assignments(api, user.id, &BTreeMap::from([(task_id, task)]), options).await?;
+
+	log::info!("{}", tr!("Adding entry with task id {}: {}", task_id, entry));
+	if !options.dry_run {
+		api.add_entry(task_id, entry.date, entry.seconds, &upload_description(entry, config)).await?;
+	}
+	Ok(())
 }
 
-fn init_logging(verbosity: i8) {
-	let level = if verbosity <= -2 {
-		log::LevelFilter::Error
-	} else if verbosity == -1 {
-		log::LevelFilter::Warn
-	} else if verbosity == 0 {
-		log::LevelFilter::Info
-	} else if verbosity == 1 {
-		log::LevelFilter::Debug
+/// Get the path of the timer state file.
+fn timer_file(options: &Options) -> Result<PathBuf, Error> {
+	match &options.timer_file {
+		Some(path) => Ok(path.clone()),
+		None => Ok(timer::default_path().ok_or_else(|| String::from("could not determine the timer state file, use --timer-file"))?),
+	}
+}
+
+/// Start a timer, on Paymo or locally.
+async fn timer_start(api: &ApiClient, config: &Config, tags: &[String], timer_file: &Path, options: &Options) -> Result<(), Error> {
+	let description = options.description.as_deref().unwrap_or("");
+	let running = timer::read(timer_file)
+		.map_err(|e| format!("failed to read timer from {}: {}", timer_file.display(), e))?;
+	if let Some(running) = running {
+		return Err(Error::Failed(tr!("a timer is already running on task {} since {}, stop it first", running.task_id, running.start)));
+	}
+
+	let task_ids = build_task_ids(api, config, options.tag_normalization()).await?;
+	let task_id = task_ids.resolve(tags)?;
+
+	let start = chrono::Utc::now();
+	if options.dry_run {
+		let start = start.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+		if options.local_timer {
+			log::info!("Would start a local timer on task {} at {}: {}", task_id, start, description);
+		} else {
+			log::info!("Would start a timer on Paymo on task {} at {}: {}", task_id, start, description);
+		}
+		return Ok(());
+	}
+
+	let entry_id = if options.local_timer {
+		None
 	} else {
-		log::LevelFilter::Trace
+		let entry_id = api.start_timer(task_id, start, description).await
+			.map_err(|e| format!("failed to start timer: {}", e))?;
+		Some(entry_id)
 	};
 
-	env_logger::Builder::from_env("RUST_LOG").filter_module("uurlog_paymo", level).init();
+	let timer = timer::Timer {
+		task_id,
+		start,
+		description: description.to_string(),
+		entry_id,
+	};
+	timer::write(timer_file, &timer)
+		.map_err(|e| format!("failed to write timer to {}: {}", timer_file.display(), e))?;
+	log::info!("Started timer on task {}", task_id);
+	Ok(())
 }
 
-async fn do_main(options: Options) -> Result<(), ()> {
-	init_logging(options.verbose);
+/// Stop the running timer, and create the entry on Paymo for local timers.
+async fn timer_stop(api: &ApiClient, timer_file: &Path, dry_run: bool) -> Result<(), Error> {
+	let timer = timer::read(timer_file)
+		.map_err(|e| format!("failed to read timer from {}: {}", timer_file.display(), e))?
+		.ok_or_else(|| String::from("no timer is running"))?;
 
-	let token = read_file(&options.token)
-		.map_err(|e| log::error!("failed to read token from {}: {}", options.token.display(), e))?;
+	let end = chrono::Utc::now();
+	if dry_run {
+		let start = timer.start.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+		let end = end.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+		match timer.entry_id {
+			Some(entry_id) => log::info!("Would stop the timer of entry {} on Paymo at {}", entry_id, end),
+			None => log::info!("Would add an entry on task {} from {} to {}: {}", timer.task_id, start, end, timer.description),
+		}
+		return Ok(());
+	}
 
-	let api = ApiClient {
-		api_root: options.api_root,
-		auth_token: token,
-	};
+	match timer.entry_id {
+		Some(entry_id) => api.stop_timer(entry_id, end).await,
+		None => api.add_entry_with_times(timer.task_id, timer.start, end, &timer.description).await.map(drop),
+	}.map_err(|e| format!("failed to stop timer: {}", e))?;
 
-	if let Some(file) = &options.sync {
-		sync_to_paymo(
-			&api,
-			file,
-			&options.task_ids.unwrap(),
-			&options.period.unwrap(),
-			options.dry_run
-		).await
-	} else if options.list_tasks {
-		list_tasks(&api).await
-	} else {
-		unreachable!("no action selected");
+	timer::remove(timer_file)
+		.map_err(|e| format!("failed to remove {}: {}", timer_file.display(), e))?;
+	let minutes = (end - timer.start).num_minutes().max(0) as u32;
+	log::info!("Stopped timer on task {} after {}", timer.task_id, uurlog::Hours::from_minutes(minutes));
+	Ok(())
+}
+
+/// Change fields of a single entry on Paymo.
+async fn edit_entry(api: &ApiClient, entry_id: u64, update: &api_client::EntryUpdate, dry_run: bool) -> Result<(), Error> {
+	if update.task_id.is_none() && update.date.is_none() && update.duration.is_none() && update.description.is_none() {
+		return Err(Error::Failed(tr!("nothing to change, use --task-id, --date, --hours or --description")));
+	}
+
+	log::info!("Updating entry {}: {:?}", entry_id, update);
+	if !dry_run {
+		let entry = api.get_entry(entry_id).await
+			.map_err(|e| format!("failed to get entry {}: {}", entry_id, e))?;
+		api.update_entry(&entry, update).await?;
 	}
+	Ok(())
 }
 
-async fn list_tasks(api: &ApiClient) -> Result<(), ()> {
-	let mut clients = api.get_clients().await.map_err(|e| log::error!("{}", e))?;
-	clients.sort_by(|a, b| a.name.cmp(&b.name));
+/// Delete all time entries of the current user in a period.
+///
+/// Without --max-deletions, at most `DEFAULT_MAX_PERIOD_DELETIONS` entries are deleted.
+async fn delete_period(api: &ApiClient, config: &Config, period: &PartialDate, options: &SyncOptions) -> Result<(), Error> {
+	let period = period.as_range();
+	let (_user, timezone, entries) = get_user_entries(api, &period, options.timezone).await?;
+	let mut entries: Vec<_> = entries.iter().collect();
+	entries.sort_by(|a, b| (a.local_date(timezone), &a.start_time, a.id).cmp(&(b.local_date(timezone), &b.start_time, b.id)));
+	check_max_period_deletions(entries.len(), options)?;
 
-	// Get all active projects, and index them by client ID.
-	let filter = api_client::ProjectsFilter {
-		active: Some(true),
-	};
-	let projects = api.get_projects_filtered(&filter).await.map_err(|e| log::error!("{}", e))?;
-	let projects_by_client_id = index_by(projects, |x| x.client_id);
+	// Refuse to delete entries older than the maximum entry age, they may already be invoiced.
+	if let Some(max_age) = config.general.max_entry_age_days {
+		let today = options.now.with_timezone(&timezone).date_naive();
+		let oldest = today - chrono::Duration::days(max_age.into());
+		let level = if options.allow_old { log::Level::Warn } else { log::Level::Error };
+		let mut old_entries = 0;
+		for entry in &entries {
+			if let Some(date) = entry.local_date(timezone).filter(|x| *x < oldest) {
+				log::log!(level, "would delete entry {} dated before {}: {}, {}", entry.id, oldest, date, redact::description(&entry.description));
+				old_entries += 1;
+			}
+		}
+		if old_entries > 0 && !options.allow_old {
+			return Err(Error::Failed(tr!("found {} entries older than {} days, use --allow-old to delete them anyway", old_entries, max_age)));
+		}
+	}
 
-	// Get all tasks, and index them by project ID.
-	let tasks = api.get_tasks().await.map_err(|e| log::error!("{}", e))?;
-	let tasks_by_project_id = index_by(tasks, |x| x.project_id);
+	let limiter = &RateLimiter::new(std::time::Duration::from_secs(1));
+	let mut failures = Vec::new();
+	delete_server_entries(api, &entries, timezone, options, limiter, &mut failures).await?;
+	report_failures(&failures, entries.len())?;
 
-	// Print a tree of clients -> projects -> tasks.
-	for client in &clients {
-		let projects = projects_by_client_id.get(&client.id);
-		if let Some(projects) = projects {
-			println!("{} ({})", client.name, client.id);
-			for project in projects {
-				println!("  {} ({})", project.name, project.id);
-				let tasks = tasks_by_project_id.get(&project.id).map(|x| x.as_slice()).unwrap_or_else(|| &[]);
-				for task in tasks {
-					if !task.complete {
-						println!("    {} ({})", task.name, task.id);
-					}
-				}
+	if options.dry_run && !entries.is_empty() {
+		log::info!("{}", tr!("not deleting {} entries, use --confirm to delete them", entries.len()));
+	}
+	Ok(())
+}
+
+/// Undo the changes of the last run recorded in the journal for the same API root, in reverse order.
+///
+/// Applies the same limits as `delete_period` to the entries that would be deleted or changed.
+async fn undo_last_run(api: &ApiClient, config: &Config, options: &SyncOptions) -> Result<(), Error> {
+	let journal = api.journal.as_deref()
+		.ok_or_else(|| String::from("no journal available, use --journal to set the journal file"))?;
+	let records = journal::read(journal.path())
+		.map_err(|e| format!("failed to read journal {}: {}", journal.path().display(), e))?;
+
+	// Only undo changes made on the same account, other API roots may use the same entry IDs.
+	let records: Vec<_> = records.into_iter()
+		.filter(|x| x.api_root.as_deref() == journal.api_root())
+		.collect();
+
+	// Skip the changes of the current run, made when undoing for an earlier profile.
+	let last_run = records.iter()
+		.rev()
+		.map(|x| &x.run)
+		.find(|&x| x != journal.run())
+		.ok_or_else(|| format!("no changes recorded in journal {} for {}", journal.path().display(), api.api_root))?;
+	let changes: Vec<_> = records.iter()
+		.filter(|x| &x.run == last_run && x.profile.as_deref() == journal.profile())
+		.collect();
+	if changes.is_empty() {
+		log::info!("The last run ({}) made no changes with this profile", last_run);
+		return Ok(());
+	}
+	log::info!("Undoing {} changes of run {}", changes.len(), last_run);
+
+	let deletions = changes.iter().filter(|x| matches!(x.change, journal::Change::Add { .. })).count();
+	check_max_period_deletions(deletions, options)?;
+
+	// Refuse to change entries older than the maximum entry age, they may already be invoiced.
+	if let Some(max_age) = config.general.max_entry_age_days {
+		let user = api.acting_user().await
+			.map_err(|e| format!("failed to determine user ID: {}", e))?;
+		let timezone = options.timezone.unwrap_or_else(|| timezone::parse_user_timezone(&user.timezone));
+		let today = options.now.with_timezone(&timezone).date_naive();
+		let oldest = today - chrono::Duration::days(max_age.into());
+		let level = if options.allow_old { log::Level::Warn } else { log::Level::Error };
+		let mut old_changes = 0;
+		for record in &changes {
+			let entry = match &record.change {
+				journal::Change::Add { entry: Some(entry), .. } => std::borrow::Cow::Borrowed(entry),
+				journal::Change::Add { entry_id, entry: None } => {
+					let entry = api.get_entry(*entry_id).await
+						.map_err(|e| format!("failed to get added entry {}: {}", entry_id, e))?;
+					std::borrow::Cow::Owned(entry)
+				},
+				journal::Change::Delete { entry } | journal::Change::Update { entry } => std::borrow::Cow::Borrowed(entry),
+			};
+			if let Some(date) = entry.local_date(timezone).filter(|x| *x < oldest) {
+				log::log!(level, "would undo change to entry {} dated before {}: {}, {}", entry.id, oldest, date, redact::description(&entry.description));
+				old_changes += 1;
+			}
+		}
+		if old_changes > 0 && !options.allow_old {
+			return Err(Error::Failed(tr!("found {} changes to entries older than {} days, use --allow-old to undo them anyway", old_changes, max_age)));
+		}
+	}
+
+	// Undo the changes one by one, since later changes may depend on earlier ones.
+	let limiter = RateLimiter::new(std::time::Duration::from_secs(1));
+	let mut failures = Vec::new();
+	for record in changes.iter().rev() {
+		if let Err(failure) = undo_change(api, &record.change, options.dry_run, &limiter).await {
+			if !options.keep_going {
+				return Err(Error::Failed(format!("failed to {}", failure)));
 			}
+			log::error!("failed to {}", failure);
+			failures.push(failure);
 		}
 	}
+	report_failures(&failures, changes.len())?;
 
+	if options.dry_run {
+		log::info!("{}", tr!("not undoing {} changes, use --confirm to undo them", changes.len()));
+	}
 	Ok(())
 }
 
-/// Synchronize logged hours to Paymo.
-async fn sync_to_paymo(api: &ApiClient, file: &Path, task_ids: &Path, period: &PartialDate, dry_run: bool) -> Result<(), ()> {
-	let period = period.as_range();
+/// Undo a single change from the journal.
+///
+/// Returns a description of the change if it failed.
+async fn undo_change(api: &ApiClient, change: &journal::Change, dry_run: bool, limiter: &RateLimiter) -> Result<(), String> {
+	match change {
+		journal::Change::Add { entry_id, .. } => {
+			log::warn!("Deleting added entry {}", entry_id);
+			if !dry_run {
+				limiter.wait().await;
+				let entry = api.get_entry(*entry_id).await
+					.map_err(|e| format!("delete entry {}: {}", entry_id, e))?;
+				api.delete_entry(&entry).await
+					.map_err(|e| format!("delete entry {}: {}", entry_id, e))?;
+			}
+		},
+		journal::Change::Delete { entry } => {
+			log::info!("Restoring deleted entry {}: {}", entry.id, describe_server_entry(entry));
+			if !dry_run {
+				limiter.wait().await;
+				restore_entry(api, entry).await
+					.map_err(|e| format!("restore entry {}: {}", entry.id, e))?;
+			}
+		},
+		journal::Change::Update { entry } => {
+			log::info!("Restoring changed entry {}: {}", entry.id, describe_server_entry(entry));
+			if !dry_run {
+				limiter.wait().await;
+				let current = api.get_entry(entry.id).await
+					.map_err(|e| format!("restore entry {}: {}", entry.id, e))?;
+				let update = api_client::EntryUpdate {
+					task_id: Some(entry.task_id),
+					date: entry.date.filter(|_| entry.is_bulk).map(timezone::from_naive_date).transpose()
+						.map_err(|e| format!("restore entry {}: {}", entry.id, e))?,
+					duration: Some(entry.total_seconds()).filter(|_| entry.is_bulk),
+					end_time: entry.end_time.filter(|_| !entry.is_bulk),
+					description: Some(entry.description.clone()),
+				};
+				api.update_entry(&current, &update).await
+					.map_err(|e| format!("restore entry {}: {}", entry.id, e))?;
+			}
+		},
+	}
+	Ok(())
+}
 
-	// Read all entries from the hour log.
-	let mut entries = uurlog::parse_file(file)
-		.map_err(|e| log::error!("failed to read {}: {}", file.display(), e))?;
+/// Add a deleted entry again, for the same user.
+async fn restore_entry(api: &ApiClient, entry: &types::TimeEntry) -> Result<u64, String> {
+	let user_api = ApiClient {
+		user_id: Some(entry.user_id),
+		..api.clone()
+	};
+	match (entry.date, entry.start_time, entry.end_time) {
+		(_, Some(start), Some(end)) if !entry.is_bulk => {
+			user_api.add_entry_with_times(entry.task_id, start, end, &entry.description).await
+		},
+		(Some(date), _, _) => {
+			user_api.add_entry(entry.task_id, timezone::from_naive_date(date)?, entry.total_seconds(), &entry.description).await
+		},
+		_ => Err(String::from("the entry has no date, or no start and end time")),
+	}
+}
 
-	// Filter entries on period.
-	entries.retain(|entry| period.contains(&entry.date));
+/// Describe an entry from the server for log messages.
+///
+/// Entries without a date use the UTC date of the start time.
+fn describe_server_entry(entry: &types::TimeEntry) -> String {
+	let date = entry.local_date(chrono_tz::UTC).map(|x| x.to_string()).unwrap_or_else(|| String::from("????"));
+	let hours = uurlog::Hours::from_minutes(entry.total_seconds() / 60);
+	format!("{}, {}, task {}, {}", date, hours, entry.task_id, redact::description(&entry.description))
+}
 
-	// Read the tag to task ID mapping from file.
-	let task_ids = parse_tasks::read_task_ids(task_ids)
-		.map_err(|e| log::error!("failed to read task IDs from {}: {}", task_ids.display(), e))?;
+/// Refuse to delete more entries than allowed by --max-deletions, or by `DEFAULT_MAX_PERIOD_DELETIONS` without it.
+fn check_max_period_deletions(deletions: usize, options: &SyncOptions) -> Result<(), Error> {
+	match options.max_deletions {
+		Some(_) => check_max_deletions(deletions, options),
+		None if deletions > DEFAULT_MAX_PERIOD_DELETIONS => {
+			Err(Error::Failed(tr!("refusing to delete {} entries, which is more than the default maximum of {}, use --max-deletions to raise it", deletions, DEFAULT_MAX_PERIOD_DELETIONS)))
+		},
+		None => Ok(()),
+	}
+}
 
-	// Get our Paymo user ID.
-	let user = api.my_user().await
-		.map_err(|e| log::error!("failed to determine user ID: {}", e))?;
+/// Refuse to continue if more entries would be deleted than allowed.
+fn check_max_deletions(deletions: usize, options: &SyncOptions) -> Result<(), Error> {
+	match options.max_deletions {
+		Some(max) if deletions > max => {
+			Err(Error::Failed(tr!("refusing to delete {} entries, which is more than the maximum of {} set by --max-deletions", deletions, max)))
+		},
+		_ => Ok(()),
+	}
+}
 
-	// Find the right task ID with each hour log entry and index them by date.
-	let mut entries_with_tasks = get_tasks_with_entries(&entries, &task_ids)?;
+/// Delete entries from the server, or only log them for a dry run.
+async fn delete_server_entries(
+	api: &ApiClient,
+	entries: &[&types::TimeEntry],
+	timezone: chrono_tz::Tz,
+	options: &SyncOptions,
+	limiter: &RateLimiter,
+	failures: &mut Vec<String>,
+) -> Result<(), Error> {
+	let deletions: Vec<_> = entries.iter().map(|&delete_entry| async move {
+		let date = delete_entry.local_date(timezone).map(|x| x.to_string()).unwrap_or_else(|| String::from("????"));
+		let hours = uurlog::Hours::from_minutes(delete_entry.total_seconds() / 60);
+		let description = redact::description(&delete_entry.description);
+		log::warn!("{}", tr!("Deleting entry {}: {}, {}, {}", delete_entry.id, date, hours, description));
+		if !options.dry_run {
+			limiter.wait().await;
+			if let Err(e) = api.delete_entry(delete_entry).await {
+				log::error!("{}", e);
+				return Err(format!("delete entry {}: {}, {}, {}", delete_entry.id, date, hours, description));
+			}
+		}
+		Ok(())
+	}).collect();
+	apply_changes(deletions, options, failures).await
+}
 
-	// Get the existing entries for the period.
-	let old_entries = api.get_time_entries(&api_client::TimeEntryFilter::new().user_id(user.id).period(period.clone()))
-		.await
-		.map_err(|e| log::error!("failed to get time entries between {} and {}: {}", period.start, period.end, e))?;
-	log::debug!("found {} existing entries on server between {} and {}", old_entries.len(), period.start, period.end);
+/// Return an error listing the failed changes, if there were any.
+fn report_failures(failures: &[String], total: usize) -> Result<(), Error> {
+	if failures.is_empty() {
+		return Ok(());
+	}
+	let mut message = tr!("failed to apply {} of {} changes:", failures.len(), total);
+	for failure in failures {
+		message.push_str("\n  ");
+		message.push_str(failure);
+	}
+	Err(Error::PartialFailure(message))
+}
 
-	// Collect old entries to delete and new entries to add.
-	let mut delete_entries = Vec::new();
+/// Refuse to upload entries dated in the future, they are most likely typos.
+///
+/// With `options.allow_future`, the entries are only logged as warnings.
+fn check_future_entries<'a>(entries: impl IntoIterator<Item = &'a Entry>, today: chrono::NaiveDate, options: &SyncOptions) -> Result<(), Error> {
+	let future_entries: Vec<_> = entries.into_iter()
+		.filter(|x| timezone::to_naive_date(x.date) > today)
+		.collect();
+	let level = if options.allow_future { log::Level::Warn } else { log::Level::Error };
+	for entry in &future_entries {
+		log::log!(level, "entry is dated in the future: {}", entry);
+	}
+	if !future_entries.is_empty() && !options.allow_future {
+		return Err(Error::Failed(tr!("found {} entries dated in the future, use --allow-future to synchronize them anyway", future_entries.len())));
+	}
+	Ok(())
+}
 
-	for old_entry in &old_entries {
-		// See if there is a matching entry in our own hour log.
-		let matching_index = entries_with_tasks
-			.iter()
-			.position(|(new_entry, _task_id)| {
-				new_entry.description == old_entry.description
-				&& new_entry.hours.total_minutes() * 60 == old_entry.duration
-			});
+/// Check that the user is assigned to all tasks.
+///
+/// With `options.auto_assign`, the user is assigned to tasks they are not yet assigned to,
+/// and an error is returned if that fails.
+/// Otherwise, only a warning is given, since Paymo may still accept entries on the tasks.
+async fn check_task_assignments(api: &ApiClient, user_id: u64, tasks: &BTreeMap<u64, types::Task>, options: &SyncOptions) -> Result<(), Error> {
+	let mut unassigned = Vec::new();
+	for task in tasks.values() {
+		if task.users.contains(&user_id) {
+			continue;
+		}
 
-		// If there is, don't upload that entry.
-		if let Some(matching_index) = matching_index {
-			entries_with_tasks.remove(matching_index);
-		// If there isn't, delete the old entry.
-		} else {
-			delete_entries.push(old_entry);
+		if !options.auto_assign {
+			unassigned.push(format!("{} ({})", task.id, task.name));
+			continue;
+		}
+
+		log::info!("Assigning user {} to task {} ({})", user_id, task.id, task.name);
+		if !options.dry_run {
+			let mut users = task.users.clone();
+			users.push(user_id);
+			api.set_task_users(task.id, &users).await
+				.map_err(|e| format!("failed to assign user {} to task {}: {}", user_id, task.id, e))?;
 		}
 	}
 
-	// Delete all old entries without match in the log.
-	for &delete_entry in &delete_entries {
-		let date = delete_entry.date.as_deref().or(delete_entry.start_time.as_deref()).unwrap_or("????");
-		let hours = uurlog::Hours::from_minutes(delete_entry.duration / 60);
-		log::warn!("Deleting entry {}: {}, {}, {}", delete_entry.id, date, hours, delete_entry.description);
-		if !dry_run {
-			api.delete_entry(delete_entry.id)
-				.await
-				.map_err(|e| log::error!("{}", e))?;
-			tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+	if !unassigned.is_empty() {
+		log::warn!("{}", tr!("you are not assigned to task {}, adding entries may fail, use --auto-assign to assign yourself", unassigned.join(", ")));
+	}
+	Ok(())
+}
+
+/// Check that configured tasks exist on Paymo.
+///
+/// Missing tasks that entries are mapped to are an error, other missing tasks only give a warning.
+async fn check_configured_tasks(api: &ApiClient, configured: &BTreeSet<u64>, mapped: &BTreeSet<u64>) -> Result<(), Error> {
+	let existing: BTreeSet<u64> = api.get_tasks().await
+		.map_err(|e| format!("failed to get tasks: {}", e))?
+		.into_iter()
+		.map(|x| x.id)
+		.collect();
+
+	let mut missing = Vec::new();
+	for task_id in configured.difference(&existing) {
+		if mapped.contains(task_id) {
+			missing.push(task_id.to_string());
+		} else {
+			log::warn!("{}", tr!("the configuration maps tags to task {}, which does not exist on Paymo", task_id));
 		}
 	}
+	if !missing.is_empty() {
+		return Err(Error::Failed(tr!("entries are mapped to tasks that do not exist on Paymo: {}, update the task IDs in the configuration", missing.join(", "))));
+	}
+	Ok(())
+}
 
-	// Upload all new entries without existing entry on Paymo.
-	for &(entry, task_id) in &entries_with_tasks {
-		log::info!("Adding entry with task id {}: {}", task_id, entry);
-		if !dry_run {
-			api.add_entry(task_id, entry.date, entry.hours, &entry.description)
-				.await
-				.map_err(|e| log::error!("{}", e))?;
-			tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+/// Refuse to add entries to completed tasks.
+///
+/// The error names the completed tasks, and suggests the active task with the most similar name in the same project.
+async fn check_completed_tasks(api: &ApiClient, tasks: impl IntoIterator<Item = &types::Task>) -> Result<(), Error> {
+	let completed: Vec<_> = tasks.into_iter().filter(|x| x.complete).collect();
+	if completed.is_empty() {
+		return Ok(());
+	}
+
+	let all_tasks = api.get_tasks().await
+		.map_err(|e| format!("failed to get tasks: {}", e))?;
+	let mut message = tr!("entries are mapped to {} completed tasks:", completed.len());
+	for task in completed {
+		let alternative = all_tasks.iter()
+			.filter(|x| x.project_id == task.project_id && !x.complete)
+			.map(|x| (strsim::jaro_winkler(&task.name.to_lowercase(), &x.name.to_lowercase()), x))
+			.max_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+		message.push_str("\n  ");
+		match alternative {
+			Some((_, alternative)) => message.push_str(&tr!(
+				"task {} ({}) is completed, reopen it on Paymo or map the entries to an active task like {} ({})",
+				task.id, task.name, alternative.id, alternative.name,
+			)),
+			None => message.push_str(&tr!(
+				"task {} ({}) is completed and its project has no active tasks, reopen it on Paymo",
+				task.id, task.name,
+			)),
 		}
 	}
+	Err(Error::Failed(message))
+}
+
+/// Add an entry to Paymo.
+///
+/// Entries with clock times are added with a start and end time, other entries with a date and duration.
+async fn add_entry(api: &ApiClient, task_id: u64, entry: &Entry, description: &str, timezone: chrono_tz::Tz) -> Result<u64, String> {
+	match &entry.clock_times {
+		None => api.add_entry(task_id, entry.date, entry.seconds, description).await,
+		Some(clock_times) => {
+			let start = timezone::local_time_to_utc(entry.date, clock_times.start, timezone);
+			let mut end = timezone::local_time_to_utc(entry.date, clock_times.end, timezone);
+			if end <= start {
+				end = timezone::local_time_to_utc(entry.date.next(), clock_times.end, timezone);
+			}
+			api.add_entry_with_times(task_id, start, end, description).await
+		},
+	}
+}
+
+/// Get the description to upload for a local entry, with a fingerprint if enabled in the configuration.
+fn upload_description(entry: &Entry, config: &Config) -> String {
+	if config.general.fingerprints {
+		fingerprint::description_with_fingerprint(entry)
+	} else {
+		entry.description.clone()
+	}
+}
 
+/// Apply changes with up to `options.concurrency` changes in flight at the same time.
+///
+/// Each change resolves to a description of the change if it failed.
+/// With `options.keep_going`, failed changes are added to `failures`.
+/// Otherwise, the first failure aborts all remaining changes.
+///
+/// Callers collect the changes in a `Vec` first, because a lazy iterator over borrowed entries
+/// makes the future too general to be `Send`, which the server needs.
+async fn apply_changes<F>(changes: impl IntoIterator<Item = F>, options: &SyncOptions, failures: &mut Vec<String>) -> Result<(), Error>
+where
+	F: Future<Output = Result<(), String>>,
+{
+	let mut results = futures::stream::iter(changes).buffer_unordered(options.concurrency);
+	while let Some(result) = results.next().await {
+		if let Err(failure) = result {
+			if !options.keep_going {
+				return Err(Error::Failed(format!("failed to {}", failure)));
+			}
+			failures.push(failure);
+		}
+	}
 	Ok(())
 }
 
+/// Check if a local entry matches an entry from the server.
+///
+/// Entries match if they have the same date, task and description,
+/// and their durations differ by at most `duration_tolerance` seconds, or 59 seconds if the server duration is a whole number of minutes.
+/// With `fingerprints`, server entries with a fingerprint are matched on the fingerprint instead of the description.
+fn entries_match(local: &Entry, task_id: u64, server: &types::TimeEntry, timezone: chrono_tz::Tz, duration_tolerance: u32, fingerprints: bool) -> bool {
+	let same_description = match fingerprint::find(&server.description) {
+		Some(fingerprint) if fingerprints => fingerprint == fingerprint::compute(local),
+		_ => server.description == local.description,
+	};
+	same_description && entries_match_except_description(local, task_id, server, timezone, duration_tolerance)
+}
+
+/// Check if a local entry matches an entry from the server, ignoring the description.
+fn entries_match_except_description(local: &Entry, task_id: u64, server: &types::TimeEntry, timezone: chrono_tz::Tz, duration_tolerance: u32) -> bool {
+	let local_duration = local.seconds;
+	let server_duration = server.total_seconds();
+	let duration_difference = local_duration.max(server_duration) - local_duration.min(server_duration);
+
+	// Entries used to be uploaded in whole minutes, so do not replace them only because the local entry has seconds.
+	let duration_tolerance = if server_duration.is_multiple_of(60) {
+		duration_tolerance.max(59)
+	} else {
+		duration_tolerance
+	};
+
+	server.local_date(timezone) == Some(timezone::to_naive_date(local.date))
+		&& server.task_id == task_id
+		&& duration_difference <= duration_tolerance
+}
+
+/// Get the IDs of the tasks that a synchronization is limited to with --task-id and --project-id.
+///
+/// Returns [`None`] if the synchronization is not limited.
+async fn sync_scope(api: &ApiClient, options: &SyncOptions) -> Result<Option<BTreeSet<u64>>, Error> {
+	let project_task_ids = match options.project_id {
+		None => None,
+		Some(project_id) => {
+			let tasks = api.get_tasks().await
+				.map_err(|e| format!("failed to get tasks: {}", e))?;
+			Some(tasks.into_iter().filter(|x| x.project_id == project_id).map(|x| x.id).collect::<BTreeSet<_>>())
+		},
+	};
+	Ok(match (options.task_id, project_task_ids) {
+		(Some(task_id), Some(task_ids)) => Some(task_ids.into_iter().filter(|&x| x == task_id).collect()),
+		(Some(task_id), None) => Some(BTreeSet::from([task_id])),
+		(None, task_ids) => task_ids,
+	})
+}
+
 /// Find the right task ID for each entry.
-fn get_tasks_with_entries<'a>(entries: &'a [uurlog::Entry], task_ids: &BTreeMap<String, u64>) -> Result<Vec<(&'a uurlog::Entry, u64)>, ()> {
+///
+/// Unless the policy is to fail, entries without task are added to `unmapped` with the reason.
+fn get_tasks_with_entries<'a>(
+	entries: &'a [Entry],
+	task_ids: &task_ids::TaskIds,
+	policy: task_ids::UnmappedPolicy,
+	unmapped: &mut Vec<(&'a Entry, String)>,
+) -> Result<Vec<(&'a Entry, u64)>, String> {
 	let mut result = Vec::new();
 
 	for entry in entries {
-		let task_id = if entry.tags.len() == 1 {
-			task_ids.get(&entry.tags[0]).ok_or_else(|| log::error!("unknown task ID for tag: {}", entry.tags[0]))?
-		} else if entry.tags.is_empty() {
-			log::error!("entry has no tags, unable to determine project/task");
-			log::error!("  {}", entry);
-			return Err(());
-		} else {
-			log::error!("entry has multiple tags, unable to determine project/task");
-			log::error!("  {}", entry);
-			return Err(());
-		};
-
-		result.push((entry, *task_id));
+		match task_ids.resolve(&entry.tags) {
+			Ok(task_id) => result.push((entry, task_id)),
+			Err(e) if policy == task_ids::UnmappedPolicy::Error => return Err(format!("{}\n  {}", e, entry)),
+			Err(e) => unmapped.push((entry, e.to_string())),
+		}
 	}
 
 	Ok(result)
 }
 
+/// List the entries that were skipped because they could not be mapped to a task.
+fn report_unmapped(unmapped: &[(Entry, String)], policy: task_ids::UnmappedPolicy) {
+	let level = match policy {
+		task_ids::UnmappedPolicy::Skip => log::Level::Info,
+		task_ids::UnmappedPolicy::Warn => log::Level::Warn,
+		task_ids::UnmappedPolicy::Error => return,
+	};
+	if unmapped.is_empty() {
+		return;
+	}
+	log::log!(level, "{}", tr!("skipped {} entries without task:", unmapped.len()));
+	for (entry, reason) in unmapped {
+		log::log!(level, "  {} ({})", entry, reason);
+	}
+}
+
 /// Create an index for a sequence.
 ///
 /// The sequence is indexed based on the return value of the `key` function.
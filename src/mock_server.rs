@@ -0,0 +1,658 @@
+//! A mock Paymo server with in-memory state, for development and testing.
+//!
+//! The server implements the subset of the Paymo API used by this tool.
+//! Any API token is accepted, and all changes are lost when the server stops.
+
+mod filter;
+
+use filter::Filter;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The number of requests allowed per rate limit period.
+const RATE_LIMIT: u32 = 120;
+
+/// The length of a rate limit period.
+const RATE_LIMIT_PERIOD: Duration = Duration::from_secs(60);
+
+/// The data served by the mock server.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Data {
+	/// The users of the company, the first user is the owner of the API token.
+	#[serde(default)]
+	users: Vec<Value>,
+
+	#[serde(default)]
+	company: Value,
+
+	#[serde(default)]
+	clients: Vec<Value>,
+
+	#[serde(default)]
+	projects: Vec<Value>,
+
+	#[serde(default)]
+	tasks: Vec<Value>,
+
+	#[serde(default)]
+	entries: Vec<Value>,
+
+	#[serde(default)]
+	expenses: Vec<Value>,
+}
+
+impl Data {
+	/// Read the initial data from a JSON file in the same format as the API responses.
+	pub fn from_file(path: &Path) -> Result<Self, String> {
+		let data = std::fs::read(path)
+			.map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+		let data: Self = serde_json::from_slice(&data)
+			.map_err(|e| format!("failed to parse {}: {}", path.display(), e))?;
+		if data.users.is_empty() {
+			return Err(format!("failed to parse {}: the data must contain at least one user", path.display()));
+		}
+		Ok(data)
+	}
+
+	/// Get a small example workspace with one user, client, project and task.
+	pub fn example() -> Self {
+		let now = now();
+		Self {
+			users: vec![json!({
+				"id": 1,
+				"name": "Mock User",
+				"email": "mock@example.com",
+				"type": "Admin",
+				"active": true,
+				"timezone": "UTC",
+				"date_format": "Y-m-d",
+				"time_format": "H:i",
+				"week_start": "1",
+				"created_on": now,
+				"updated_on": now,
+			})],
+			company: json!({
+				"id": 1,
+				"name": "Mock Company",
+				"timezone": "UTC",
+				"date_format": "Y-m-d",
+				"time_format": "H:i",
+				"week_start": "1",
+				"working_days": "1,2,3,4,5",
+				"account_type": "business",
+				"created_on": now,
+				"updated_on": now,
+			}),
+			clients: vec![json!({
+				"id": 1,
+				"name": "Mock Client",
+				"active": true,
+				"created_on": now,
+				"updated_on": now,
+			})],
+			projects: vec![json!({
+				"id": 1,
+				"name": "Mock Project",
+				"client_id": 1,
+				"active": true,
+				"billable": true,
+				"users": [1],
+				"managers": [1],
+				"created_on": now,
+				"updated_on": now,
+			})],
+			tasks: vec![json!({
+				"id": 1,
+				"name": "Mock Task",
+				"project_id": 1,
+				"user_id": 1,
+				"complete": false,
+				"billable": true,
+				"users": [1],
+				"created_on": now,
+				"updated_on": now,
+			})],
+			entries: Vec::new(),
+			expenses: Vec::new(),
+		}
+	}
+}
+
+/// Run the mock server until it fails.
+pub async fn run(address: SocketAddr, data: Data) -> Result<(), String> {
+	let server = Arc::new(Server::new(data)?);
+	let make_service = hyper::service::make_service_fn(move |_| {
+		let server = server.clone();
+		async move {
+			Ok::<_, Infallible>(hyper::service::service_fn(move |request| {
+				let server = server.clone();
+				async move { Ok::<_, Infallible>(server.handle(request).await) }
+			}))
+		}
+	});
+
+	let server = hyper::Server::try_bind(&address)
+		.map_err(|e| format!("failed to listen on {}: {}", address, e))?
+		.serve(make_service);
+	log::info!("Mock Paymo server listening on http://{}/api", server.local_addr());
+	server.await.map_err(|e| format!("mock server failed: {}", e))
+}
+
+/// The state of the mock server.
+struct Server {
+	state: Mutex<State>,
+	rate_limit: Mutex<RateLimit>,
+}
+
+/// The in-memory data of the mock server, by ID.
+struct State {
+	users: BTreeMap<u64, Value>,
+	company: Value,
+	clients: BTreeMap<u64, Value>,
+	projects: BTreeMap<u64, Value>,
+	tasks: BTreeMap<u64, Value>,
+	entries: BTreeMap<u64, Value>,
+	next_entry_id: u64,
+	invoices: BTreeMap<u64, Value>,
+	expenses: BTreeMap<u64, Value>,
+	next_expense_id: u64,
+}
+
+/// The rate limit window of the mock server.
+struct RateLimit {
+	start: Instant,
+	requests: u32,
+}
+
+/// An error response.
+struct Failure {
+	status: StatusCode,
+	message: String,
+}
+
+impl Failure {
+	fn new(status: StatusCode, message: impl Into<String>) -> Self {
+		Self {
+			status,
+			message: message.into(),
+		}
+	}
+
+	fn not_found(what: &str, id: u64) -> Self {
+		Self::new(StatusCode::NOT_FOUND, format!("{} with ID {} does not exist", what, id))
+	}
+
+	fn bad_request(message: impl Into<String>) -> Self {
+		Self::new(StatusCode::BAD_REQUEST, message)
+	}
+}
+
+impl Server {
+	fn new(data: Data) -> Result<Self, String> {
+		let entries = by_id("entries", data.entries)?;
+		let next_entry_id = entries.keys().next_back().map(|x| x + 1).unwrap_or(1);
+		let expenses = by_id("expenses", data.expenses)?;
+		let next_expense_id = expenses.keys().next_back().map(|x| x + 1).unwrap_or(1);
+		let state = State {
+			users: by_id("users", data.users)?,
+			company: data.company,
+			clients: by_id("clients", data.clients)?,
+			projects: by_id("projects", data.projects)?,
+			tasks: by_id("tasks", data.tasks)?,
+			entries,
+			next_entry_id,
+			invoices: BTreeMap::new(),
+			expenses,
+			next_expense_id,
+		};
+		Ok(Self {
+			state: Mutex::new(state),
+			rate_limit: Mutex::new(RateLimit {
+				start: Instant::now(),
+				requests: 0,
+			}),
+		})
+	}
+
+	async fn handle(&self, request: Request<Body>) -> Response<Body> {
+		let method = request.method().clone();
+		let path = request.uri().path().to_owned();
+		let query = request.uri().query().unwrap_or("").to_owned();
+		log::debug!("{} {}", method, path);
+
+		let remaining = match self.check_rate_limit() {
+			Some(remaining) => remaining,
+			None => {
+				let response = error_response(Failure::new(StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded"));
+				return self.add_rate_limit_headers(response, 0);
+			},
+		};
+
+		if !has_basic_auth(&request) {
+			let mut response = error_response(Failure::new(StatusCode::UNAUTHORIZED, "missing or invalid API token"));
+			response.headers_mut().insert("www-authenticate", hyper::header::HeaderValue::from_static("Basic realm=\"Paymo API\""));
+			return self.add_rate_limit_headers(response, remaining);
+		}
+
+		let body = match hyper::body::to_bytes(request.into_body()).await {
+			Ok(body) => body,
+			Err(e) => return error_response(Failure::bad_request(format!("failed to read request body: {}", e))),
+		};
+
+		let result = self.route(&method, &path, &query, &body);
+		let response = match result {
+			Ok((status, body)) => json_response(status, &body),
+			Err(failure) => {
+				log::debug!("{} {}: {}", method, path, failure.message);
+				error_response(failure)
+			},
+		};
+		self.add_rate_limit_headers(response, remaining)
+	}
+
+	/// Count a request against the rate limit, and return the number of remaining requests.
+	///
+	/// Returns `None` if the rate limit is exceeded.
+	fn check_rate_limit(&self) -> Option<u32> {
+		let mut rate_limit = self.rate_limit.lock().unwrap();
+		if rate_limit.start.elapsed() >= RATE_LIMIT_PERIOD {
+			rate_limit.start = Instant::now();
+			rate_limit.requests = 0;
+		}
+		if rate_limit.requests >= RATE_LIMIT {
+			None
+		} else {
+			rate_limit.requests += 1;
+			Some(RATE_LIMIT - rate_limit.requests)
+		}
+	}
+
+	fn add_rate_limit_headers(&self, mut response: Response<Body>, remaining: u32) -> Response<Body> {
+		let headers = response.headers_mut();
+		headers.insert("x-ratelimit-limit", RATE_LIMIT.into());
+		headers.insert("x-ratelimit-remaining", remaining.into());
+		headers.insert("x-ratelimit-decay-period", RATE_LIMIT_PERIOD.as_secs().into());
+		if remaining == 0 {
+			let reset = RATE_LIMIT_PERIOD.saturating_sub(self.rate_limit.lock().unwrap().start.elapsed());
+			headers.insert("retry-after", (reset.as_secs() + 1).into());
+		}
+		response
+	}
+
+	fn route(&self, method: &Method, path: &str, query: &str, body: &[u8]) -> Result<(StatusCode, Value), Failure> {
+		let path = path.strip_prefix("/api").unwrap_or(path);
+		let segments: Vec<_> = path.trim_matches('/').split('/').collect();
+		let parameters = parse_query(query)?;
+		let mut state = self.state.lock().unwrap();
+
+		let ok = |value| Ok((StatusCode::OK, value));
+		match (method, segments.as_slice()) {
+			(&Method::GET, ["me"]) => {
+				let user = state.users.values().next().cloned();
+				ok(json!({ "users": user.into_iter().collect::<Vec<_>>() }))
+			},
+			(&Method::GET, ["company"]) => ok(json!({ "company": state.company })),
+			(&Method::GET, ["users"]) => ok(json!({ "users": list(&state.users, &parameters, |_| Value::Null)? })),
+			(&Method::GET, ["users", id]) => ok(json!({ "users": [get(&state.users, "user", id)?] })),
+			(&Method::GET, ["clients"]) => ok(json!({ "clients": list(&state.clients, &parameters, |_| Value::Null)? })),
+			(&Method::GET, ["clients", id]) => ok(json!({ "clients": [get(&state.clients, "client", id)?] })),
+			(&Method::GET, ["projects"]) => ok(json!({ "projects": list(&state.projects, &parameters, |_| Value::Null)? })),
+			(&Method::GET, ["projects", id]) => ok(json!({ "projects": [get(&state.projects, "project", id)?] })),
+			(&Method::GET, ["tasks"]) => ok(json!({ "tasks": list(&state.tasks, &parameters, |_| Value::Null)? })),
+			(&Method::GET, ["tasks", id]) => ok(json!({ "tasks": [get(&state.tasks, "task", id)?] })),
+			(&Method::PUT, ["tasks", id]) => {
+				let id = parse_id(id)?;
+				let update = parse_body(body)?;
+				let task = state.tasks.get_mut(&id).ok_or_else(|| Failure::not_found("task", id))?;
+				merge(task, update);
+				ok(json!({ "tasks": [task] }))
+			},
+			(&Method::GET, ["entries"]) => {
+				let projects = &state.projects;
+				let entries = list(&state.entries, &parameters, |entry| {
+					// Entries do not have a client ID, so look it up through the project.
+					let project_id = entry.get("project_id").and_then(Value::as_u64).unwrap_or(0);
+					projects.get(&project_id).and_then(|x| x.get("client_id")).cloned().unwrap_or(Value::Null)
+				})?;
+				ok(json!({ "entries": entries }))
+			},
+			(&Method::GET, ["entries", id]) => ok(json!({ "entries": [get(&state.entries, "entry", id)?] })),
+			(&Method::POST, ["entries"]) => {
+				let entry = state.add_entry(parse_body(body)?)?;
+				Ok((StatusCode::CREATED, json!({ "entries": [entry] })))
+			},
+			(&Method::PUT, ["entries", id]) => {
+				let entry = state.update_entry(parse_id(id)?, parse_body(body)?)?;
+				ok(json!({ "entries": [entry] }))
+			},
+			(&Method::DELETE, ["entries", id]) => {
+				let id = parse_id(id)?;
+				state.entries.remove(&id).ok_or_else(|| Failure::not_found("entry", id))?;
+				ok(json!({}))
+			},
+			(&Method::GET, ["invoices"]) => ok(json!({ "invoices": list(&state.invoices, &parameters, |_| Value::Null)? })),
+			(&Method::GET, ["invoices", id]) => ok(json!({ "invoices": [get(&state.invoices, "invoice", id)?] })),
+			(&Method::POST, ["invoices"]) => {
+				let invoice = state.add_invoice(parse_body(body)?)?;
+				Ok((StatusCode::CREATED, json!({ "invoices": [invoice] })))
+			},
+			(&Method::GET, ["expenses"]) => ok(json!({ "expenses": list(&state.expenses, &parameters, |_| Value::Null)? })),
+			(&Method::GET, ["expenses", id]) => ok(json!({ "expenses": [get(&state.expenses, "expense", id)?] })),
+			(&Method::POST, ["expenses"]) => {
+				let expense = state.add_expense(parse_body(body)?)?;
+				Ok((StatusCode::CREATED, json!({ "expenses": [expense] })))
+			},
+			(&Method::DELETE, ["expenses", id]) => {
+				let id = parse_id(id)?;
+				state.expenses.remove(&id).ok_or_else(|| Failure::not_found("expense", id))?;
+				ok(json!({}))
+			},
+			_ => Err(Failure::new(StatusCode::NOT_FOUND, format!("no such endpoint: {} {}", method, path))),
+		}
+	}
+}
+
+impl State {
+	/// Add a new time entry.
+	fn add_entry(&mut self, fields: serde_json::Map<String, Value>) -> Result<Value, Failure> {
+		let task_id = fields.get("task_id")
+			.and_then(Value::as_u64)
+			.ok_or_else(|| Failure::bad_request("missing task_id"))?;
+		let task = self.tasks.get(&task_id).ok_or_else(|| Failure::not_found("task", task_id))?;
+		let project_id = task.get("project_id").cloned().unwrap_or(Value::Null);
+		let user_id = match fields.get("user_id") {
+			Some(user_id) => user_id.clone(),
+			None => self.users.values().next().and_then(|x| x.get("id")).cloned().unwrap_or(Value::Null),
+		};
+		let user_id = user_id.as_u64().ok_or_else(|| Failure::bad_request("invalid user_id"))?;
+		if !self.users.contains_key(&user_id) {
+			return Err(Failure::not_found("user", user_id));
+		}
+
+		let is_bulk = fields.contains_key("date");
+		if is_bulk && !fields.contains_key("duration") {
+			return Err(Failure::bad_request("entries with a date also need a duration"));
+		} else if !is_bulk && !fields.contains_key("start_time") {
+			return Err(Failure::bad_request("entries need a date and duration, or a start time"));
+		}
+
+		let id = self.next_entry_id;
+		self.next_entry_id += 1;
+		let now = now();
+		let mut entry = json!({
+			"id": id,
+			"task_id": task_id,
+			"user_id": user_id,
+			"project_id": project_id,
+			"start_time": null,
+			"end_time": null,
+			"date": null,
+			"duration": 0,
+			"description": "",
+			"added_manually": true,
+			"invoice_item_id": null,
+			"billed": false,
+			"is_bulk": is_bulk,
+			"created_on": now,
+			"updated_on": now,
+		});
+		merge(&mut entry, fields);
+		update_duration(&mut entry)?;
+		self.entries.insert(id, entry.clone());
+		Ok(entry)
+	}
+
+	/// Add a new draft invoice.
+	fn add_invoice(&mut self, fields: serde_json::Map<String, Value>) -> Result<Value, Failure> {
+		let client_id = fields.get("client_id")
+			.and_then(Value::as_u64)
+			.ok_or_else(|| Failure::bad_request("missing client_id"))?;
+		if !self.clients.contains_key(&client_id) {
+			return Err(Failure::not_found("client", client_id));
+		}
+		let mut subtotal = 0.0;
+		for item in fields.get("items").and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default() {
+			let number = |key: &str| item.get(key).and_then(Value::as_f64).ok_or_else(|| Failure::bad_request(format!("invalid or missing item {}", key)));
+			subtotal += number("price_unit")? * number("quantity")?;
+		}
+
+		let id = self.invoices.keys().next_back().map(|x| x + 1).unwrap_or(1);
+		let now = now();
+		let mut invoice = json!({
+			"id": id,
+			"number": format!("INV-{:04}", id),
+			"client_id": client_id,
+			"status": "draft",
+			"currency": "EUR",
+			"date": null,
+			"title": "",
+			"subtotal": subtotal,
+			"total": subtotal,
+			"created_on": now,
+			"updated_on": now,
+		});
+		merge(&mut invoice, fields);
+		self.invoices.insert(id, invoice.clone());
+		Ok(invoice)
+	}
+
+	/// Add a new expense.
+	///
+	/// Expenses for a project get the client of the project.
+	fn add_expense(&mut self, mut fields: serde_json::Map<String, Value>) -> Result<Value, Failure> {
+		if let Some(project_id) = fields.get("project_id").and_then(Value::as_u64) {
+			let project = self.projects.get(&project_id).ok_or_else(|| Failure::not_found("project", project_id))?;
+			let client_id = project.get("client_id").cloned().unwrap_or(Value::Null);
+			fields.entry("client_id").or_insert(client_id);
+		}
+		let client_id = fields.get("client_id")
+			.and_then(Value::as_u64)
+			.ok_or_else(|| Failure::bad_request("missing client_id"))?;
+		if !self.clients.contains_key(&client_id) {
+			return Err(Failure::not_found("client", client_id));
+		}
+		if !fields.get("amount").is_some_and(Value::is_number) {
+			return Err(Failure::bad_request("invalid or missing amount"));
+		}
+		let user_id = match fields.get("user_id") {
+			Some(user_id) => user_id.clone(),
+			None => self.users.values().next().and_then(|x| x.get("id")).cloned().unwrap_or(Value::Null),
+		};
+
+		let id = self.next_expense_id;
+		self.next_expense_id += 1;
+		let now = now();
+		let mut expense = json!({
+			"id": id,
+			"client_id": client_id,
+			"project_id": null,
+			"user_id": user_id,
+			"amount": 0,
+			"currency": "EUR",
+			"date": null,
+			"notes": "",
+			"invoiced": false,
+			"created_on": now,
+			"updated_on": now,
+		});
+		merge(&mut expense, fields);
+		self.expenses.insert(id, expense.clone());
+		Ok(expense)
+	}
+
+	/// Change fields of an existing time entry.
+	fn update_entry(&mut self, id: u64, fields: serde_json::Map<String, Value>) -> Result<Value, Failure> {
+		if let Some(task_id) = fields.get("task_id").and_then(Value::as_u64) {
+			if !self.tasks.contains_key(&task_id) {
+				return Err(Failure::not_found("task", task_id));
+			}
+		}
+		let project_id = fields.get("task_id")
+			.and_then(Value::as_u64)
+			.and_then(|x| self.tasks.get(&x))
+			.and_then(|x| x.get("project_id"))
+			.cloned();
+
+		let entry = self.entries.get_mut(&id).ok_or_else(|| Failure::not_found("entry", id))?;
+		merge(entry, fields);
+		if let Some(project_id) = project_id {
+			entry["project_id"] = project_id;
+		}
+		entry["updated_on"] = now().into();
+		update_duration(entry)?;
+		Ok(entry.clone())
+	}
+}
+
+/// Compute the duration of an entry with a start and end time.
+fn update_duration(entry: &mut Value) -> Result<(), Failure> {
+	let parse = |key: &str| -> Result<Option<chrono::DateTime<chrono::Utc>>, Failure> {
+		match entry.get(key).and_then(Value::as_str) {
+			None => Ok(None),
+			Some(x) => chrono::DateTime::parse_from_rfc3339(x)
+				.map(|x| Some(x.into()))
+				.map_err(|e| Failure::bad_request(format!("invalid {}: {}", key, e))),
+		}
+	};
+
+	if let (Some(start_time), Some(end_time)) = (parse("start_time")?, parse("end_time")?) {
+		if end_time < start_time {
+			return Err(Failure::bad_request("end_time is before start_time"));
+		}
+		entry["duration"] = (end_time - start_time).num_seconds().into();
+	}
+	Ok(())
+}
+
+/// Index a list of objects by their ID.
+fn by_id(name: &str, values: Vec<Value>) -> Result<BTreeMap<u64, Value>, String> {
+	let mut result = BTreeMap::new();
+	for value in values {
+		let id = value.get("id")
+			.and_then(Value::as_u64)
+			.ok_or_else(|| format!("{}: object without valid ID: {}", name, value))?;
+		if result.insert(id, value).is_some() {
+			return Err(format!("{}: duplicate ID: {}", name, id));
+		}
+	}
+	Ok(result)
+}
+
+/// Get a single object by ID.
+fn get<'a>(values: &'a BTreeMap<u64, Value>, what: &str, id: &str) -> Result<&'a Value, Failure> {
+	let id = parse_id(id)?;
+	values.get(&id).ok_or_else(|| Failure::not_found(what, id))
+}
+
+/// List the objects matching the query parameters.
+///
+/// The `client_id` function gives the client ID of objects that do not have it as a field.
+fn list(values: &BTreeMap<u64, Value>, parameters: &BTreeMap<String, String>, client_id: impl Fn(&Value) -> Value) -> Result<Vec<Value>, Failure> {
+	let filter = match parameters.get("where") {
+		Some(filter) => Filter::parse(filter).map_err(Failure::bad_request)?,
+		None => Filter::All(Vec::new()),
+	};
+
+	let mut result: Vec<_> = values.values()
+		.filter(|value| filter.matches(&|key| lookup(value, key, &client_id)))
+		.cloned()
+		.collect();
+
+	if let Some(field) = parameters.get("order_by") {
+		result.sort_by(|a, b| filter::compare(&a[field.as_str()], &b[field.as_str()]).unwrap_or(std::cmp::Ordering::Equal));
+		if parameters.get("order").map(String::as_str) == Some("desc") {
+			result.reverse();
+		}
+	}
+
+	if let Some(page) = parameters.get("page") {
+		let page: usize = page.parse().map_err(|_| Failure::bad_request(format!("invalid page: {}", page)))?;
+		let per_page: usize = match parameters.get("per_page") {
+			Some(x) => x.parse().map_err(|_| Failure::bad_request(format!("invalid per_page: {}", x)))?,
+			None => 100,
+		};
+		result = result.into_iter().skip(page.saturating_sub(1) * per_page).take(per_page).collect();
+	}
+
+	Ok(result)
+}
+
+/// Get the value of a field for evaluating a filter.
+fn lookup(value: &Value, key: &str, client_id: &impl Fn(&Value) -> Value) -> Value {
+	match key {
+		"client_id" if value.get("client_id").is_none() => client_id(value),
+		// Entries with only a date are placed at noon UTC, so that they fall on the right date in most timezones.
+		"time_interval" => match (value.get("start_time"), value.get("date").and_then(Value::as_str)) {
+			(Some(Value::String(start_time)), _) => Value::String(start_time.clone()),
+			(_, Some(date)) => Value::String(format!("{}T12:00:00Z", date)),
+			_ => Value::Null,
+		},
+		key => value.get(key).cloned().unwrap_or(Value::Null),
+	}
+}
+
+/// Parse the query string of a request.
+fn parse_query(query: &str) -> Result<BTreeMap<String, String>, Failure> {
+	let mut result = BTreeMap::new();
+	for pair in query.split('&').filter(|x| !x.is_empty()) {
+		let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+		// The query string may use `+` for spaces.
+		let decode = |x: &str| urlencoding::decode(&x.replace('+', " "))
+			.map(|x| x.into_owned())
+			.map_err(|e| Failure::bad_request(format!("invalid query string: {}", e)));
+		result.insert(decode(key)?, decode(value)?);
+	}
+	Ok(result)
+}
+
+fn parse_id(id: &str) -> Result<u64, Failure> {
+	id.parse().map_err(|_| Failure::new(StatusCode::NOT_FOUND, format!("invalid ID: {}", id)))
+}
+
+fn parse_body(body: &[u8]) -> Result<serde_json::Map<String, Value>, Failure> {
+	serde_json::from_slice(body).map_err(|e| Failure::bad_request(format!("invalid request body: {}", e)))
+}
+
+/// Set all the fields in `fields` on an object.
+fn merge(object: &mut Value, fields: serde_json::Map<String, Value>) {
+	if let Value::Object(object) = object {
+		object.extend(fields);
+	}
+}
+
+/// Check if a request has basic authentication with a non-empty user name.
+///
+/// The Paymo API uses the API token as user name.
+fn has_basic_auth(request: &Request<Body>) -> bool {
+	request.headers()
+		.get(hyper::header::AUTHORIZATION)
+		.and_then(|x| x.to_str().ok())
+		.and_then(|x| x.strip_prefix("Basic "))
+		// The base64 encoding of ":" means the user name and password are empty.
+		.map(|x| !x.is_empty() && x != "Og==")
+		.unwrap_or(false)
+}
+
+fn json_response(status: StatusCode, body: &Value) -> Response<Body> {
+	Response::builder()
+		.status(status)
+		.header(hyper::header::CONTENT_TYPE, "application/json")
+		.body(Body::from(body.to_string()))
+		.unwrap()
+}
+
+fn error_response(failure: Failure) -> Response<Body> {
+	json_response(failure.status, &json!({ "message": failure.message }))
+}
+
+/// Get the current time as formatted by the Paymo API.
+fn now() -> String {
+	chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
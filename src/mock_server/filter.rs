@@ -0,0 +1,276 @@
+//! Evaluation of the `where` filter expressions of the Paymo API.
+//!
+//! Only the subset of the syntax produced by the API client is supported.
+
+use serde_json::Value;
+
+/// A parsed filter expression.
+#[derive(Debug)]
+pub enum Filter {
+	/// All conditions must match.
+	All(Vec<Filter>),
+
+	/// At least one condition must match.
+	Any(Vec<Filter>),
+
+	/// Compare a field with a value.
+	Test {
+		key: String,
+		operator: Operator,
+		value: Value,
+	},
+}
+
+/// A comparison operator.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Operator {
+	Equal,
+	NotEqual,
+	GreaterEqual,
+	LessEqual,
+	Like,
+	NotLike,
+	In,
+	NotIn,
+}
+
+impl Filter {
+	/// Parse a filter expression.
+	pub fn parse(input: &str) -> Result<Self, String> {
+		let input = input.trim();
+		if input.is_empty() {
+			return Ok(Self::All(Vec::new()));
+		}
+
+		let conditions = split_top_level(input, " and ");
+		if conditions.len() > 1 {
+			return Ok(Self::All(conditions.into_iter().map(Self::parse).collect::<Result<_, _>>()?));
+		}
+
+		if let Some(inner) = strip_group(input) {
+			let conditions = split_top_level(inner, " or ");
+			return Ok(Self::Any(conditions.into_iter().map(Self::parse).collect::<Result<_, _>>()?));
+		}
+
+		// Parse the key first, so operators inside quoted values are not mistaken for the operator.
+		let key_end = input.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.')).unwrap_or(input.len());
+		let (key, rest) = input.split_at(key_end);
+		let operators = [
+			(" not in ", Operator::NotIn),
+			(" in ", Operator::In),
+			(" not like ", Operator::NotLike),
+			(" like ", Operator::Like),
+			(">=", Operator::GreaterEqual),
+			("<=", Operator::LessEqual),
+			("!=", Operator::NotEqual),
+			("=", Operator::Equal),
+		];
+		for (token, operator) in operators {
+			if let Some(value) = rest.strip_prefix(token).filter(|_| !key.is_empty()) {
+				return Ok(Self::Test {
+					key: key.to_owned(),
+					operator,
+					value: parse_value(value.trim())?,
+				});
+			}
+		}
+		Err(format!("unsupported filter condition: {}", input))
+	}
+
+	/// Check if an object matches the filter.
+	///
+	/// The `lookup` function gets the value of a field of the object.
+	/// The special `time_interval in (start, end)` condition matches timestamps from `start` up to but not including `end`.
+	pub fn matches(&self, lookup: &dyn Fn(&str) -> Value) -> bool {
+		match self {
+			Self::All(filters) => filters.iter().all(|x| x.matches(lookup)),
+			Self::Any(filters) => filters.iter().any(|x| x.matches(lookup)),
+			Self::Test { key, operator: Operator::In, value } if key == "time_interval" => {
+				let field = lookup(key);
+				match value.as_array().map(Vec::as_slice) {
+					Some([start, end]) => {
+						compare(&field, start).map(|x| x.is_ge()).unwrap_or(false)
+							&& compare(&field, end).map(|x| x.is_lt()).unwrap_or(false)
+					},
+					_ => false,
+				}
+			},
+			Self::Test { key, operator, value } => test(&lookup(key), *operator, value),
+		}
+	}
+}
+
+/// Compare a field value with a filter value.
+fn test(field: &Value, operator: Operator, value: &Value) -> bool {
+	match operator {
+		Operator::Equal => equal(field, value),
+		Operator::NotEqual => !equal(field, value),
+		Operator::GreaterEqual => compare(field, value).map(|x| x.is_ge()).unwrap_or(false),
+		Operator::LessEqual => compare(field, value).map(|x| x.is_le()).unwrap_or(false),
+		Operator::Like => like(field, value),
+		Operator::NotLike => !like(field, value),
+		Operator::In => value.as_array().map(|x| x.iter().any(|x| equal(field, x))).unwrap_or(false),
+		Operator::NotIn => !value.as_array().map(|x| x.iter().any(|x| equal(field, x))).unwrap_or(false),
+	}
+}
+
+/// Check if a field is equal to a value, comparing numbers and booleans with their string form.
+fn equal(field: &Value, value: &Value) -> bool {
+	field == value || (!field.is_null() && as_string(field) == as_string(value))
+}
+
+/// Compare a field with a value.
+///
+/// Strings are compared lexicographically, which works for the ISO 8601 dates and timestamps used by the API.
+pub fn compare(field: &Value, value: &Value) -> Option<std::cmp::Ordering> {
+	match (field, value) {
+		(Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+		(Value::Null, _) | (_, Value::Null) => None,
+		(a, b) => Some(as_string(a).cmp(&as_string(b))),
+	}
+}
+
+/// Check if a field matches a pattern with `%` as wildcard, ignoring case.
+fn like(field: &Value, pattern: &Value) -> bool {
+	let field = as_string(field).to_lowercase();
+	let pattern = as_string(pattern).to_lowercase();
+	let parts: Vec<_> = pattern.split('%').collect();
+	let (first, rest) = parts.split_first().unwrap();
+	let mut remaining = match field.strip_prefix(first) {
+		Some(x) => x,
+		None => return false,
+	};
+	for (i, part) in rest.iter().enumerate() {
+		if i == rest.len() - 1 {
+			return remaining.ends_with(part);
+		}
+		match remaining.find(part) {
+			Some(index) => remaining = &remaining[index + part.len()..],
+			None => return false,
+		}
+	}
+	remaining.is_empty()
+}
+
+/// Get a value as string, without quotes for strings.
+fn as_string(value: &Value) -> String {
+	match value {
+		Value::String(x) => x.clone(),
+		x => x.to_string(),
+	}
+}
+
+/// Parse a value in a filter expression: a number, boolean, quoted string or parenthesized list.
+fn parse_value(input: &str) -> Result<Value, String> {
+	if let Some(inner) = strip_group(input) {
+		let values = split_top_level(inner, ",");
+		return Ok(Value::Array(values.into_iter().map(|x| parse_value(x.trim())).collect::<Result<_, _>>()?));
+	}
+	if let Some(quoted) = input.strip_prefix('"').and_then(|x| x.strip_suffix('"')) {
+		let mut result = String::with_capacity(quoted.len());
+		let mut chars = quoted.chars();
+		while let Some(c) = chars.next() {
+			match c {
+				'\\' => result.extend(chars.next()),
+				c => result.push(c),
+			}
+		}
+		return Ok(Value::String(result));
+	}
+	match input {
+		"true" => Ok(Value::Bool(true)),
+		"false" => Ok(Value::Bool(false)),
+		x => x.parse::<u64>()
+			.map(Value::from)
+			.map_err(|_| format!("unsupported filter value: {}", x)),
+	}
+}
+
+/// Get the contents of a parenthesized group, if the whole input is one group.
+fn strip_group(input: &str) -> Option<&str> {
+	let inner = input.strip_prefix('(')?.strip_suffix(')')?;
+
+	// The opening parenthesis must not be closed before the end, like in `(a) or (b)`.
+	let mut depth = 0;
+	let mut quoted = false;
+	let mut escaped = false;
+	for c in inner.chars() {
+		if escaped {
+			escaped = false;
+			continue;
+		}
+		match c {
+			'\\' if quoted => escaped = true,
+			'"' => quoted = !quoted,
+			'(' if !quoted => depth += 1,
+			')' if !quoted && depth == 0 => return None,
+			')' if !quoted => depth -= 1,
+			_ => (),
+		}
+	}
+	Some(inner)
+}
+
+/// Split a string on a separator, but not inside quotes or parentheses.
+fn split_top_level<'a>(input: &'a str, separator: &str) -> Vec<&'a str> {
+	let mut parts = Vec::new();
+	let mut depth = 0;
+	let mut quoted = false;
+	let mut escaped = false;
+	let mut start = 0;
+	for (i, c) in input.char_indices() {
+		if escaped {
+			escaped = false;
+			continue;
+		}
+		match c {
+			'\\' if quoted => escaped = true,
+			'"' => quoted = !quoted,
+			'(' if !quoted => depth += 1,
+			')' if !quoted => depth -= 1,
+			_ => (),
+		}
+		if !quoted && depth == 0 && i >= start && input[i..].starts_with(separator) {
+			parts.push(&input[start..i]);
+			start = i + separator.len();
+		}
+	}
+	parts.push(&input[start..]);
+	parts
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn parse_operator_after_key() {
+		let filter = Filter::parse(r#"description like "sign in (draft)" and task_id not in (1, 2)"#).unwrap();
+		let lookup = |key: &str| match key {
+			"description" => json!("Sign in (draft)"),
+			"task_id" => json!(3),
+			_ => Value::Null,
+		};
+		assert!(filter.matches(&lookup));
+	}
+
+	#[test]
+	fn parse_quoted_operators() {
+		match Filter::parse(r#"description="a in b = c""#).unwrap() {
+			Filter::Test { key, operator, value } => {
+				assert_eq!(key, "description");
+				assert_eq!(operator, Operator::Equal);
+				assert_eq!(value, json!("a in b = c"));
+			},
+			x => panic!("unexpected filter: {:?}", x),
+		}
+	}
+
+	#[test]
+	fn parse_escaped_quotes() {
+		let filter = Filter::parse(r#"description like "say \"hi\" in %""#).unwrap();
+		assert!(filter.matches(&|_: &str| json!(r#"say "hi" in public"#)));
+		assert!(!filter.matches(&|_: &str| json!("say hi in public")));
+	}
+}
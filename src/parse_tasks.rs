@@ -1,17 +1,19 @@
-use std::collections::BTreeMap;
+//! The old task ID file format, with one `tag = ID` line per task.
+//!
+//! Deprecated in favour of `[[Task]]` sections in the configuration file.
+
+use crate::config::TaskConfig;
 use std::path::Path;
 
-pub fn read_task_ids(path: impl AsRef<Path>) -> Result<BTreeMap<String, u64>, String> {
+pub fn read_task_ids(path: impl AsRef<Path>) -> Result<Vec<TaskConfig>, String> {
 	let path = path.as_ref();
 	let data = std::fs::read_to_string(path)
 		.map_err(|e| format!("{}", e))?;
 	parse_task_ids(&data)
 }
 
-pub fn parse_task_ids(data: &str) -> Result<BTreeMap<String, u64>, String> {
-	use std::collections::btree_map::Entry;
-
-	let mut result = BTreeMap::new();
+pub fn parse_task_ids(data: &str) -> Result<Vec<TaskConfig>, String> {
+	let mut result = Vec::new();
 
 	for (i, line) in data.lines().enumerate() {
 		let line = line.trim();
@@ -19,7 +21,7 @@ pub fn parse_task_ids(data: &str) -> Result<BTreeMap<String, u64>, String> {
 			continue;
 		}
 
-		let (tag, id) = partition(line, '=')
+		let (tag, id) = line.split_once('=')
 			.ok_or_else(|| format!("invalid syntax on line {}: expected \"tag = ID\"", i))?;
 
 		let tag = tag.trim();
@@ -28,26 +30,16 @@ pub fn parse_task_ids(data: &str) -> Result<BTreeMap<String, u64>, String> {
 		let id : u64 = id.parse()
 			.map_err(|_| format!("invalid task ID on line {}: expected unsigned number, got {}", i, id))?;
 
-		match result.entry(tag.to_string()) {
-			Entry::Vacant(x) => {
-				x.insert(id);
-			},
-			Entry::Occupied(_) => {
-				return Err(format!("duplicate tag on line {}: {}", i, tag));
-			},
+		if result.iter().any(|x: &TaskConfig| x.name.as_deref() == Some(tag)) {
+			return Err(format!("duplicate tag on line {}: {}", i, tag));
 		}
+
+		result.push(TaskConfig {
+			name: Some(tag.to_owned()),
+			tags: Vec::new(),
+			id,
+		});
 	}
 
 	Ok(result)
 }
-
-fn partition(input: &str, split: char) -> Option<(&str, &str)> {
-	let mut parts = input.splitn(2, split);
-	let first = parts.next().unwrap();
-	let second = parts.next();
-
-	match second {
-		None => None,
-		Some(second) => Some((first, second)),
-	}
-}
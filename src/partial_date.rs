@@ -1,3 +1,4 @@
+use chrono::Datelike;
 use uurlog::gregorian;
 use gregorian::{Date, Year, Month, YearMonth, InvalidDate};
 use std::ops::Range;
@@ -7,6 +8,9 @@ pub enum PartialDate {
 	Year(Year),
 	YearMonth(YearMonth),
 	YearMonthDay(Date),
+
+	/// An ISO week, represented by its monday.
+	YearWeek(Date),
 }
 
 impl PartialDate {
@@ -24,14 +28,94 @@ impl PartialDate {
 				start: x,
 				end: x.next(),
 			},
+			Self::YearWeek(x) => Range {
+				start: x,
+				end: (0..7).fold(x, |date, _| date.next()),
+			},
+		}
+	}
+
+	/// Split a year in its months.
+	///
+	/// Other periods are returned as they are.
+	pub fn months(self) -> Vec<Self> {
+		match self {
+			Self::Year(x) => {
+				let first = YearMonth::new(x, Month::new(1).unwrap());
+				std::iter::successors(Some(first), |x| Some(x.next()))
+					.take(12)
+					.map(Self::YearMonth)
+					.collect()
+			},
+			x => vec![x],
+		}
+	}
+}
+
+/// The names of periods relative to the current date.
+const RELATIVE_NAMES: &[&str] = &["today", "yesterday", "this-week", "last-week", "this-month", "last-month", "this-year", "last-year"];
+
+impl PartialDate {
+	/// Get a period relative to a date.
+	///
+	/// Supported names are `today`, `yesterday`, `this-week`, `last-week`, `this-month`, `last-month`, `this-year` and `last-year`.
+	pub fn relative(name: &str, today: chrono::NaiveDate) -> Option<Self> {
+		use crate::timezone::from_naive_date;
+		let monday = today - chrono::Duration::days(today.weekday().num_days_from_monday().into());
+		let last_month = today.with_day(1)? - chrono::Duration::days(1);
+		let year_month = |date: chrono::NaiveDate| Some(YearMonth::new(date.year() as i16, Month::new(date.month() as u8).ok()?));
+		match name {
+			"today" => Some(Self::YearMonthDay(from_naive_date(today).ok()?)),
+			"yesterday" => Some(Self::YearMonthDay(from_naive_date(today.pred_opt()?).ok()?)),
+			"this-week" => Some(Self::YearWeek(from_naive_date(monday).ok()?)),
+			"last-week" => Some(Self::YearWeek(from_naive_date(monday - chrono::Duration::weeks(1)).ok()?)),
+			"this-month" => year_month(today).map(Self::YearMonth),
+			"last-month" => year_month(last_month).map(Self::YearMonth),
+			"this-year" => Some(Self::Year((today.year() as i16).into())),
+			"last-year" => Some(Self::Year((today.year() as i16 - 1).into())),
+			_ => None,
+		}
+	}
+}
+
+impl std::fmt::Display for PartialDate {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::Year(x) => write!(f, "{}", x.to_number()),
+			Self::YearMonth(x) => write!(f, "{}-{:02}", x.year().to_number(), x.month().to_number()),
+			Self::YearMonthDay(x) => write!(f, "{}", x),
+			Self::YearWeek(x) => {
+				let monday = chrono::NaiveDate::from_ymd_opt(x.year().to_number().into(), x.month().to_number().into(), x.day().into());
+				match monday {
+					Some(monday) => write!(f, "{}-W{:02}", monday.iso_week().year(), monday.iso_week().week()),
+					None => write!(f, "week of {}", x),
+				}
+			},
 		}
 	}
 }
 
-impl std::str::FromStr for PartialDate {
-	type Err = ParsePartialDateError;
+impl PartialDate {
+	/// Parse a partial date, resolving relative periods like `this-week` from the given date.
+	///
+	/// The date should be today in the timezone the period is used in.
+	pub fn parse_relative_to(data: &str, today: chrono::NaiveDate) -> Result<Self, ParsePartialDateError> {
+		if let Some(period) = Self::relative(data, today) {
+			return Ok(period);
+		}
+		Self::parse_absolute(data)
+	}
 
-	fn from_str(data: &str) -> Result<Self, Self::Err> {
+	/// Check that a partial date can be parsed, without resolving relative periods.
+	pub fn validate(data: &str) -> Result<(), ParsePartialDateError> {
+		if RELATIVE_NAMES.contains(&data) {
+			return Ok(());
+		}
+		Self::parse_absolute(data).map(drop)
+	}
+
+	/// Parse a partial date that is not relative to the current date.
+	fn parse_absolute(data: &str) -> Result<Self, ParsePartialDateError> {
 		let mut fields = data.splitn(3, '-');
 		let year = fields.next().unwrap();
 		let month = fields.next();
@@ -39,7 +123,16 @@ impl std::str::FromStr for PartialDate {
 
 		let year: i16 = year.parse().map_err(|_| InvalidPartialDateSyntax::new())?;
 
-		if let Some(month) = month {
+		if let Some(week) = month.and_then(|x| x.strip_prefix('W')) {
+			if day.is_some() {
+				return Err(InvalidPartialDateSyntax::new().into());
+			}
+			let week: u32 = week.parse().map_err(|_| InvalidPartialDateSyntax::new())?;
+			let monday = chrono::NaiveDate::from_isoywd_opt(year.into(), week, chrono::Weekday::Mon)
+				.ok_or_else(InvalidPartialDateSyntax::new)?;
+			let month = Month::new(monday.month() as u8)?;
+			Ok(Self::YearWeek(Date::new(monday.year() as i16, month, monday.day() as u8)?))
+		} else if let Some(month) = month {
 			let month: u8 = month.parse().map_err(|_| InvalidPartialDateSyntax::new())?;
 			let month = Month::new(month)?;
 			if let Some(day) = day {
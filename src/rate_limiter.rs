@@ -0,0 +1,26 @@
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Limit the rate at which operations are started.
+pub struct RateLimiter {
+	interval: Duration,
+	next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+	/// Create a rate limiter that allows one operation per `interval`.
+	pub fn new(interval: Duration) -> Self {
+		Self {
+			interval,
+			next: Mutex::new(Instant::now()),
+		}
+	}
+
+	/// Wait until the next operation is allowed to start.
+	pub async fn wait(&self) {
+		let mut next = self.next.lock().await;
+		tokio::time::sleep_until(*next).await;
+		*next = Instant::now() + self.interval;
+	}
+}
@@ -0,0 +1,42 @@
+//! Redaction of secrets and personal data from log output.
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Replacement text for redacted values.
+const REDACTED: &str = "[redacted]";
+
+/// Redact entry descriptions from log output.
+static REDACT_DESCRIPTIONS: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable the redaction of entry descriptions.
+pub fn set_redact_descriptions(redact: bool) {
+	REDACT_DESCRIPTIONS.store(redact, Ordering::Relaxed);
+}
+
+/// Get an entry description for log output.
+///
+/// Returns a placeholder if descriptions are redacted.
+pub fn description(description: &str) -> &str {
+	if REDACT_DESCRIPTIONS.load(Ordering::Relaxed) && !description.is_empty() {
+		REDACTED
+	} else {
+		description
+	}
+}
+
+/// Remove credentials from a URL for log output.
+pub fn url(url: &str) -> Cow<'_, str> {
+	let mut parsed = match reqwest::Url::parse(url) {
+		Ok(x) => x,
+		Err(_) => return Cow::Borrowed(url),
+	};
+	if parsed.username().is_empty() && parsed.password().is_none() {
+		return Cow::Borrowed(url);
+	}
+	// These only fail for URLs that can not have credentials in the first place.
+	let _ = parsed.set_username("redacted");
+	let _ = parsed.set_password(None);
+	Cow::Owned(parsed.to_string())
+}
+
@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+
+use crate::locale::Locale;
+use crate::types;
+use crate::workspace::Workspace;
+
+/// Print the billable and non-billable hours per client.
+///
+/// An entry is billable if its task is billable.
+pub fn print_billable_report(workspace: &Workspace, entries: &[types::TimeEntry]) {
+	#[derive(Default)]
+	struct Totals {
+		billable: u64,
+		non_billable: u64,
+	}
+
+	let mut per_client = BTreeMap::<String, Totals>::new();
+	let mut total = Totals::default();
+	for entry in entries {
+		let client = match workspace.client_of_task(entry.task_id) {
+			Some(client) => format!("{} ({})", client.name, client.id),
+			None => String::from("unknown client"),
+		};
+		let billable = workspace.tasks.get(&entry.task_id).map(|x| x.billable).unwrap_or(false);
+		let duration = u64::from(entry.total_seconds());
+
+		let client_totals = per_client.entry(client).or_default();
+		if billable {
+			client_totals.billable += duration;
+			total.billable += duration;
+		} else {
+			client_totals.non_billable += duration;
+			total.non_billable += duration;
+		}
+	}
+
+	for (client, totals) in &per_client {
+		println!("{}: billable {}, non-billable {}, total {}",
+			client,
+			format_seconds(totals.billable),
+			format_seconds(totals.non_billable),
+			format_seconds(totals.billable + totals.non_billable),
+		);
+	}
+	println!("Total: billable {}, non-billable {}, total {}",
+		format_seconds(total.billable),
+		format_seconds(total.non_billable),
+		format_seconds(total.billable + total.non_billable),
+	);
+}
+
+/// Print the estimated earnings per client, from the hours on billable tasks and their price per hour.
+///
+/// Hours on non-billable tasks are not counted.
+/// Returns the number of entries on billable tasks without a price per hour, which are not counted either.
+pub fn print_earnings(workspace: &Workspace, entries: &[types::TimeEntry], user: &types::User, company: &types::Company, locale: &Locale) -> usize {
+	let mut per_task = BTreeMap::<u64, (usize, u64)>::new();
+	let mut non_billable = 0;
+	for entry in entries {
+		let duration = u64::from(entry.total_seconds());
+		if workspace.tasks.get(&entry.task_id).map(|x| x.billable).unwrap_or(false) {
+			let (count, seconds) = per_task.entry(entry.task_id).or_default();
+			*count += 1;
+			*seconds += duration;
+		} else {
+			non_billable += duration;
+		}
+	}
+
+	let mut per_client = BTreeMap::<String, Vec<(u64, usize, u64)>>::new();
+	for (&task_id, &(count, seconds)) in &per_task {
+		let client = match workspace.client_of_task(task_id) {
+			Some(client) => format!("{} ({})", client.name, client.id),
+			None => String::from("unknown client"),
+		};
+		per_client.entry(client).or_default().push((task_id, count, seconds));
+	}
+
+	let mut total = 0.0;
+	let mut total_seconds = 0;
+	let mut unrated = 0;
+	for (client, tasks) in &per_client {
+		let mut client_total = 0.0;
+		let mut lines = Vec::with_capacity(tasks.len());
+		for &(task_id, count, seconds) in tasks {
+			let task = workspace.tasks.get(&task_id).map(|x| x.name.as_str()).unwrap_or("unknown task");
+			let project = workspace.project_of_task(task_id).map(|x| x.name.as_str()).unwrap_or("unknown project");
+			let hours = seconds as f64 / 3600.0;
+			match workspace.hourly_rate(task_id, user, company) {
+				Some(rate) => {
+					client_total += hours * rate;
+					total_seconds += seconds;
+					lines.push(format!("  {} / {}: {} × {} = {}", project, task, format_seconds(seconds), locale.number(rate, 2), locale.number(hours * rate, 2)));
+				},
+				None => {
+					unrated += count;
+					lines.push(format!("  {} / {}: {}, no price per hour ({} entries not counted)", project, task, format_seconds(seconds), count));
+				},
+			}
+		}
+		total += client_total;
+		println!("{}: {}", client, locale.number(client_total, 2));
+		for line in lines {
+			println!("{}", line);
+		}
+	}
+
+	let currency = company.default_currency.as_deref().map(|x| format!(" {}", x)).unwrap_or_default();
+	println!("Total: {}{} for {} billable hours, {} non-billable hours not counted",
+		locale.number(total, 2),
+		currency,
+		format_seconds(total_seconds),
+		format_seconds(non_billable),
+	);
+	unrated
+}
+
+/// Print time entries with their date, duration, task and description.
+pub fn print_entries(entries: &[types::TimeEntry], workspace: &Workspace, timezone: chrono_tz::Tz, locale: &Locale) {
+	for entry in entries {
+		let date = entry.local_date(timezone).map(|x| locale.date(x)).unwrap_or_else(|| String::from("????"));
+		let task = match workspace.tasks.get(&entry.task_id) {
+			Some(task) => format!("{} ({})", task.name, task.id),
+			None => format!("unknown task ({})", entry.task_id),
+		};
+		let project = workspace.project_of_task(entry.task_id).map(|x| x.name.as_str()).unwrap_or("unknown project");
+		println!("{} {:>6}  {} / {}: {}  [{}]",
+			date,
+			format_seconds(entry.total_seconds().into()),
+			project,
+			task,
+			entry.description,
+			entry.id,
+		);
+	}
+}
+
+/// Logged time per day and per task, in seconds.
+#[derive(Debug, Default)]
+pub struct Totals {
+	pub per_day: BTreeMap<chrono::NaiveDate, u64>,
+	pub per_task: BTreeMap<Option<u64>, u64>,
+}
+
+impl Totals {
+	/// Add logged time for a date and task.
+	///
+	/// A task ID of [`None`] is used for unmapped entries.
+	pub fn add(&mut self, date: chrono::NaiveDate, task_id: Option<u64>, seconds: u64) {
+		*self.per_day.entry(date).or_default() += seconds;
+		*self.per_task.entry(task_id).or_default() += seconds;
+	}
+}
+
+/// Print local and server totals side by side, highlighting differences.
+///
+/// Returns the number of days and tasks with differences.
+pub fn print_reconciliation(local: &Totals, server: &Totals, workspace: &Workspace, locale: &Locale) -> usize {
+	let mut differences = 0;
+
+	println!("{:<40} {:>8} {:>8}", "Date", "Local", "Server");
+	let dates: std::collections::BTreeSet<_> = local.per_day.keys().chain(server.per_day.keys()).collect();
+	for date in dates {
+		let local = local.per_day.get(date).copied().unwrap_or(0);
+		let server = server.per_day.get(date).copied().unwrap_or(0);
+		differences += print_reconciliation_line(&locale.date(*date), local, server);
+	}
+
+	println!();
+	println!("{:<40} {:>8} {:>8}", "Task", "Local", "Server");
+	let task_ids: std::collections::BTreeSet<_> = local.per_task.keys().chain(server.per_task.keys()).collect();
+	for task_id in task_ids {
+		let name = match task_id {
+			None => String::from("unmapped"),
+			Some(id) => match workspace.tasks.get(id) {
+				Some(task) => format!("{} ({})", task.name, task.id),
+				None => format!("unknown task ({})", id),
+			},
+		};
+		let local = local.per_task.get(task_id).copied().unwrap_or(0);
+		let server = server.per_task.get(task_id).copied().unwrap_or(0);
+		differences += print_reconciliation_line(&name, local, server);
+	}
+
+	differences
+}
+
+/// Print a single line of a reconciliation.
+///
+/// Returns 1 if the local and server time differ, 0 otherwise.
+fn print_reconciliation_line(label: &str, local: u64, server: u64) -> usize {
+	if local == server {
+		println!("{:<40} {:>8} {:>8}", label, format_seconds(local), format_seconds(server));
+		0
+	} else {
+		let (sign, difference) = if server > local { ('+', server - local) } else { ('-', local - server) };
+		println!("{:<40} {:>8} {:>8}  <-- {}{}", label, format_seconds(local), format_seconds(server), sign, format_seconds(difference));
+		1
+	}
+}
+
+/// Number of entries and total logged time in seconds.
+#[derive(Debug, Default)]
+pub struct Stats {
+	pub entries: usize,
+	pub seconds: u64,
+}
+
+impl Stats {
+	/// Add an entry with the given logged time.
+	pub fn add(&mut self, seconds: u64) {
+		self.entries += 1;
+		self.seconds += seconds;
+	}
+}
+
+/// Print statistics per group of entries.
+///
+/// The average per day is computed over all days with logged time, and the share is relative to `total`.
+pub fn print_stats(label: &str, groups: &BTreeMap<String, Stats>, total: &Stats, days: usize, locale: &Locale) {
+	println!("{:<40} {:>8} {:>8} {:>8} {:>6}", label, "Entries", "Total", "Per day", "Share");
+	for (name, stats) in groups {
+		let per_day = if days == 0 { 0 } else { stats.seconds / days as u64 };
+		let share = if total.seconds == 0 { 0.0 } else { 100.0 * stats.seconds as f64 / total.seconds as f64 };
+		println!("{:<40} {:>8} {:>8} {:>8} {:>5}%", name, stats.entries, format_seconds(stats.seconds), format_seconds(per_day), locale.number(share, 1));
+	}
+	let per_day = if days == 0 { 0 } else { total.seconds / days as u64 };
+	println!("{:<40} {:>8} {:>8} {:>8}", "Total", total.entries, format_seconds(total.seconds), format_seconds(per_day));
+}
+
+/// Print a table with the logged time per task and per day, with row and column totals.
+///
+/// The logged time is given in seconds, indexed by task ID and date.
+pub fn print_timesheet(period: &std::ops::Range<uurlog::Date>, per_task_day: &BTreeMap<(u64, chrono::NaiveDate), u64>, workspace: &Workspace) {
+	let dates: Vec<_> = crate::calendar::dates(period).collect();
+	let task_ids: std::collections::BTreeSet<_> = per_task_day.keys().map(|&(task_id, _)| task_id).collect();
+
+	print!("{:<40}", "Task");
+	for date in &dates {
+		print!(" {:>6}", date.format("%a %d").to_string());
+	}
+	println!(" {:>8}", "Total");
+
+	let mut day_totals = vec![0; dates.len()];
+	for task_id in task_ids {
+		let name = match workspace.tasks.get(&task_id) {
+			Some(task) => format!("{} ({})", task.name, task.id),
+			None => format!("unknown task ({})", task_id),
+		};
+		print!("{:<40}", name);
+		let mut task_total = 0;
+		for (date, day_total) in dates.iter().zip(&mut day_totals) {
+			let seconds = per_task_day.get(&(task_id, *date)).copied().unwrap_or(0);
+			task_total += seconds;
+			*day_total += seconds;
+			if seconds == 0 {
+				print!(" {:>6}", "");
+			} else {
+				print!(" {:>6}", format_seconds(seconds));
+			}
+		}
+		println!(" {:>8}", format_seconds(task_total));
+	}
+
+	print!("{:<40}", "Total");
+	for day_total in &day_totals {
+		print!(" {:>6}", format_seconds(*day_total));
+	}
+	println!(" {:>8}", format_seconds(day_totals.iter().sum()));
+}
+
+/// Print a heatmap of the logged time per day, with one column per week.
+///
+/// Weeks start on `week_start`.
+/// Dates outside of the period are left blank.
+pub fn print_heatmap(period: &std::ops::Range<uurlog::Date>, per_day: &BTreeMap<chrono::NaiveDate, u64>, week_start: chrono::Weekday) {
+	use chrono::Datelike;
+
+	let start = crate::timezone::to_naive_date(period.start);
+	let end = crate::timezone::to_naive_date(period.end);
+	let days_since_week_start = (7 + start.weekday().num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+	let first_day = start - chrono::Duration::days(days_since_week_start.into());
+	let weeks = ((end - first_day).num_days() + 6) / 7;
+
+	// Label the columns with the month that starts in that week, if there is room for it.
+	let mut header = String::from("    ");
+	for week in 0..weeks {
+		let week_first = first_day + chrono::Duration::weeks(week);
+		let week_last = week_first + chrono::Duration::days(6);
+		let column = 4 + 2 * week as usize;
+		let month_starts = week_first.day() == 1 || week_first.month() != week_last.month();
+		if (week == 0 || month_starts) && header.chars().count() <= column {
+			while header.chars().count() < column {
+				header.push(' ');
+			}
+			header.push_str(&week_last.format("%b").to_string());
+		}
+	}
+	println!("{}", header.trim_end());
+
+	for weekday in 0..7 {
+		let mut line = (first_day + chrono::Duration::days(weekday)).format("%a ").to_string();
+		for week in 0..weeks {
+			let date = first_day + chrono::Duration::weeks(week) + chrono::Duration::days(weekday);
+			if date < start || date >= end {
+				line.push_str("  ");
+			} else {
+				line.push(heatmap_cell(per_day.get(&date).copied().unwrap_or(0)));
+				line.push(' ');
+			}
+		}
+		println!("{}", line.trim_end());
+	}
+
+	println!();
+	println!("· none, ░ up to 2 hours, ▒ up to 4 hours, ▓ up to 6 hours, █ more than 6 hours");
+}
+
+/// Get the heatmap character for an amount of logged time in seconds.
+fn heatmap_cell(seconds: u64) -> char {
+	match seconds {
+		0 => '·',
+		x if x <= 2 * 3600 => '░',
+		x if x <= 4 * 3600 => '▒',
+		x if x <= 6 * 3600 => '▓',
+		_ => '█',
+	}
+}
+
+/// Format a number of seconds as hours and minutes.
+pub fn format_seconds(seconds: u64) -> String {
+	uurlog::Hours::from_minutes((seconds / 60) as u32).to_string()
+}
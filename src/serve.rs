@@ -0,0 +1,523 @@
+//! A local API to plan and apply synchronizations, for front-ends and editor plugins.
+//!
+//! The API is served over HTTP, or as JSON-RPC over standard input and output.
+//! The server keeps the configuration and API clients of the configured profiles,
+//! so front-ends do not have to start a new process for every request.
+//! Anyone who can connect to the HTTP server can change entries on Paymo, so it only listens on a local address by default.
+//! Every HTTP request needs the random token printed at startup as `Authorization: Bearer TOKEN` header,
+//! and requests with a `Host` or `Origin` header that is not local are rejected to block cross-site requests and DNS rebinding.
+//!
+//! HTTP endpoints:
+//! * `GET /status`: the available profiles and the default period.
+//! * `GET /plan?profile=NAME[&period=PERIOD]`: the changes a synchronization would make.
+//! * `POST /apply?profile=NAME[&period=PERIOD]`: plan the synchronization again and apply it.
+//!
+//! JSON-RPC methods, with one request or response per line:
+//! * `status`, `plan` and `apply`: the same as the HTTP endpoints, with the query parameters as named parameters.
+//! * `resolve-tag`: the task ID for a list of `tags`, optionally with the overrides for a `file`.
+//! * `validate-entries`: the unmapped and overlapping entries in a `file`.
+//!
+//! The profile may be omitted if only one profile is configured.
+
+use crate::api_client::ApiClient;
+use crate::config::Config;
+use crate::import;
+use crate::partial_date::PartialDate;
+use crate::{Entry, Error, SyncOptions, SyncPlan};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::io::{BufRead, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A profile that can be synchronized through the server.
+pub struct Profile {
+	pub name: String,
+	pub config: Config,
+	pub api: ApiClient,
+}
+
+/// The state of the server.
+pub struct Server {
+	profiles: Vec<Profile>,
+	reader: import::Reader,
+	options: SyncOptions,
+
+	/// The period to synchronize if a request does not give one.
+	default_period: Option<String>,
+
+	/// The time of the recording when replaying API responses.
+	recorded_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+
+	/// Lock to plan and apply one synchronization at a time.
+	lock: tokio::sync::Mutex<()>,
+}
+
+/// An error response.
+struct Failure {
+	status: StatusCode,
+	message: String,
+
+	/// The method does not exist, as opposed to a missing profile or file.
+	method_not_found: bool,
+}
+
+impl Failure {
+	fn new(status: StatusCode, message: impl Into<String>) -> Self {
+		Self {
+			status,
+			message: message.into(),
+			method_not_found: false,
+		}
+	}
+
+	fn bad_request(message: impl Into<String>) -> Self {
+		Self::new(StatusCode::BAD_REQUEST, message)
+	}
+
+	fn method_not_found(method: &str) -> Self {
+		Self {
+			method_not_found: true,
+			..Self::new(StatusCode::NOT_FOUND, format!("unknown method {}", method))
+		}
+	}
+}
+
+impl Server {
+	pub fn new(
+		profiles: Vec<Profile>,
+		reader: import::Reader,
+		options: SyncOptions,
+		default_period: Option<String>,
+		recorded_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+	) -> Self {
+		Self {
+			profiles,
+			reader,
+			options,
+			default_period,
+			recorded_at,
+			lock: tokio::sync::Mutex::new(()),
+		}
+	}
+
+	async fn handle(&self, request: Request<Body>, access: &Access) -> Response<Body> {
+		let method = request.method().clone();
+		let path = request.uri().path().to_owned();
+		let query = request.uri().query().unwrap_or("").to_owned();
+		log::debug!("{} {}", method, path);
+
+		if let Err(failure) = access.check(&request) {
+			log::warn!("rejected {} {}: {}", method, path, failure.message);
+			return json_response(failure.status, &json!({ "message": failure.message }));
+		}
+
+		let result = match (&method, path.as_str()) {
+			(&Method::GET, "/status") | (&Method::GET, "/plan") | (&Method::POST, "/apply") => match parse_query(&query) {
+				Ok(params) => self.call(&path[1..], &params).await,
+				Err(failure) => Err(failure),
+			},
+			(_, "/status") | (_, "/plan") | (_, "/apply") => Err(Failure::new(StatusCode::METHOD_NOT_ALLOWED, format!("method {} not allowed for {}", method, path))),
+			_ => Err(Failure::new(StatusCode::NOT_FOUND, format!("no endpoint {}", path))),
+		};
+		match result {
+			Ok(body) => json_response(StatusCode::OK, &body),
+			Err(failure) => {
+				log::debug!("{} {}: {}", method, path, failure.message);
+				json_response(failure.status, &json!({ "message": failure.message }))
+			},
+		}
+	}
+
+	/// Handle a JSON-RPC request, and get the response.
+	async fn handle_rpc(&self, line: &str) -> Value {
+		let request: Value = match serde_json::from_str(line) {
+			Ok(request) => request,
+			Err(e) => return rpc_error(Value::Null, -32700, format!("failed to parse request: {}", e)),
+		};
+		let id = request.get("id").cloned().unwrap_or(Value::Null);
+		let method = match request.get("method").and_then(Value::as_str) {
+			Some(method) => method,
+			None => return rpc_error(id, -32600, "missing method"),
+		};
+		let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+		log::debug!("{}", method);
+
+		match self.call(method, &params).await {
+			Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+			Err(failure) => {
+				log::debug!("{}: {}", method, failure.message);
+				let code = match failure.status {
+					StatusCode::NOT_FOUND if failure.method_not_found => -32601,
+					StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND => -32602,
+					_ => -32000,
+				};
+				rpc_error(id, code, failure.message)
+			},
+		}
+	}
+
+	/// Call a method of the API with named parameters.
+	async fn call(&self, method: &str, params: &Value) -> Result<Value, Failure> {
+		match method {
+			"status" => Ok(self.status()),
+			"plan" => self.plan(params).await,
+			"apply" => self.apply(params).await,
+			"resolve-tag" => self.resolve_tag(params).await,
+			"validate-entries" => self.validate_entries(params).await,
+			_ => Err(Failure::method_not_found(method)),
+		}
+	}
+
+	fn status(&self) -> Value {
+		json!({
+			"profiles": self.profiles.iter().map(|x| x.name.as_str()).collect::<Vec<_>>(),
+			"default_period": self.default_period,
+			"dry_run": self.options.dry_run,
+		})
+	}
+
+	async fn plan(&self, params: &Value) -> Result<Value, Failure> {
+		let profile = self.profile(params)?;
+		let period = self.period(params)?;
+		let options = self.sync_options();
+		let _lock = self.lock.lock().await;
+		let plan = plan_profile(profile, &self.reader, &period, &options).await?;
+		Ok(plan_json(&plan, &profile.config))
+	}
+
+	async fn apply(&self, params: &Value) -> Result<Value, Failure> {
+		if self.options.dry_run {
+			return Err(Failure::new(StatusCode::CONFLICT, "the server is running with --dry-run"));
+		}
+		let profile = self.profile(params)?;
+		let period = self.period(params)?;
+		let options = self.sync_options();
+		let _lock = self.lock.lock().await;
+		let plan = plan_profile(profile, &self.reader, &period, &options).await?;
+		log::info!("Applying changes for profile {}", profile.name);
+
+		// Record each apply as a separate run in the journal, so that `--undo-last-run` only undoes the last one.
+		let label = chrono::Utc::now().format("%Y%m%dT%H%M%S").to_string();
+		let api = ApiClient {
+			journal: profile.api.journal.as_ref().map(|x| Arc::new(x.for_period(&label))),
+			..profile.api.clone()
+		};
+		crate::apply_sync(&api, &plan, &profile.config, &options).await
+			.map_err(failure)?;
+		Ok(plan_json(&plan, &profile.config))
+	}
+
+	/// Get the task ID for the tags of an entry.
+	async fn resolve_tag(&self, params: &Value) -> Result<Value, Failure> {
+		let profile = self.profile(params)?;
+		let tags: Vec<String> = match params.get("tags") {
+			Some(tags) => serde_json::from_value(tags.clone())
+				.map_err(|e| Failure::bad_request(format!("invalid parameter tags: {}", e)))?,
+			None => return Err(Failure::bad_request("missing parameter: tags")),
+		};
+		let task_ids = match string_param(params, "file")? {
+			Some(file) => crate::load_task_ids(&profile.api, &profile.config, Path::new(file), self.options.tag_normalization).await,
+			None => crate::build_task_ids(&profile.api, &profile.config, self.options.tag_normalization).await,
+		};
+		let task_ids = task_ids.map_err(failure)?;
+		Ok(match task_ids.resolve(&tags) {
+			Ok(task_id) => json!({ "task_id": task_id }),
+			Err(reason) => json!({ "task_id": null, "reason": reason }),
+		})
+	}
+
+	/// Check the entries of an hour log for problems that would prevent a synchronization.
+	async fn validate_entries(&self, params: &Value) -> Result<Value, Failure> {
+		let profile = self.profile(params)?;
+		let file = string_param(params, "file")?
+			.ok_or_else(|| Failure::bad_request("missing parameter: file"))?;
+		let file = Path::new(file);
+		let entries = self.reader.read(file)
+			.map_err(|e| Failure::new(StatusCode::UNPROCESSABLE_ENTITY, format!("failed to read {}: {}", file.display(), e)))?;
+		let task_ids = crate::load_task_ids(&profile.api, &profile.config, file, self.options.tag_normalization).await
+			.map_err(failure)?;
+
+		let unmapped: Vec<_> = entries.iter()
+			.filter_map(|entry| Some((entry, task_ids.resolve(&entry.tags).err()?)))
+			.map(|(entry, reason)| json!({ "entry": entry_json(entry), "reason": reason }))
+			.collect();
+		let overlaps: Vec<_> = crate::checks::find_overlaps(&entries).into_iter()
+			.map(|(a, b)| json!([entry_json(a), entry_json(b)]))
+			.collect();
+		Ok(json!({
+			"entries": entries.len(),
+			"unmapped": unmapped,
+			"overlaps": overlaps,
+		}))
+	}
+
+	/// Get the profile of a request.
+	fn profile(&self, params: &Value) -> Result<&Profile, Failure> {
+		match (string_param(params, "profile")?, self.profiles.as_slice()) {
+			(None, [profile]) => Ok(profile),
+			(None, _) => Err(Failure::bad_request("missing parameter: profile")),
+			(Some(name), profiles) => profiles.iter()
+				.find(|x| x.name == name)
+				.ok_or_else(|| Failure::new(StatusCode::NOT_FOUND, format!("no profile named {}", name))),
+		}
+	}
+
+	/// Get the period of a request, or the default period.
+	fn period(&self, params: &Value) -> Result<PartialDate, Failure> {
+		let period = string_param(params, "period")?
+			.or(self.default_period.as_deref())
+			.ok_or_else(|| Failure::bad_request("missing parameter: period"))?;
+		let today = match self.options.timezone {
+			Some(timezone) => self.now().with_timezone(&timezone).date_naive(),
+			None => self.now().date_naive(),
+		};
+		PartialDate::parse_relative_to(period, today)
+			.map_err(|e| Failure::bad_request(format!("invalid period {}: {}", period, e)))
+	}
+
+	/// Get the current time, or the time of the recording when replaying API responses.
+	fn now(&self) -> chrono::DateTime<chrono::FixedOffset> {
+		self.recorded_at.unwrap_or_else(|| chrono::Local::now().into())
+	}
+
+	/// Get the synchronization options for a request, with the current time.
+	fn sync_options(&self) -> SyncOptions {
+		SyncOptions {
+			now: self.now().into(),
+			..self.options.clone()
+		}
+	}
+}
+
+/// The checks that HTTP requests must pass before they are handled.
+struct Access {
+	/// The bearer token that requests must send in the `Authorization` header.
+	token: String,
+
+	/// Accept requests with a `Host` or `Origin` header that is not local.
+	allow_remote: bool,
+}
+
+impl Access {
+	/// Check the token and the `Host` and `Origin` headers of a request.
+	fn check(&self, request: &Request<Body>) -> Result<(), Failure> {
+		let header = |name| request.headers().get(name).map(|x| x.to_str().unwrap_or(""));
+		if !self.allow_remote {
+			if let Some(host) = header(hyper::header::HOST) {
+				if !is_local_host(host) {
+					return Err(Failure::new(StatusCode::FORBIDDEN, format!("host {} is not a local address", host)));
+				}
+			}
+			if let Some(origin) = header(hyper::header::ORIGIN) {
+				let local = reqwest::Url::parse(origin).ok()
+					.and_then(|x| x.host_str().map(is_local_host))
+					.unwrap_or(false);
+				if !local {
+					return Err(Failure::new(StatusCode::FORBIDDEN, format!("origin {} is not a local address", origin)));
+				}
+			}
+		}
+
+		let token = header(hyper::header::AUTHORIZATION).and_then(|x| x.strip_prefix("Bearer "));
+		match token {
+			Some(token) if constant_time_eq(token.as_bytes(), self.token.as_bytes()) => Ok(()),
+			_ => Err(Failure::new(StatusCode::UNAUTHORIZED, "missing or invalid bearer token")),
+		}
+	}
+}
+
+/// Check if the host of a `Host` header or URL is a local address, ignoring the port.
+fn is_local_host(host: &str) -> bool {
+	let host = match host.strip_prefix('[') {
+		Some(rest) => rest.split(']').next().unwrap_or(""),
+		None if host.matches(':').count() == 1 => host.split(':').next().unwrap_or(""),
+		None => host,
+	};
+	host.eq_ignore_ascii_case("localhost")
+		|| host.parse::<std::net::IpAddr>().map(|x| x.is_loopback()).unwrap_or(false)
+}
+
+/// Compare two byte strings in a time that only depends on their length.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Generate a random token of 128 bits, as hexadecimal string.
+///
+/// The hashers of the standard library are seeded with random keys from the operating system,
+/// which avoids a dependency on a random number generator.
+pub fn random_token() -> String {
+	use std::hash::{BuildHasher, Hasher};
+	(0..2u8)
+		.map(|i| {
+			let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+			hasher.write_u8(i);
+			format!("{:016x}", hasher.finish())
+		})
+		.collect()
+}
+
+/// Run the server until it fails.
+///
+/// Non-local addresses are refused, unless `allow_remote` is set.
+/// A new token is generated for each run and printed to standard output.
+pub async fn run(address: SocketAddr, server: Server, allow_remote: bool) -> Result<(), String> {
+	if !address.ip().is_loopback() {
+		if !allow_remote {
+			return Err(format!("refusing to listen on non-local address {}, use --serve-allow-remote to allow it", address));
+		}
+		log::warn!("listening on non-local address {}, anyone who can connect and knows the token can change entries on Paymo", address);
+	}
+
+	let server = Arc::new(server);
+	let access = Arc::new(Access {
+		token: random_token(),
+		allow_remote,
+	});
+	println!("Token: {}", access.token);
+	let make_service = {
+		let access = access.clone();
+		hyper::service::make_service_fn(move |_| {
+			let server = server.clone();
+			let access = access.clone();
+			async move {
+				Ok::<_, Infallible>(hyper::service::service_fn(move |request| {
+					let server = server.clone();
+					let access = access.clone();
+					async move { Ok::<_, Infallible>(server.handle(request, &access).await) }
+				}))
+			}
+		})
+	};
+
+	let server = hyper::Server::try_bind(&address)
+		.map_err(|e| format!("failed to listen on {}: {}", address, e))?
+		.serve(make_service);
+	log::info!("Listening on http://{}", server.local_addr());
+	server.await.map_err(|e| format!("server failed: {}", e))
+}
+
+/// Serve JSON-RPC requests from standard input until it is closed.
+///
+/// Each line of input is a request, and each response is written as a single line to standard output.
+/// Log messages go to standard error, so they do not interfere with the responses.
+pub async fn run_stdio(server: Server) -> Result<(), String> {
+	let stdin = std::io::stdin();
+	let mut stdout = std::io::stdout();
+	for line in stdin.lock().lines() {
+		let line = line.map_err(|e| format!("failed to read from standard input: {}", e))?;
+		if line.trim().is_empty() {
+			continue;
+		}
+		let response = server.handle_rpc(&line).await;
+		writeln!(stdout, "{}", response)
+			.and_then(|()| stdout.flush())
+			.map_err(|e| format!("failed to write to standard output: {}", e))?;
+	}
+	Ok(())
+}
+
+/// Plan the synchronization of the configured hour logs of a profile.
+async fn plan_profile(profile: &Profile, reader: &import::Reader, period: &PartialDate, options: &SyncOptions) -> Result<SyncPlan, Failure> {
+	let files = crate::configured_hour_logs(&profile.config)
+		.map_err(failure)?;
+	crate::plan_sync(&profile.api, &files, reader, &profile.config, period, options).await
+		.map_err(failure)
+}
+
+/// Get the response for a failed synchronization.
+fn failure(error: Error) -> Failure {
+	match error {
+		Error::Failed(message) => Failure::new(StatusCode::UNPROCESSABLE_ENTITY, message),
+		Error::PartialFailure(message) => Failure::new(StatusCode::INTERNAL_SERVER_ERROR, message),
+	}
+}
+
+/// Get the parameters of an HTTP request from the query string.
+fn parse_query(query: &str) -> Result<Value, Failure> {
+	let mut params = serde_json::Map::new();
+	for (key, value) in query.split('&').filter(|x| !x.is_empty()).map(|x| x.split_once('=').unwrap_or((x, ""))) {
+		let value = urlencoding::decode(value)
+			.map_err(|e| Failure::bad_request(format!("invalid query parameter {}: {}", key, e)))?;
+		params.insert(key.to_owned(), Value::String(value.into_owned()));
+	}
+	Ok(Value::Object(params))
+}
+
+/// Get an optional string parameter of a request.
+fn string_param<'a>(params: &'a Value, name: &str) -> Result<Option<&'a str>, Failure> {
+	match params.get(name) {
+		None | Some(Value::Null) => Ok(None),
+		Some(Value::String(value)) => Ok(Some(value)),
+		Some(_) => Err(Failure::bad_request(format!("invalid parameter {}: expected a string", name))),
+	}
+}
+
+/// Get a JSON-RPC error response.
+fn rpc_error(id: Value, code: i32, message: impl Into<String>) -> Value {
+	json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message.into() } })
+}
+
+/// Get the JSON representation of an entry from an hour log.
+fn entry_json(entry: &Entry) -> Value {
+	json!({
+		"date": crate::timezone::to_naive_date(entry.date),
+		"seconds": entry.seconds,
+		"tags": entry.tags,
+		"description": entry.description,
+	})
+}
+
+/// Get the JSON representation of a synchronization plan.
+fn plan_json(plan: &SyncPlan, config: &Config) -> Value {
+	let timezone = plan.timezone;
+	let deletions: Vec<_> = plan.deletions.iter()
+		.map(|entry| json!({
+			"id": entry.id,
+			"date": entry.local_date(timezone),
+			"seconds": entry.total_seconds(),
+			"task_id": entry.task_id,
+			"description": entry.description,
+		}))
+		.collect();
+	let updates: Vec<_> = plan.updates.iter()
+		.map(|(old_entry, new_entry)| json!({
+			"id": old_entry.id,
+			"date": old_entry.local_date(timezone),
+			"seconds": old_entry.total_seconds(),
+			"task_id": old_entry.task_id,
+			"old_description": old_entry.description,
+			"description": crate::upload_description(new_entry, config),
+		}))
+		.collect();
+	let additions: Vec<_> = plan.additions.iter()
+		.map(|(entry, task_id)| json!({
+			"date": crate::timezone::to_naive_date(entry.date),
+			"seconds": entry.seconds,
+			"task_id": task_id,
+			"description": crate::upload_description(entry, config),
+		}))
+		.collect();
+	let unmapped: Vec<_> = plan.unmapped.iter()
+		.map(|(entry, reason)| json!({ "entry": entry_json(entry), "reason": reason }))
+		.collect();
+	json!({
+		"user_id": plan.user.id,
+		"timezone": plan.timezone.name(),
+		"deletions": deletions,
+		"updates": updates,
+		"additions": additions,
+		"unmapped": unmapped,
+	})
+}
+
+fn json_response(status: StatusCode, body: &Value) -> Response<Body> {
+	Response::builder()
+		.status(status)
+		.header(hyper::header::CONTENT_TYPE, "application/json")
+		.body(Body::from(body.to_string()))
+		.unwrap()
+}
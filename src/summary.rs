@@ -0,0 +1,325 @@
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::entry::Entry;
+use crate::report::format_seconds;
+use crate::types;
+use crate::workspace::Workspace;
+
+/// The logged hours of a period, aggregated per client, project, task and day.
+///
+/// This is the data available to templates for --render.
+/// Durations are given in seconds and formatted as `H:MM`.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+	pub period: Period,
+	pub user: String,
+	pub seconds: u64,
+	pub hours: String,
+	pub billable_seconds: u64,
+	pub billable_hours: String,
+
+	/// The clients with hours in the period, sorted by name.
+	pub clients: Vec<ClientSummary>,
+
+	/// All days of the period, including days without hours.
+	pub days: Vec<DaySummary>,
+
+	/// The entries in the configured hour logs that can not be mapped to a task, grouped by tags.
+	pub unmapped: Vec<UnmappedSummary>,
+}
+
+/// The period of a summary.
+#[derive(Debug, Serialize)]
+pub struct Period {
+	/// The period as given on the command line, like `2024-03` or `last-month`.
+	pub name: String,
+
+	pub first_day: NaiveDate,
+	pub last_day: NaiveDate,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClientSummary {
+	pub id: Option<u64>,
+	pub name: String,
+	pub seconds: u64,
+	pub hours: String,
+	pub projects: Vec<ProjectSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectSummary {
+	pub id: Option<u64>,
+	pub name: String,
+	pub seconds: u64,
+	pub hours: String,
+	pub tasks: Vec<TaskSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskSummary {
+	pub id: u64,
+	pub name: String,
+	pub billable: bool,
+	pub seconds: u64,
+	pub hours: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DaySummary {
+	pub date: NaiveDate,
+
+	/// The full English name of the weekday.
+	pub weekday: String,
+
+	pub seconds: u64,
+	pub hours: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnmappedSummary {
+	/// The tags of the entries, separated by commas.
+	pub tags: String,
+
+	/// Why the tags can not be mapped to a task.
+	pub reason: String,
+
+	pub entries: usize,
+	pub seconds: u64,
+	pub hours: String,
+}
+
+impl UnmappedSummary {
+	/// Group unmapped entries by their tags.
+	pub fn group(unmapped: &[(Entry, String)]) -> Vec<Self> {
+		let mut groups = BTreeMap::<String, Self>::new();
+		for (entry, reason) in unmapped {
+			let tags = entry.tags.join(", ");
+			let group = groups.entry(tags.clone()).or_insert_with(|| Self {
+				tags,
+				reason: reason.clone(),
+				entries: 0,
+				seconds: 0,
+				hours: String::new(),
+			});
+			group.entries += 1;
+			group.seconds += u64::from(entry.seconds);
+		}
+		groups.into_values()
+			.map(|x| Self { hours: format_seconds(x.seconds), ..x })
+			.collect()
+	}
+}
+
+impl Summary {
+	/// Aggregate the time entries of a period.
+	///
+	/// Entries for tasks that are not in the workspace are grouped under an unknown client and project.
+	pub fn new(
+		name: &str,
+		period: &std::ops::Range<uurlog::Date>,
+		user: &types::User,
+		entries: &[types::TimeEntry],
+		workspace: &Workspace,
+		timezone: chrono_tz::Tz,
+	) -> Self {
+		let mut per_task = BTreeMap::<u64, u64>::new();
+		let mut per_day = BTreeMap::<NaiveDate, u64>::new();
+		for entry in entries {
+			let duration = u64::from(entry.total_seconds());
+			*per_task.entry(entry.task_id).or_default() += duration;
+			if let Some(date) = entry.local_date(timezone) {
+				*per_day.entry(date).or_default() += duration;
+			}
+		}
+
+		// Group the tasks per client and project, by ID so that clients or projects with the same name are kept apart.
+		let mut clients = BTreeMap::<Option<u64>, BTreeMap<Option<u64>, Vec<TaskSummary>>>::new();
+		for (&task_id, &seconds) in &per_task {
+			let task = workspace.tasks.get(&task_id);
+			let project_id = task.map(|x| x.project_id);
+			let client_id = workspace.project_of_task(task_id).map(|x| x.client_id);
+			clients.entry(client_id).or_default().entry(project_id).or_default().push(TaskSummary {
+				id: task_id,
+				name: task.map(|x| x.name.clone()).unwrap_or_else(|| String::from("unknown task")),
+				billable: task.map(|x| x.billable).unwrap_or(false),
+				seconds,
+				hours: format_seconds(seconds),
+			});
+		}
+
+		let mut clients: Vec<_> = clients.into_iter()
+			.map(|(client_id, projects)| {
+				let mut projects: Vec<_> = projects.into_iter()
+					.map(|(project_id, mut tasks)| {
+						tasks.sort_by(|a, b| a.name.cmp(&b.name));
+						let seconds = tasks.iter().map(|x| x.seconds).sum();
+						ProjectSummary {
+							id: project_id,
+							name: project_id.and_then(|x| workspace.projects.get(&x)).map(|x| x.name.clone()).unwrap_or_else(|| String::from("unknown project")),
+							seconds,
+							hours: format_seconds(seconds),
+							tasks,
+						}
+					})
+					.collect();
+				projects.sort_by(|a, b| a.name.cmp(&b.name));
+				let seconds = projects.iter().map(|x| x.seconds).sum();
+				ClientSummary {
+					id: client_id,
+					name: client_id.and_then(|x| workspace.clients.get(&x)).map(|x| x.name.clone()).unwrap_or_else(|| String::from("unknown client")),
+					seconds,
+					hours: format_seconds(seconds),
+					projects,
+				}
+			})
+			.collect();
+		clients.sort_by(|a, b| a.name.cmp(&b.name));
+
+		let days = crate::calendar::dates(period)
+			.map(|date| {
+				let seconds = per_day.get(&date).copied().unwrap_or(0);
+				DaySummary {
+					date,
+					weekday: date.format("%A").to_string(),
+					seconds,
+					hours: format_seconds(seconds),
+				}
+			})
+			.collect();
+
+		let seconds = per_task.values().sum();
+		let billable_seconds = per_task.iter()
+			.filter(|(task_id, _)| workspace.tasks.get(task_id).map(|x| x.billable).unwrap_or(false))
+			.map(|(_, seconds)| seconds)
+			.sum();
+		let first_day = crate::timezone::to_naive_date(period.start);
+		let last_day = crate::timezone::to_naive_date(period.end).pred_opt().unwrap_or(first_day);
+		Self {
+			period: Period {
+				name: name.to_owned(),
+				first_day,
+				last_day,
+			},
+			user: user.name.clone(),
+			seconds,
+			hours: format_seconds(seconds),
+			billable_seconds,
+			billable_hours: format_seconds(billable_seconds),
+			clients,
+			days,
+			unmapped: Vec::new(),
+		}
+	}
+}
+
+/// The format of a report.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ReportFormat {
+	/// Plain text, for reading in a terminal.
+	Text,
+
+	/// A standalone HTML page with tables and bar charts.
+	Html,
+
+	/// Markdown tables, for pasting in a wiki or pull request.
+	Markdown,
+}
+
+impl std::str::FromStr for ReportFormat {
+	type Err = String;
+
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		match data {
+			"text" => Ok(Self::Text),
+			"html" => Ok(Self::Html),
+			"markdown" => Ok(Self::Markdown),
+			_ => Err(format!("unknown report format: {}, expected text, html or markdown", data)),
+		}
+	}
+}
+
+/// The template for HTML reports.
+const HTML_REPORT: &str = include_str!("report.html.tera");
+
+/// The template for Markdown reports.
+const MARKDOWN_REPORT: &str = include_str!("report.md.tera");
+
+/// Format a summary as a report.
+pub fn report(summary: &Summary, format: ReportFormat) -> Result<String, String> {
+	match format {
+		ReportFormat::Text => Ok(text_report(summary)),
+		ReportFormat::Html => render_template(HTML_REPORT, true, summary)
+			.map_err(|e| format!("failed to render HTML report: {}", e)),
+		ReportFormat::Markdown => render_template(MARKDOWN_REPORT, false, summary)
+			.map_err(|e| format!("failed to render Markdown report: {}", e)),
+	}
+}
+
+/// Format a summary as plain text.
+fn text_report(summary: &Summary) -> String {
+	use std::fmt::Write;
+
+	let mut output = String::new();
+	writeln!(output, "Hours of {} for {} ({} to {})", summary.user, summary.period.name, summary.period.first_day, summary.period.last_day).unwrap();
+	for client in &summary.clients {
+		writeln!(output).unwrap();
+		writeln!(output, "{:<60} {:>8}", client.name, client.hours).unwrap();
+		for project in &client.projects {
+			writeln!(output, "  {:<58} {:>8}", project.name, project.hours).unwrap();
+			for task in &project.tasks {
+				writeln!(output, "    {:<56} {:>8}", task.name, task.hours).unwrap();
+			}
+		}
+	}
+	writeln!(output).unwrap();
+	for day in summary.days.iter().filter(|x| x.seconds > 0) {
+		writeln!(output, "{} {:<49} {:>8}", day.date, day.weekday, day.hours).unwrap();
+	}
+	writeln!(output).unwrap();
+	writeln!(output, "{:<60} {:>8}", "Billable", summary.billable_hours).unwrap();
+	writeln!(output, "{:<60} {:>8}", "Total", summary.hours).unwrap();
+	if !summary.unmapped.is_empty() {
+		writeln!(output).unwrap();
+		writeln!(output, "Unmapped tags in the hour logs:").unwrap();
+		for x in &summary.unmapped {
+			writeln!(output, "  {:<58} {:>8}  ({} entries, {})", x.tags, x.hours, x.entries, x.reason).unwrap();
+		}
+	}
+	output
+}
+
+/// Render a template file with a summary.
+///
+/// Templates use the Tera syntax, and the fields of [`Summary`] are available as variables.
+/// Templates with a name ending in `.html` or `.htm` (before an optional `.tera` extension) get HTML escaping.
+pub fn render(template_path: &std::path::Path, summary: &Summary) -> Result<String, String> {
+	let template = std::fs::read_to_string(template_path)
+		.map_err(|e| format!("failed to read template {}: {}", template_path.display(), e))?;
+	let name = template_path.file_name().map(|x| x.to_string_lossy()).unwrap_or_default();
+	let name = name.strip_suffix(".tera").unwrap_or(&name);
+	let autoescape = name.ends_with(".html") || name.ends_with(".htm");
+	render_template(&template, autoescape, summary)
+		.map_err(|e| format!("failed to render template {}: {}", template_path.display(), e))
+}
+
+/// Render a template with a summary.
+fn render_template(template: &str, autoescape: bool, summary: &Summary) -> Result<String, String> {
+	let context = tera::Context::from_serialize(summary)
+		.map_err(|e| format!("failed to prepare template data: {}", e))?;
+	tera::Tera::one_off(template, &context, autoescape)
+		.map_err(|e| error_chain(&e))
+}
+
+/// Format an error with all its sources, since Tera puts the details of template errors in the sources.
+fn error_chain(error: &dyn std::error::Error) -> String {
+	let mut message = error.to_string();
+	let mut source = error.source();
+	while let Some(error) = source {
+		message += &format!(": {}", error);
+		source = error.source();
+	}
+	message
+}
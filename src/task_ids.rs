@@ -0,0 +1,262 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+/// How tags are normalized before looking up the task ID.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct TagNormalization {
+	/// Compare tags case insensitively.
+	pub ignore_case: bool,
+
+	/// Apply unicode normalization (NFKC) to tags.
+	pub unicode: bool,
+}
+
+impl TagNormalization {
+	/// Normalize a tag.
+	///
+	/// Leading and trailing whitespace is always removed.
+	pub fn normalize(&self, tag: &str) -> String {
+		use unicode_normalization::UnicodeNormalization;
+
+		let tag = tag.trim();
+		let tag = if self.unicode {
+			tag.nfkc().collect()
+		} else {
+			tag.to_string()
+		};
+		if self.ignore_case {
+			tag.to_lowercase()
+		} else {
+			tag
+		}
+	}
+}
+
+/// Selection of entries by their tags.
+#[derive(Debug, Clone, Default)]
+pub struct TagFilter {
+	/// Only select entries with at least one of these tags, unless empty.
+	pub only: Vec<String>,
+
+	/// Never select entries with any of these tags.
+	pub exclude: Vec<String>,
+}
+
+impl TagFilter {
+	/// Check if the filter selects all entries.
+	pub fn is_empty(&self) -> bool {
+		self.only.is_empty() && self.exclude.is_empty()
+	}
+
+	/// Check if the filter selects an entry with the given tags.
+	pub fn matches(&self, tags: &[String], normalization: TagNormalization) -> bool {
+		let tags: BTreeSet<_> = tags.iter().map(|x| normalization.normalize(x)).collect();
+		let only = self.only.is_empty() || self.only.iter().any(|x| tags.contains(&normalization.normalize(x)));
+		let exclude = self.exclude.iter().any(|x| tags.contains(&normalization.normalize(x)));
+		only && !exclude
+	}
+}
+
+/// What to do with entries that can not be mapped to a task.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum UnmappedPolicy {
+	/// Skip the entries, and list them at info level.
+	Skip,
+
+	/// Skip the entries, and list them as warnings.
+	Warn,
+
+	/// Refuse to continue.
+	Error,
+}
+
+impl std::fmt::Display for UnmappedPolicy {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::Skip => f.write_str("skip"),
+			Self::Warn => f.write_str("warn"),
+			Self::Error => f.write_str("error"),
+		}
+	}
+}
+
+impl std::str::FromStr for UnmappedPolicy {
+	type Err = String;
+
+	fn from_str(data: &str) -> Result<Self, Self::Err> {
+		match data {
+			"skip" => Ok(Self::Skip),
+			"warn" => Ok(Self::Warn),
+			"error" => Ok(Self::Error),
+			_ => Err(format!("unknown unmapped entry mode: {}, expected skip, warn or error", data)),
+		}
+	}
+}
+
+/// Mapping from tags to task IDs.
+///
+/// A task can be mapped from a single tag, or from a combination of tags.
+/// Combinations take precedence over single tags.
+///
+/// Tags can also be mapped to a project with a default task,
+/// which is used when no other mapping matches.
+#[derive(Debug, Clone)]
+pub struct TaskIds {
+	tags: BTreeMap<String, u64>,
+	combinations: BTreeMap<BTreeSet<String>, u64>,
+	projects: BTreeMap<String, u64>,
+	default_tasks: BTreeMap<u64, u64>,
+	normalization: TagNormalization,
+}
+
+impl TaskIds {
+	/// Create an empty mapping.
+	pub fn new(normalization: TagNormalization) -> Self {
+		Self {
+			tags: BTreeMap::new(),
+			combinations: BTreeMap::new(),
+			projects: BTreeMap::new(),
+			default_tasks: BTreeMap::new(),
+			normalization,
+		}
+	}
+
+	/// Map a single tag to a task ID.
+	pub fn insert_tag(&mut self, tag: &str, task_id: u64) -> Result<(), String> {
+		use std::collections::btree_map::Entry;
+
+		let normalized = self.normalization.normalize(tag);
+		match self.tags.entry(normalized) {
+			Entry::Vacant(x) => {
+				x.insert(task_id);
+				Ok(())
+			},
+			Entry::Occupied(x) if x.key() != tag => {
+				Err(format!("duplicate tag: {} (normalized to {})", tag, x.key()))
+			},
+			Entry::Occupied(_) => {
+				Err(format!("duplicate tag: {}", tag))
+			},
+		}
+	}
+
+	/// Map a combination of tags to a task ID.
+	pub fn insert_combination(&mut self, tags: &[String], task_id: u64) -> Result<(), String> {
+		use std::collections::btree_map::Entry;
+
+		let normalized: BTreeSet<_> = tags.iter().map(|x| self.normalization.normalize(x)).collect();
+		if normalized.len() < 2 {
+			return Err(format!("tag combination must contain at least two different tags: [{}]", tags.join(", ")));
+		}
+		match self.combinations.entry(normalized) {
+			Entry::Vacant(x) => {
+				x.insert(task_id);
+				Ok(())
+			},
+			Entry::Occupied(_) => {
+				Err(format!("duplicate tag combination: [{}]", tags.join(", ")))
+			},
+		}
+	}
+
+	/// Map a tag to a project ID.
+	pub fn insert_project(&mut self, tag: &str, project_id: u64) -> Result<(), String> {
+		let normalized = self.normalization.normalize(tag);
+		if self.projects.insert(normalized, project_id).is_some() {
+			return Err(format!("duplicate project tag: {}", tag));
+		}
+		Ok(())
+	}
+
+	/// Set the default task for a project.
+	///
+	/// The default task is used for entries with a project tag that do not match any other mapping.
+	pub fn insert_default_task(&mut self, project_id: u64, task_id: u64) {
+		self.default_tasks.insert(project_id, task_id);
+	}
+
+	/// Get all task IDs that tags can map to.
+	pub fn all_task_ids(&self) -> BTreeSet<u64> {
+		self.tags.values()
+			.chain(self.combinations.values())
+			.chain(self.default_tasks.values())
+			.copied()
+			.collect()
+	}
+
+	/// Get the project IDs of all project tags.
+	pub fn project_ids(&self) -> impl Iterator<Item = u64> + '_ {
+		self.projects.values().copied()
+	}
+
+	/// Get the task ID for a single tag.
+	pub fn get(&self, tag: &str) -> Option<u64> {
+		self.tags.get(&self.normalization.normalize(tag)).copied()
+	}
+
+	/// Determine the task ID for the tags of an entry.
+	///
+	/// If the tags match one or more combinations, the combination with the most tags is used.
+	/// Otherwise, the entry must have exactly one tag which is mapped to a task.
+	/// If that also fails, the default task of the project of a project tag is used.
+	pub fn resolve(&self, tags: &[String]) -> Result<u64, String> {
+		self.resolve_task(tags).or_else(|e| self.resolve_default_task(tags).ok_or(e))
+	}
+
+	/// Determine the task ID for the tags of an entry, without falling back to default tasks.
+	fn resolve_task(&self, tags: &[String]) -> Result<u64, String> {
+		let normalized: BTreeSet<_> = tags.iter().map(|x| self.normalization.normalize(x)).collect();
+
+		// Find the most specific matching combination.
+		let mut best: Option<(&BTreeSet<String>, u64)> = None;
+		let mut ambiguous = false;
+		for (combination, &task_id) in &self.combinations {
+			if !combination.is_subset(&normalized) {
+				continue;
+			}
+			match best {
+				Some((x, _)) if x.len() > combination.len() => (),
+				Some((x, best_id)) if x.len() == combination.len() => ambiguous |= best_id != task_id,
+				_ => {
+					best = Some((combination, task_id));
+					ambiguous = false;
+				},
+			}
+		}
+
+		if let Some((combination, task_id)) = best {
+			if ambiguous {
+				return Err(format!("multiple tag combinations with {} tags match, unable to determine project/task", combination.len()));
+			}
+			return Ok(task_id);
+		}
+
+		match tags {
+			[] => Err("entry has no tags, unable to determine project/task".into()),
+			[tag] => self.get(tag).ok_or_else(|| format!("unknown task ID for tag: {}", tag)),
+			_ => Err("entry has multiple tags and no matching tag combination, unable to determine project/task".into()),
+		}
+	}
+
+	/// Determine the project for the project tags of an entry.
+	///
+	/// Returns [`None`] if the tags do not map to exactly one project.
+	pub fn resolve_project(&self, tags: &[String]) -> Option<u64> {
+		let mut project_ids: Vec<_> = tags.iter()
+			.filter_map(|x| self.projects.get(&self.normalization.normalize(x)))
+			.collect();
+		project_ids.sort();
+		project_ids.dedup();
+		match project_ids.as_slice() {
+			[project_id] => Some(**project_id),
+			_ => None,
+		}
+	}
+
+	/// Determine the default task for the project tags of an entry.
+	///
+	/// Returns [`None`] if the tags do not map to exactly one project with a default task.
+	fn resolve_default_task(&self, tags: &[String]) -> Option<u64> {
+		self.resolve_project(tags).and_then(|x| self.default_tasks.get(&x).copied())
+	}
+}
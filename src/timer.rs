@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A running timer, stored in the timer state file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Timer {
+	/// The task to log the time on.
+	pub task_id: u64,
+
+	/// The moment the timer was started.
+	pub start: DateTime<Utc>,
+
+	/// The description of the entry.
+	pub description: String,
+
+	/// The ID of the running timer entry on Paymo.
+	///
+	/// If there is no entry, the timer only runs locally and the entry is created when the timer is stopped.
+	pub entry_id: Option<u64>,
+}
+
+/// Get the default path of the timer state file.
+///
+/// Uses `$XDG_STATE_HOME/uurlog-paymo/timer.json`, falling back to `$HOME/.local/state/uurlog-paymo/timer.json`.
+pub fn default_path() -> Option<PathBuf> {
+	Some(state_dir()?.join("timer.json"))
+}
+
+/// Get the directory for state files.
+///
+/// Uses `$XDG_STATE_HOME/uurlog-paymo`, falling back to `$HOME/.local/state/uurlog-paymo`.
+pub fn state_dir() -> Option<PathBuf> {
+	let state_dir = match std::env::var_os("XDG_STATE_HOME") {
+		Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+		_ => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+	};
+	Some(state_dir.join("uurlog-paymo"))
+}
+
+/// Read the running timer from the state file, if there is one.
+pub fn read(path: &Path) -> Result<Option<Timer>, String> {
+	match std::fs::read(path) {
+		Ok(data) => serde_json::from_slice(&data).map(Some).map_err(|e| e.to_string()),
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(e.to_string()),
+	}
+}
+
+/// Write the running timer to the state file.
+pub fn write(path: &Path, timer: &Timer) -> Result<(), String> {
+	if let Some(parent) = path.parent() {
+		std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+	}
+	let data = serde_json::to_vec_pretty(timer).map_err(|e| e.to_string())?;
+	std::fs::write(path, data).map_err(|e| e.to_string())
+}
+
+/// Remove the state file after the timer is stopped.
+pub fn remove(path: &Path) -> Result<(), String> {
+	std::fs::remove_file(path).map_err(|e| e.to_string())
+}
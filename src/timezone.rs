@@ -0,0 +1,85 @@
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Convert a date from an hour log to a chrono date.
+pub fn to_naive_date(date: uurlog::Date) -> NaiveDate {
+	NaiveDate::from_ymd_opt(date.year().to_number().into(), date.month().to_number().into(), date.day().into())
+		.expect("uurlog::Date is always a valid date")
+}
+
+/// Get the first moment of a date in a timezone, as UTC time.
+pub fn start_of_day(date: uurlog::Date, timezone: Tz) -> DateTime<Utc> {
+	local_to_utc(to_naive_date(date).and_hms_opt(0, 0, 0).unwrap(), timezone)
+}
+
+/// Convert a local date and time to UTC time.
+pub fn local_time_to_utc(date: uurlog::Date, time: NaiveTime, timezone: Tz) -> DateTime<Utc> {
+	local_to_utc(to_naive_date(date).and_time(time), timezone)
+}
+
+/// Convert a local timestamp in a timezone to UTC time.
+fn local_to_utc(local: NaiveDateTime, timezone: Tz) -> DateTime<Utc> {
+	if let Some(x) = timezone.from_local_datetime(&local).earliest() {
+		return x.with_timezone(&Utc);
+	}
+
+	// The local time does not exist in the timezone (DST transition), so use the UTC offset after the transition.
+	// The time an hour later is past the gap, and has that offset.
+	let hour = chrono::Duration::hours(1);
+	match timezone.from_local_datetime(&(local + hour)).earliest() {
+		Some(x) => x.with_timezone(&Utc) - hour,
+		None => Utc.from_utc_datetime(&local),
+	}
+}
+
+/// Parse the timezone of a Paymo user.
+///
+/// Falls back to UTC if the timezone is not recognized.
+pub fn parse_user_timezone(timezone: &str) -> Tz {
+	timezone.parse().unwrap_or_else(|_| {
+		log::warn!("unknown timezone {:?}, using UTC instead", timezone);
+		Tz::UTC
+	})
+}
+
+/// Get the local date of a timestamp from the Paymo API.
+pub fn local_date(timestamp: DateTime<Utc>, timezone: Tz) -> NaiveDate {
+	timestamp.with_timezone(&timezone).date_naive()
+}
+
+/// Convert a chrono date to a date for an hour log entry.
+///
+/// Fails for dates with a year that does not fit in an hour log date.
+pub fn from_naive_date(date: NaiveDate) -> Result<uurlog::Date, String> {
+	use std::convert::TryFrom;
+	let year = i16::try_from(date.year())
+		.map_err(|_| format!("invalid date {}: the year is out of range", date))?;
+	let month = uurlog::gregorian::Month::new(date.month() as u8)
+		.map_err(|e| format!("invalid date {}: {}", date, e))?;
+	uurlog::Date::new(year, month, date.day() as u8)
+		.map_err(|e| format!("invalid date {}: {}", date, e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn local_to_utc_in_dst_gap() {
+		// Clocks in Amsterdam jump from 02:00 to 03:00 on the last Sunday of March, so 02:30 does not exist.
+		let local = NaiveDate::from_ymd_opt(2026, 3, 29).unwrap().and_hms_opt(2, 30, 0).unwrap();
+		let expected = Utc.with_ymd_and_hms(2026, 3, 29, 0, 30, 0).unwrap();
+		assert_eq!(local_to_utc(local, chrono_tz::Europe::Amsterdam), expected);
+	}
+
+	#[test]
+	fn local_to_utc_outside_dst_gap() {
+		let local = NaiveDate::from_ymd_opt(2026, 3, 29).unwrap().and_hms_opt(1, 30, 0).unwrap();
+		let expected = Utc.with_ymd_and_hms(2026, 3, 29, 0, 30, 0).unwrap();
+		assert_eq!(local_to_utc(local, chrono_tz::Europe::Amsterdam), expected);
+
+		let local = NaiveDate::from_ymd_opt(2026, 3, 29).unwrap().and_hms_opt(3, 30, 0).unwrap();
+		let expected = Utc.with_ymd_and_hms(2026, 3, 29, 1, 30, 0).unwrap();
+		assert_eq!(local_to_utc(local, chrono_tz::Europe::Amsterdam), expected);
+	}
+}
@@ -1,16 +1,176 @@
+//! Types returned by the Paymo API.
+//!
+//! Unknown fields are ignored, so new fields added by Paymo do not break deserialization.
+//! Fields that are not essential accept `null` or a missing value and fall back to a default.
+//!
+//! All types are `#[non_exhaustive]`, so that fields for new Paymo features can be added without breaking users of these types.
+
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
+/// Deserialize a value, using the default value if it is `null`.
+///
+/// Combined with `#[serde(default)]`, this also accepts missing fields.
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+	D: serde::Deserializer<'de>,
+	T: Default + Deserialize<'de>,
+{
+	Ok(Option::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Define an enum for a string field with known values, and a fallback for unknown values.
+macro_rules! string_enum {
+	($(#[$meta:meta])* $name:ident { $($(#[$variant_meta:meta])* $variant:ident = $value:literal,)* }) => {
+		$(#[$meta])*
+		#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+		#[serde(from = "String", into = "String")]
+		#[non_exhaustive]
+		pub enum $name {
+			$($(#[$variant_meta])* $variant,)*
+
+			/// A value not known to this version of the API client.
+			Other(String),
+		}
+
+		impl $name {
+			/// Get the value as used by the Paymo API.
+			pub fn as_str(&self) -> &str {
+				match self {
+					$(Self::$variant => $value,)*
+					Self::Other(x) => x,
+				}
+			}
+		}
+
+		impl From<String> for $name {
+			fn from(value: String) -> Self {
+				match value.as_str() {
+					$($value => Self::$variant,)*
+					_ => {
+						log::warn!("unknown {} from Paymo: {:?}", stringify!($name), value);
+						Self::Other(value)
+					},
+				}
+			}
+		}
+
+		impl From<$name> for String {
+			fn from(value: $name) -> Self {
+				match value {
+					$name::Other(x) => x,
+					x => x.as_str().into(),
+				}
+			}
+		}
+
+		impl Default for $name {
+			fn default() -> Self {
+				Self::Other(String::new())
+			}
+		}
+
+		impl std::fmt::Display for $name {
+			fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+				f.write_str(self.as_str())
+			}
+		}
+	};
+}
+
+string_enum! {
+	/// The type of a user account.
+	UserType {
+		/// An administrator of the company.
+		Admin = "Admin",
+
+		/// A regular employee.
+		Employee = "Employee",
+
+		/// A guest with limited access.
+		Guest = "Guest",
+	}
+}
+
+string_enum! {
+	/// The first day of the week.
+	WeekStart {
+		Sunday = "0",
+		Monday = "1",
+		Tuesday = "2",
+		Wednesday = "3",
+		Thursday = "4",
+		Friday = "5",
+		Saturday = "6",
+	}
+}
+
+impl WeekStart {
+	/// Get the first day of the week, if it is known.
+	pub fn weekday(&self) -> Option<chrono::Weekday> {
+		match self {
+			Self::Sunday => Some(chrono::Weekday::Sun),
+			Self::Monday => Some(chrono::Weekday::Mon),
+			Self::Tuesday => Some(chrono::Weekday::Tue),
+			Self::Wednesday => Some(chrono::Weekday::Wed),
+			Self::Thursday => Some(chrono::Weekday::Thu),
+			Self::Friday => Some(chrono::Weekday::Fri),
+			Self::Saturday => Some(chrono::Weekday::Sat),
+			Self::Other(_) => None,
+		}
+	}
+}
+
+string_enum! {
+	/// The format for displaying dates, in PHP date format syntax.
+	DateFormat {
+		YearMonthDay = "Y-m-d",
+		DaySlashMonthSlashYear = "d/m/Y",
+		MonthSlashDaySlashYear = "m/d/Y",
+		DayDotMonthDotYear = "d.m.Y",
+	}
+}
+
+impl DateFormat {
+	/// Get the equivalent `chrono` format string, if the format is known.
+	pub fn strftime(&self) -> Option<&'static str> {
+		match self {
+			Self::YearMonthDay => Some("%Y-%m-%d"),
+			Self::DaySlashMonthSlashYear => Some("%d/%m/%Y"),
+			Self::MonthSlashDaySlashYear => Some("%m/%d/%Y"),
+			Self::DayDotMonthDotYear => Some("%d.%m.%Y"),
+			Self::Other(_) => None,
+		}
+	}
+}
+
+string_enum! {
+	/// The format for displaying times of day, in PHP date format syntax.
+	TimeFormat {
+		/// 24-hour clock.
+		Hours24 = "H:i",
+
+		/// 12-hour clock with AM and PM.
+		Hours12 = "h:i a",
+	}
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct User {
 	pub id: u64,
 	pub name: String,
 	pub email: String,
 	#[serde(rename = "type")]
-	pub kind: String,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub kind: UserType,
 	pub active: bool,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub timezone: String,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub phone: String,
 	pub skype: Option<String>,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub position: String,
 	pub workday_hours: Option<f64>,
 	pub price_per_hour: Option<f64>,
@@ -18,22 +178,35 @@ pub struct User {
 	pub image_thumb_large: Option<String>,
 	pub image_thumb_medium: Option<String>,
 	pub image_thumb_small: Option<String>,
-	pub date_format: String,
-	pub time_format: String,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub date_format: DateFormat,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub time_format: TimeFormat,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub decimal_sep: String,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub thousands_sep: String,
-	pub week_start: String,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub week_start: WeekStart,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub language: String,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub theme: String,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub assigned_projects: Vec<u64>,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub managed_projects: Vec<u64>,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub is_online: bool,
 	pub password: Option<String>,
-	pub created_on: String,
-	pub updated_on: String,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub created_on: DateTime<Utc>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub updated_on: DateTime<Utc>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct Client {
 	pub id: u64,
 	pub name: String,
@@ -49,65 +222,223 @@ pub struct Client {
 	pub image: Option<String>,
 	pub fiscal_information: Option<String>,
 	pub active: bool,
-	pub created_on: String,
-	pub updated_on: String,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub created_on: DateTime<Utc>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub updated_on: DateTime<Utc>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct Project {
 	pub id: u64,
 	pub name: String,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub code: String,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub task_code_increment: u64,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub description: String,
 	pub client_id: u64,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub status_id: u64,
 	pub active: bool,
 	pub budget_hours: Option<f64>,
 	pub price_per_hour: Option<f64>,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub billable: bool,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub color: String,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub users: Vec<u64>,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub managers: Vec<u64>,
-	pub created_on: String,
-	pub updated_on: String,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub created_on: DateTime<Utc>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub updated_on: DateTime<Utc>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct Task {
 	pub id: u64,
 	pub name: String,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub code: String,
 	pub project_id: u64,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub tasklist_id: u64,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub user_id: u64,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub complete: bool,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub billable: bool,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub seq: u64,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub description: String,
 	pub price_per_hour: Option<f64>,
-	pub due_date: Option<String>,
+	pub due_date: Option<NaiveDate>,
 	pub budget_hours: Option<f64>,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub users: Vec<u64>,
-	pub created_on: String,
-	pub updated_on: String,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub created_on: DateTime<Utc>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub updated_on: DateTime<Utc>,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct TimeEntry {
 	pub id: u64,
 	pub task_id: u64,
 	pub user_id: u64,
-	pub start_time: Option<String>,
-	pub end_time: Option<String>,
+	pub start_time: Option<DateTime<Utc>>,
+	pub end_time: Option<DateTime<Utc>>,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub description: String,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub added_manually: bool,
 	pub invoice_item_id: Option<u64>,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub billed: bool,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub is_bulk: bool,
 	pub project_id: u64,
+	#[serde(default, deserialize_with = "null_as_default")]
 	pub duration: u32,
-	pub date: Option<String>,
-	pub created_on: String,
-	pub updated_on: String,
+	pub date: Option<NaiveDate>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub created_on: DateTime<Utc>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub updated_on: DateTime<Utc>,
+}
+
+impl TimeEntry {
+	/// Get the date of the entry.
+	///
+	/// Entries without a date (such as entries created by the Paymo timer)
+	/// use the date of the start time in the given timezone.
+	pub fn local_date(&self, timezone: chrono_tz::Tz) -> Option<NaiveDate> {
+		match self.date {
+			Some(date) => Some(date),
+			None => Some(crate::timezone::local_date(self.start_time?, timezone)),
+		}
+	}
+
+	/// Get the duration of the entry in seconds.
+	///
+	/// If the entry has no duration but does have a start and end time, the duration is computed from those.
+	pub fn total_seconds(&self) -> u32 {
+		if self.duration != 0 {
+			return self.duration;
+		}
+
+		match (self.start_time, self.end_time) {
+			(Some(start), Some(end)) if end > start => (end - start).num_seconds() as u32,
+			_ => 0,
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Company {
+	pub id: u64,
+	pub name: String,
+	pub address: Option<String>,
+	pub phone: Option<String>,
+	pub email: Option<String>,
+	pub url: Option<String>,
+	pub fiscal_information: Option<String>,
+	pub country: Option<String>,
+	pub image: Option<String>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub timezone: String,
+	pub default_currency: Option<String>,
+	pub default_price_per_hour: Option<f64>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub date_format: DateFormat,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub time_format: TimeFormat,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub decimal_sep: String,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub thousands_sep: String,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub week_start: WeekStart,
+	pub workday_start: Option<String>,
+	pub workday_end: Option<String>,
+	pub working_days: Option<String>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub account_type: String,
+	pub max_users: Option<u64>,
+	pub current_users: Option<u64>,
+	pub max_projects: Option<u64>,
+	pub current_projects: Option<u64>,
+	pub max_invoices: Option<u64>,
+	pub current_invoices: Option<u64>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub created_on: DateTime<Utc>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub updated_on: DateTime<Utc>,
+}
+
+string_enum! {
+	/// The status of an invoice.
+	InvoiceStatus {
+		/// Not sent to the client yet, and still editable.
+		Draft = "draft",
+
+		Sent = "sent",
+		Viewed = "viewed",
+		Paid = "paid",
+		Void = "void",
+	}
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Invoice {
+	pub id: u64,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub number: String,
+	pub client_id: u64,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub status: InvoiceStatus,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub currency: String,
+	pub date: Option<NaiveDate>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub title: String,
+	pub subtotal: Option<f64>,
+	pub total: Option<f64>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub created_on: DateTime<Utc>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub updated_on: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Expense {
+	pub id: u64,
+	pub client_id: u64,
+	pub project_id: Option<u64>,
+	pub user_id: Option<u64>,
+	pub amount: f64,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub currency: String,
+	pub date: Option<NaiveDate>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub notes: String,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub invoiced: bool,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub created_on: DateTime<Utc>,
+	#[serde(default, deserialize_with = "null_as_default")]
+	pub updated_on: DateTime<Utc>,
 }
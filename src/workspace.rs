@@ -0,0 +1,73 @@
+use std::collections::BTreeMap;
+
+use crate::api_client::ApiClient;
+use crate::types;
+
+/// The clients, projects and tasks of a Paymo workspace.
+///
+/// Used to look up the task, project and client of time entries.
+pub struct Workspace {
+	pub clients: BTreeMap<u64, types::Client>,
+	pub projects: BTreeMap<u64, types::Project>,
+	pub tasks: BTreeMap<u64, types::Task>,
+}
+
+impl Workspace {
+	/// Get all clients, projects and tasks from the server.
+	pub async fn fetch(api: &ApiClient) -> Result<Self, String> {
+		let clients = api.get_clients().await?;
+		let projects = api.get_projects().await?;
+		let tasks = api.get_tasks().await?;
+		Ok(Self {
+			clients: clients.into_iter().map(|x| (x.id, x)).collect(),
+			projects: projects.into_iter().map(|x| (x.id, x)).collect(),
+			tasks: tasks.into_iter().map(|x| (x.id, x)).collect(),
+		})
+	}
+
+	/// Get the project of a task.
+	pub fn project_of_task(&self, task_id: u64) -> Option<&types::Project> {
+		self.projects.get(&self.tasks.get(&task_id)?.project_id)
+	}
+
+	/// Get the client of a task.
+	pub fn client_of_task(&self, task_id: u64) -> Option<&types::Client> {
+		self.clients.get(&self.project_of_task(task_id)?.client_id)
+	}
+
+	/// Get the price per hour for a task.
+	///
+	/// This is the price per hour of the task, its project, the user or the company, in that order.
+	pub fn hourly_rate(&self, task_id: u64, user: &types::User, company: &types::Company) -> Option<f64> {
+		self.tasks.get(&task_id).and_then(|x| x.price_per_hour)
+			.or_else(|| self.project_of_task(task_id).and_then(|x| x.price_per_hour))
+			.or(user.price_per_hour)
+			.or(company.default_price_per_hour)
+	}
+
+	/// Find tasks with a task, project or client name similar to a query.
+	///
+	/// Returns the matching tasks with a similarity score between 0 and 1, best matches first.
+	pub fn find_tasks(&self, query: &str, min_score: f64) -> Vec<(f64, &types::Task)> {
+		let query = query.to_lowercase();
+		let score = |name: &str| {
+			let name = name.to_lowercase();
+			if name.contains(&query) {
+				1.0
+			} else {
+				strsim::jaro_winkler(&query, &name)
+			}
+		};
+
+		let mut matches: Vec<_> = self.tasks.values()
+			.map(|task| {
+				let project = self.project_of_task(task.id).map(|x| score(&x.name)).unwrap_or(0.0);
+				let client = self.client_of_task(task.id).map(|x| score(&x.name)).unwrap_or(0.0);
+				(score(&task.name).max(project).max(client), task)
+			})
+			.filter(|(score, _)| *score >= min_score)
+			.collect();
+		matches.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+		matches
+	}
+}
@@ -0,0 +1,121 @@
+//! Synchronize an hour log to the mock server, running the binary like a user would.
+
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output};
+
+/// A mock server running in a child process, killed when dropped.
+struct MockServer {
+	child: Child,
+	api_root: String,
+}
+
+impl MockServer {
+	/// Start the mock server with the example data and wait until it accepts connections.
+	fn start() -> Self {
+		let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+		let child = Command::new(env!("CARGO_BIN_EXE_uurlog-paymo"))
+			.arg("--mock-server")
+			.arg(address.to_string())
+			.spawn()
+			.unwrap();
+		let server = Self {
+			child,
+			api_root: format!("http://{}/api", address),
+		};
+		for _ in 0..100 {
+			if std::net::TcpStream::connect(address).is_ok() {
+				return server;
+			}
+			std::thread::sleep(std::time::Duration::from_millis(50));
+		}
+		panic!("mock server did not start on {}", address);
+	}
+}
+
+impl Drop for MockServer {
+	fn drop(&mut self) {
+		let _ = self.child.kill();
+		let _ = self.child.wait();
+	}
+}
+
+/// Create an empty directory for the files of a test.
+fn test_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("uurlog-paymo-test-{}-{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(&dir).unwrap();
+	dir
+}
+
+/// Run the binary against the mock server with the configuration and token in a test directory.
+///
+/// The journal and other state are kept in the test directory too.
+fn run(server: &MockServer, dir: &Path, args: &[&str]) -> Output {
+	let output = Command::new(env!("CARGO_BIN_EXE_uurlog-paymo"))
+		.arg("--config")
+		.arg(dir.join("config.toml"))
+		.arg("--token")
+		.arg(dir.join("token"))
+		.arg("--journal")
+		.arg(dir.join("journal.jsonl"))
+		.arg("--api-root")
+		.arg(&server.api_root)
+		.args(args)
+		.env("LC_ALL", "C")
+		.env("XDG_STATE_HOME", dir.join("state"))
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "command failed: {}", String::from_utf8_lossy(&output.stderr));
+	output
+}
+
+#[test]
+fn sync_to_mock_server() {
+	let dir = test_dir("sync");
+	std::fs::write(dir.join("token"), "mock-token\n").unwrap();
+	std::fs::write(dir.join("config.toml"), "[[Task]]\nname = \"foo\"\nid = 1\n").unwrap();
+	std::fs::write(
+		dir.join("hours.csv"),
+		"date,duration,description,tags\n2026-01-05,1:30,sign in (draft),foo\n2026-01-06,0:45,\"say \"\"hi\"\"\",foo\n",
+	).unwrap();
+	let hours = dir.join("hours.csv");
+	let hours = hours.to_str().unwrap();
+
+	let server = MockServer::start();
+	run(&server, &dir, &["--input-format", "csv", "--period", "2026-01", "--sync", hours]);
+
+	let output = run(&server, &dir, &["--period", "2026-01", "--list-entries"]);
+	let listing = String::from_utf8_lossy(&output.stdout);
+	assert!(listing.contains("sign in (draft)"), "missing entry in listing:\n{}", listing);
+	assert!(listing.contains("say \"hi\""), "missing entry in listing:\n{}", listing);
+
+	// A second synchronization finds the entries already on the server.
+	let output = run(&server, &dir, &["--input-format", "csv", "--period", "2026-01", "--sync", hours, "--dry-run"]);
+	let diff = String::from_utf8_lossy(&output.stdout);
+	assert_eq!(diff.trim(), "No changes.");
+
+	let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn sync_clock_times_that_differ_from_logged_hours() {
+	let dir = test_dir("clock-times");
+	std::fs::write(dir.join("token"), "mock-token\n").unwrap();
+	std::fs::write(dir.join("config.toml"), "[csv]\nstart = \"start\"\nend = \"end\"\n\n[[Task]]\nname = \"foo\"\nid = 1\n").unwrap();
+	std::fs::write(
+		dir.join("hours.csv"),
+		"date,duration,start,end,description,tags\n2026-01-07,1:00,09:00,10:30,meeting,foo\n",
+	).unwrap();
+	let hours = dir.join("hours.csv");
+	let hours = hours.to_str().unwrap();
+
+	let server = MockServer::start();
+	run(&server, &dir, &["--input-format", "csv", "--period", "2026-01", "--sync", hours]);
+
+	// The entry is uploaded with the clock times, and matched with the same duration next time.
+	let output = run(&server, &dir, &["--input-format", "csv", "--period", "2026-01", "--sync", hours, "--dry-run"]);
+	let diff = String::from_utf8_lossy(&output.stdout);
+	assert_eq!(diff.trim(), "No changes.");
+
+	let _ = std::fs::remove_dir_all(&dir);
+}